@@ -2,5 +2,10 @@ CREATE TABLE notes (
     id SERIAL PRIMARY KEY,
     title TEXT NOT NULL,
     content TEXT NOT NULL,
-    date_time TEXT NOT NULL
-);
\ No newline at end of file
+    date_time TEXT NOT NULL,
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    pinned BOOLEAN NOT NULL DEFAULT FALSE,
+    tags TEXT NOT NULL DEFAULT '',
+    reminder TEXT NOT NULL DEFAULT '',
+    uid TEXT NOT NULL DEFAULT ''
+);
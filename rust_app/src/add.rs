@@ -0,0 +1,132 @@
+use crate::note::Note;
+use crate::note_taker::NoteTaker;
+
+use std::fs::File;
+use std::io::Read;
+
+pub const USAGE: &str = "Usage: note_taker add --title <title> [--file <path>]\n\
+    The content is read from the file, or from stdin when no file (or -) is given.";
+
+/// The largest content accepted, in bytes.
+pub const MAX_CONTENT_SIZE: u64 = 1024 * 1024;
+
+/// Reads note content, refusing anything over `limit` bytes, anything that
+/// looks binary and anything that is not UTF-8.
+pub fn read_content(reader: impl Read, limit: u64) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    reader
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("Content is larger than {} bytes", limit));
+    }
+    // Like git, treat content with a NUL byte as binary
+    if bytes.contains(&0) {
+        return Err("Content looks like binary data".to_string());
+    }
+    let text = String::from_utf8(bytes).map_err(|e| {
+        format!(
+            "Content is not valid UTF-8 (invalid byte at offset {})",
+            e.utf8_error().valid_up_to()
+        )
+    })?;
+    Ok(text.strip_prefix('\u{feff}').unwrap_or(&text).to_string())
+}
+
+/// Runs `add --title <title> [--file <path>]` from command line arguments,
+/// adding the note to the note taker and returning it.
+pub fn run(args: &[String], note_taker: &mut NoteTaker) -> Result<Note, String> {
+    let mut title = None;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--title" => title = args.next(),
+            "--file" => file = args.next(),
+            _ => return Err(format!("Unknown argument: {}\n{}", arg, USAGE)),
+        }
+    }
+    let title = title.ok_or(USAGE)?;
+    let content = match file.map(String::as_str) {
+        None | Some("-") => read_content(std::io::stdin().lock(), MAX_CONTENT_SIZE)?,
+        Some(path) => read_content(
+            File::open(path).map_err(|e| format!("{}: {}", path, e))?,
+            MAX_CONTENT_SIZE,
+        )
+        .map_err(|e| format!("{}: {}", path, e))?,
+    };
+    let note = Note::new(title.to_string(), content)?;
+    note_taker.add_note(note.clone());
+    Ok(note)
+}
+
+#[cfg(test)]
+use crate::persistence::file_persistence::FilePersistence;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_should_read_text_content() {
+        assert_eq!(
+            read_content("Line 1\nLine 2\n".as_bytes(), 100),
+            Ok("Line 1\nLine 2\n".to_string())
+        );
+        assert_eq!(
+            read_content("\u{feff}Café".as_bytes(), 100),
+            Ok("Café".to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_reject_large_binary_or_invalid_content() {
+        assert_eq!(
+            read_content("12345".as_bytes(), 4),
+            Err("Content is larger than 4 bytes".to_string())
+        );
+        assert_eq!(read_content("1234".as_bytes(), 4), Ok("1234".to_string()));
+        assert_eq!(
+            read_content(&b"PK\x03\x04\x00\x00"[..], 100),
+            Err("Content looks like binary data".to_string())
+        );
+        assert_eq!(
+            read_content(&b"ok \xff"[..], 100),
+            Err("Content is not valid UTF-8 (invalid byte at offset 3)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_add_note_from_file() {
+        let path = std::env::temp_dir().join("note_taker_add_build.log");
+        std::fs::write(&path, "Build succeeded\n").unwrap();
+        let persistence = Box::new(FilePersistence::new("test_notes.json".to_string()));
+        let mut note_taker = NoteTaker::new(persistence);
+
+        let note = run(
+            &args(&["--title", "Build log", "--file", path.to_str().unwrap()]),
+            &mut note_taker,
+        )
+        .unwrap();
+
+        assert_eq!(note.get_title(), "Build log");
+        assert_eq!(note.get_content(), "Build succeeded");
+        assert_eq!(note_taker.get_size(), 1);
+        assert!(run(&args(&["--file", path.to_str().unwrap()]), &mut note_taker).is_err());
+        assert!(
+            run(
+                &args(&["--title", " ", "--file", path.to_str().unwrap()]),
+                &mut note_taker
+            )
+            .is_err()
+        );
+        assert_eq!(note_taker.get_size(), 1);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
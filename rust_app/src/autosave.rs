@@ -0,0 +1,337 @@
+use crate::crypto::{self, Key};
+use crate::history::Command;
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// Marks a journal line encrypted under the storage passphrase
+const JOURNAL_PREFIX: &str = "note_taker:journal:v1:";
+
+/// When journaled changes are saved to storage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    OnChange,
+    /// At most once per interval, when a change is made or checked for.
+    Every(Duration),
+}
+
+/// Journals the changes made since the notes were last saved, so a session
+/// that ends without saving can be replayed on the next start, and tells
+/// when the changes are due to be saved.
+pub struct Autosave {
+    journal_path: PathBuf,
+    policy: Policy,
+    last_save: Instant,
+    pending: usize,
+    error: Option<String>,
+    // The passphrase, and the salt and key new lines are sealed with
+    encryption: Option<(String, Vec<u8>, Key)>,
+}
+
+impl Autosave {
+    /// Configures autosave from `NOTES_AUTOSAVE`, the seconds between saves
+    /// (on every change when unset or 0), and `NOTES_JOURNAL`, the journal
+    /// path. By default that is `~/.note_taker_journal.jsonl` for PostgreSQL
+    /// and a journal of its own for each other `NOTES_BACKEND`, so changes
+    /// are never replayed into another store. The journal is encrypted when
+    /// `NOTES_PASSPHRASE` is set, as the notes are.
+    pub fn from_env() -> Result<Self, String> {
+        let policy = match std::env::var("NOTES_AUTOSAVE") {
+            Ok(seconds) => match seconds.trim().parse::<u64>() {
+                Ok(0) => Policy::OnChange,
+                Ok(seconds) => Policy::Every(Duration::from_secs(seconds)),
+                Err(_) => return Err(format!("Invalid NOTES_AUTOSAVE: {}", seconds)),
+            },
+            Err(_) => Policy::OnChange,
+        };
+        let name = match std::env::var("NOTES_BACKEND") {
+            Ok(backend) if backend != "postgres" => {
+                format!("note_taker_journal_{}.jsonl", Self::backend_id(&backend))
+            }
+            _ => "note_taker_journal.jsonl".to_string(),
+        };
+        let journal_path = std::env::var_os("NOTES_JOURNAL")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(format!(".{}", name)))
+            })
+            .unwrap_or_else(|| PathBuf::from(name));
+        let autosave = Autosave {
+            journal_path,
+            ..Autosave::new("", policy)
+        };
+        match std::env::var("NOTES_PASSPHRASE") {
+            Ok(passphrase) => autosave.with_passphrase(&passphrase),
+            Err(_) => Ok(autosave),
+        }
+    }
+
+    // Identifies the store a backend spec such as `sqlite:notes.db` names,
+    // with relative locations made absolute, by an FNV-1a hash that is the
+    // same across releases
+    fn backend_id(backend: &str) -> String {
+        let backend = match backend.split_once(':') {
+            Some((kind, location)) if kind != "postgres" => match std::path::absolute(location) {
+                Ok(location) => format!("{}:{}", kind, location.display()),
+                Err(_) => backend.to_string(),
+            },
+            _ => backend.to_string(),
+        };
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in backend.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:016x}", hash)
+    }
+
+    pub fn new(journal_path: &str, policy: Policy) -> Self {
+        Autosave {
+            journal_path: PathBuf::from(journal_path),
+            policy,
+            last_save: Instant::now(),
+            pending: 0,
+            error: None,
+            encryption: None,
+        }
+    }
+
+    /// Encrypts the journal lines written from now on with a key derived
+    /// from `passphrase`, so changes are not kept in plaintext when storage
+    /// is encrypted.
+    pub fn with_passphrase(self, passphrase: &str) -> Result<Self, String> {
+        let salt = crypto::generate_salt();
+        let key = crypto::derive_key(passphrase, &salt)?;
+        Ok(Autosave {
+            encryption: Some((passphrase.to_string(), salt, key)),
+            ..self
+        })
+    }
+
+    // Reads a journal line, decrypting it with keys derived for the salts met
+    fn read_line(&self, line: &str, keys: &mut HashMap<Vec<u8>, Key>) -> Result<Command, String> {
+        let json = match crypto::parse(line, JOURNAL_PREFIX) {
+            Some(envelope) => {
+                let envelope = envelope?;
+                let (passphrase, _, _) = self
+                    .encryption
+                    .as_ref()
+                    .ok_or("Journal is encrypted, set NOTES_PASSPHRASE to read it")?;
+                let key = match keys.get(&envelope.salt) {
+                    Some(key) => *key,
+                    None => {
+                        let key = crypto::derive_key(passphrase, &envelope.salt)?;
+                        keys.insert(envelope.salt.clone(), key);
+                        key
+                    }
+                };
+                crypto::open(&key, &envelope, JOURNAL_PREFIX).ok_or("Wrong passphrase")?
+            }
+            None => line.to_string(),
+        };
+        let json_value = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+        Command::from_json_value(&json_value)
+    }
+
+    /// Reads the changes left in the journal. A torn last line, written as
+    /// the process died and so without its newline, is ignored.
+    pub fn read_journal(&self) -> Result<Vec<Command>, String> {
+        let text = match fs::read_to_string(&self.journal_path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.to_string()),
+        };
+        let lines: Vec<&str> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let mut commands = Vec::new();
+        let mut keys = HashMap::new();
+        if let Some((_, salt, key)) = &self.encryption {
+            keys.insert(salt.clone(), *key);
+        }
+        for (i, line) in lines.iter().enumerate() {
+            match self.read_line(line, &mut keys) {
+                Ok(command) => commands.push(command),
+                Err(_) if i == lines.len() - 1 && !text.ends_with('\n') => break,
+                Err(e) => return Err(format!("Corrupted journal line {}: {}", i + 1, e)),
+            }
+        }
+        Ok(commands)
+    }
+
+    /// Appends a change to the journal, on disk before this returns.
+    pub fn record(&mut self, command: &Command) -> Result<(), String> {
+        self.pending += 1;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.journal_path)
+            .map_err(|e| e.to_string())?;
+        let mut line = command.to_json_value().to_string();
+        if let Some((_, salt, key)) = &self.encryption {
+            line = crypto::seal(key, salt, &line, JOURNAL_PREFIX)?;
+        }
+        writeln!(file, "{}", line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Counts changes replayed from the journal as not saved yet.
+    pub fn set_pending(&mut self, pending: usize) {
+        self.pending = pending;
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.pending > 0
+            && match self.policy {
+                Policy::OnChange => true,
+                Policy::Every(interval) => self.last_save.elapsed() >= interval,
+            }
+    }
+
+    /// Empties the journal once its changes are in storage.
+    pub fn saved(&mut self) -> Result<(), String> {
+        self.pending = 0;
+        self.last_save = Instant::now();
+        match fs::remove_file(&self.journal_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("note_taker_autosave_{}", name));
+        let _ = fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    fn note(title: &str) -> Note {
+        Note::new(title.to_string(), "Content".to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_should_journal_changes_until_saved() {
+        let path = temp_path("journal.jsonl");
+        let mut autosave = Autosave::new(&path, Policy::OnChange);
+        assert_eq!(autosave.read_journal(), Ok(Vec::new()));
+        assert!(!autosave.is_due());
+
+        autosave.record(&Command::Insert(note("First"))).unwrap();
+        autosave.record(&Command::Remove(note("Second"))).unwrap();
+
+        assert!(autosave.is_due());
+        assert_eq!(
+            Autosave::new(&path, Policy::OnChange).read_journal(),
+            Ok(vec![
+                Command::Insert(note("First")),
+                Command::Remove(note("Second"))
+            ])
+        );
+        autosave.saved().unwrap();
+        assert!(!autosave.is_due());
+        assert_eq!(autosave.read_journal(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_should_ignore_torn_last_line() {
+        let path = temp_path("torn.jsonl");
+        let line = Command::Insert(note("First")).to_json_value().to_string();
+        fs::write(&path, format!("{}\n{{\"insert\": {{\"ti", line)).unwrap();
+        let autosave = Autosave::new(&path, Policy::OnChange);
+
+        assert_eq!(
+            autosave.read_journal(),
+            Ok(vec![Command::Insert(note("First"))])
+        );
+
+        fs::write(&path, format!("garbage\n{}\n", line)).unwrap();
+        assert!(autosave.read_journal().is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_should_encrypt_journal_with_passphrase() {
+        let path = temp_path("encrypted.jsonl");
+        let mut autosave = Autosave::new(&path, Policy::OnChange)
+            .with_passphrase("secret")
+            .unwrap();
+        let mut unlocked = Note::new("Bank".to_string(), "PIN 1234".to_string()).unwrap();
+        let mut locked = unlocked.clone();
+        locked.lock("password").unwrap();
+        autosave
+            .record(&Command::Replace {
+                before: unlocked.clone(),
+                after: locked,
+            })
+            .unwrap();
+        unlocked.set_content("PIN 5678".to_string());
+        autosave.record(&Command::Insert(unlocked.clone())).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("PIN"));
+        assert!(!text.contains("Bank"));
+        let reopened = Autosave::new(&path, Policy::OnChange)
+            .with_passphrase("secret")
+            .unwrap();
+        assert_eq!(
+            reopened.read_journal().unwrap()[1],
+            Command::Insert(unlocked)
+        );
+        assert!(
+            Autosave::new(&path, Policy::OnChange)
+                .read_journal()
+                .is_err()
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_periodic_policy_should_wait_for_interval() {
+        let path = temp_path("periodic.jsonl");
+        let mut autosave = Autosave::new(&path, Policy::Every(Duration::from_secs(3600)));
+
+        autosave.record(&Command::Insert(note("First"))).unwrap();
+        assert!(!autosave.is_due());
+
+        let mut autosave = Autosave::new(&path, Policy::Every(Duration::ZERO));
+        autosave.set_pending(1);
+        assert!(autosave.is_due());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_each_backend_should_have_its_own_journal() {
+        let absolute = std::path::absolute("notes.db").unwrap();
+
+        assert_eq!(
+            Autosave::backend_id("sqlite:notes.db"),
+            Autosave::backend_id(&format!("sqlite:{}", absolute.display()))
+        );
+        assert_ne!(
+            Autosave::backend_id("sqlite:notes.db"),
+            Autosave::backend_id("vault:notes.db")
+        );
+        assert_ne!(
+            Autosave::backend_id("sqlite:notes.db"),
+            Autosave::backend_id("sqlite:other.db")
+        );
+    }
+}
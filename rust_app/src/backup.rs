@@ -0,0 +1,323 @@
+use crate::note::Note;
+use crate::persistence::persistence_trait::PersistenceTrait;
+
+use chrono::Local;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+pub const BACKUP_FORMAT: &str = "note_taker-backup";
+pub const BACKUP_VERSION: u64 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const NOTES_FILE: &str = "notes.jsonl";
+
+/// Describes a backup: the format version, when it was made, how many notes
+/// it holds and the SHA-256 checksum of each file in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub version: u64,
+    pub created_at: String,
+    pub note_count: usize,
+    pub checksums: Vec<(String, String)>,
+}
+
+impl Manifest {
+    fn to_json_value(&self) -> serde_json::Value {
+        let files: serde_json::Map<String, serde_json::Value> = self
+            .checksums
+            .iter()
+            .map(|(name, checksum)| (name.clone(), serde_json::json!({ "sha256": checksum })))
+            .collect();
+        serde_json::json!({
+            "format": BACKUP_FORMAT,
+            "version": self.version,
+            "created_at": self.created_at,
+            "note_count": self.note_count,
+            "files": files,
+        })
+    }
+
+    fn from_json_value(json_value: &serde_json::Value) -> Result<Manifest, String> {
+        if json_value["format"].as_str() != Some(BACKUP_FORMAT) {
+            return Err("Not a note taker backup".to_string());
+        }
+        let version = json_value["version"]
+            .as_u64()
+            .ok_or("Missing version in manifest")?;
+        if version > BACKUP_VERSION {
+            return Err(format!(
+                "Backup version {} is newer than the supported version {}",
+                version, BACKUP_VERSION
+            ));
+        }
+        let checksums = json_value["files"]
+            .as_object()
+            .ok_or("Missing files in manifest")?
+            .iter()
+            .map(|(name, file)| {
+                file["sha256"]
+                    .as_str()
+                    .map(|checksum| (name.clone(), checksum.to_string()))
+                    .ok_or(format!("Missing checksum of {} in manifest", name))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Manifest {
+            version,
+            created_at: json_value["created_at"].as_str().unwrap_or("").to_string(),
+            note_count: json_value["note_count"]
+                .as_u64()
+                .ok_or("Missing note count in manifest")? as usize,
+            checksums,
+        })
+    }
+}
+
+fn sha256(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn append_file(
+    archive: &mut tar::Builder<GzEncoder<File>>,
+    name: &str,
+    data: &[u8],
+) -> Result<(), String> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Local::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    archive
+        .append_data(&mut header, name, data)
+        .map_err(|e| e.to_string())
+}
+
+/// Writes every note, archived ones and all their metadata included, to a
+/// gzip-compressed tar archive with a manifest of checksums.
+pub fn create_backup(notes: &[Note], path: &str) -> Result<Manifest, String> {
+    let mut lines = String::new();
+    for note in notes {
+        lines.push_str(&note.to_json_value().to_string());
+        lines.push('\n');
+    }
+    let manifest = Manifest {
+        version: BACKUP_VERSION,
+        created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        note_count: notes.len(),
+        checksums: vec![(NOTES_FILE.to_string(), sha256(lines.as_bytes()))],
+    };
+
+    let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    append_file(
+        &mut archive,
+        MANIFEST_FILE,
+        manifest.to_json_value().to_string().as_bytes(),
+    )?;
+    append_file(&mut archive, NOTES_FILE, lines.as_bytes())?;
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .and_then(|file| file.sync_all())
+        .map_err(|e| e.to_string())?;
+    Ok(manifest)
+}
+
+/// Reads the notes of a backup, checking the manifest, every checksum and
+/// the note count before returning any of them.
+pub fn read_backup(path: &str) -> Result<(Manifest, Vec<Note>), String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut files = HashMap::new();
+    for entry in archive
+        .entries()
+        .map_err(|e| format!("Corrupted backup: {}", e))?
+    {
+        let mut entry = entry.map_err(|e| format!("Corrupted backup: {}", e))?;
+        let name = entry
+            .path()
+            .map_err(|e| e.to_string())?
+            .to_string_lossy()
+            .to_string();
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Corrupted backup: {}", e))?;
+        files.insert(name, data);
+    }
+
+    let manifest = files.get(MANIFEST_FILE).ok_or("Backup has no manifest")?;
+    let manifest: serde_json::Value =
+        serde_json::from_slice(manifest).map_err(|e| format!("Invalid manifest: {}", e))?;
+    let manifest = Manifest::from_json_value(&manifest)?;
+
+    for (name, checksum) in &manifest.checksums {
+        let data = files
+            .get(name)
+            .ok_or(format!("Backup is missing {}", name))?;
+        if sha256(data) != *checksum {
+            return Err(format!("Checksum mismatch for {}", name));
+        }
+    }
+    let lines = files
+        .get(NOTES_FILE)
+        .ok_or(format!("Backup is missing {}", NOTES_FILE))?;
+    if !manifest
+        .checksums
+        .iter()
+        .any(|(name, _)| name == NOTES_FILE)
+    {
+        return Err(format!("Manifest has no checksum for {}", NOTES_FILE));
+    }
+
+    let lines = String::from_utf8(lines.clone()).map_err(|e| e.to_string())?;
+    let notes = lines
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let json_value: serde_json::Value =
+                serde_json::from_str(line).map_err(|e| e.to_string())?;
+            Note::from_json_value(&json_value)
+        })
+        .collect::<Result<Vec<Note>, String>>()?;
+    if notes.len() != manifest.note_count {
+        return Err(format!(
+            "Backup should hold {} notes but holds {}",
+            manifest.note_count,
+            notes.len()
+        ));
+    }
+    Ok((manifest, notes))
+}
+
+/// Verifies a backup, then replaces the notes stored by `persistence` with it.
+#[allow(dead_code)]
+pub fn restore_backup(path: &str, persistence: &dyn PersistenceTrait) -> Result<usize, String> {
+    let (_, notes) = read_backup(path)?;
+    persistence.save(&notes)?;
+    Ok(notes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::rusqlite_persistence::RusqlitePersistence;
+    use std::io::Write;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("note_taker_backup_{}", name));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    fn notes() -> Vec<Note> {
+        let mut archived = Note::new_with_date_time(
+            "Old \"quoted\"".to_string(),
+            "Line 1\nLine 2".to_string(),
+            "2024-01-01 09:00".to_string(),
+        )
+        .unwrap();
+        archived.set_archived(true);
+        archived.add_tag("history").unwrap();
+        let mut pinned = Note::new_with_date_time(
+            "Current".to_string(),
+            "Content".to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+        pinned.set_pinned(true);
+        vec![archived, pinned]
+    }
+
+    // Rewrites a backup with the given files, to simulate tampering
+    fn write_archive(path: &str, files: &[(&str, &[u8])]) {
+        let file = File::create(path).unwrap();
+        let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        for (name, data) in files {
+            append_file(&mut archive, name, data).unwrap();
+        }
+        archive.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_backup_should_round_trip_notes_and_metadata() {
+        let path = temp_path("round_trip.tar.gz");
+
+        let manifest = create_backup(&notes(), &path).unwrap();
+        let (read_manifest, restored) = read_backup(&path).unwrap();
+
+        assert_eq!(read_manifest, manifest);
+        assert_eq!(manifest.note_count, 2);
+        assert_eq!(restored, notes());
+        assert!(restored[0].is_archived());
+        assert_eq!(restored[0].get_tags(), ["history"]);
+        assert!(restored[1].is_pinned());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_should_restore_into_backend() {
+        let path = temp_path("restore.tar.gz");
+        let store = temp_path("restore.db");
+        create_backup(&notes(), &path).unwrap();
+        let persistence = RusqlitePersistence::new(&store).unwrap();
+
+        assert_eq!(restore_backup(&path, &persistence), Ok(2));
+        assert_eq!(persistence.load().unwrap(), notes());
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(store).unwrap();
+    }
+
+    #[test]
+    fn test_should_reject_tampered_backup_before_restoring() {
+        let path = temp_path("tampered.tar.gz");
+        let store = temp_path("tampered.db");
+        create_backup(&notes(), &path).unwrap();
+        let (manifest, _) = read_backup(&path).unwrap();
+        let manifest = manifest.to_json_value().to_string();
+        write_archive(
+            &path,
+            &[
+                (MANIFEST_FILE, manifest.as_bytes()),
+                (NOTES_FILE, b"{\"title\":\"Injected\"}\n"),
+            ],
+        );
+        let persistence = RusqlitePersistence::new(&store).unwrap();
+        persistence.save(&notes()[..1]).unwrap();
+
+        assert_eq!(
+            restore_backup(&path, &persistence),
+            Err(format!("Checksum mismatch for {}", NOTES_FILE))
+        );
+        assert_eq!(persistence.load().unwrap(), notes()[..1]);
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(store).unwrap();
+    }
+
+    #[test]
+    fn test_should_reject_unknown_or_newer_backups() {
+        let path = temp_path("newer.tar.gz");
+        let manifest = serde_json::json!({"format": BACKUP_FORMAT, "version": 99, "files": {}});
+        write_archive(&path, &[(MANIFEST_FILE, manifest.to_string().as_bytes())]);
+        assert_eq!(
+            read_backup(&path).map(|_| ()),
+            Err("Backup version 99 is newer than the supported version 1".to_string())
+        );
+
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"not an archive").unwrap();
+        assert!(read_backup(&path).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
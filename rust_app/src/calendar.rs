@@ -0,0 +1,232 @@
+use crate::note::Note;
+use crate::templates::TEMPLATE_TAG;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const DUE_MARKER: &str = "due:";
+
+/// Returns the time of the first `due:YYYY-MM-DD HH:MM` marker in some
+/// content. The marker must start a word, so `overdue:` is not one.
+pub fn parse_due(content: &str) -> Option<NaiveDateTime> {
+    content.match_indices(DUE_MARKER).find_map(|(start, _)| {
+        let starts_word = content[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_alphanumeric());
+        let time = content.get(start + DUE_MARKER.len()..start + DUE_MARKER.len() + 16)?;
+        starts_word
+            .then(|| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").ok())
+            .flatten()
+    })
+}
+
+// Escapes a TEXT value as RFC 5545 requires
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\n', '\r'], "\\n")
+}
+
+// Ends a content line with CRLF, folding it so no line is longer than 75
+// octets without splitting a character
+fn push_line(output: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            output.push_str("\r\n ");
+            length = 1;
+        }
+        output.push(c);
+        length += c.len_utf8();
+    }
+    output.push_str("\r\n");
+}
+
+// Note times have no time zone, so they are written as floating local times
+fn format_local(date_time: NaiveDateTime) -> String {
+    date_time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// Renders the notes as an iCalendar feed. Each note is a VJOURNAL at its
+/// date time, or a VTODO due at the time of its `due:` marker. UIDs come
+/// from the note UIDs, so an event stays the same when its note is renamed.
+/// Templates are left out.
+pub fn to_ics(notes: &[Note], now: DateTime<Utc>) -> String {
+    let mut output = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//note_taker//Notes//EN",
+        "CALSCALE:GREGORIAN",
+    ] {
+        push_line(&mut output, line);
+    }
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for note in notes.iter().filter(|note| !note.has_tag(TEMPLATE_TAG)) {
+        // Notes imported twice share a UID, so later ones are numbered in the
+        // order notes are kept
+        let count = seen.entry(note.get_uid()).or_default();
+        *count += 1;
+        let uid = match count {
+            1 => format!("{}@note_taker", note.get_uid()),
+            n => format!("{}-{}@note_taker", note.get_uid(), n),
+        };
+        let date_time = NaiveDateTime::parse_from_str(&note.get_date_time(), "%Y-%m-%d %H:%M")
+            .expect("Note date times are valid");
+        // Locked content stays out of the feed, markers included
+        let content = if note.is_locked() {
+            ""
+        } else {
+            note.get_content()
+        };
+        let due = parse_due(content);
+        let component = if due.is_some() { "VTODO" } else { "VJOURNAL" };
+
+        push_line(&mut output, &format!("BEGIN:{}", component));
+        push_line(&mut output, &format!("UID:{}", uid));
+        push_line(&mut output, &format!("DTSTAMP:{}", stamp));
+        match due {
+            Some(due) => {
+                // A to-do can only start before it is due
+                if date_time < due {
+                    push_line(&mut output, &format!("DTSTART:{}", format_local(date_time)));
+                }
+                push_line(&mut output, &format!("DUE:{}", format_local(due)));
+                let tasks = note.get_tasks();
+                let status = if !tasks.is_empty() && tasks.iter().all(|task| task.done) {
+                    "COMPLETED"
+                } else {
+                    "NEEDS-ACTION"
+                };
+                push_line(&mut output, &format!("STATUS:{}", status));
+            }
+            None => {
+                push_line(&mut output, &format!("DTSTART:{}", format_local(date_time)));
+                push_line(&mut output, "STATUS:FINAL");
+            }
+        }
+        push_line(
+            &mut output,
+            &format!("SUMMARY:{}", escape_text(note.get_title())),
+        );
+        if !content.is_empty() {
+            push_line(
+                &mut output,
+                &format!("DESCRIPTION:{}", escape_text(content)),
+            );
+        }
+        if !note.get_tags().is_empty() {
+            let tags: Vec<String> = note.get_tags().iter().map(|tag| escape_text(tag)).collect();
+            push_line(&mut output, &format!("CATEGORIES:{}", tags.join(",")));
+        }
+        push_line(&mut output, &format!("END:{}", component));
+    }
+    push_line(&mut output, "END:VCALENDAR");
+    output
+}
+
+/// Writes the feed of the notes to `path`, replacing the previous one at
+/// once so calendar clients never read a partial feed.
+pub fn write_feed(notes: &[Note], path: &Path) -> Result<(), String> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    fs::write(&temp_path, to_ics(notes, Utc::now()))
+        .and_then(|_| fs::rename(&temp_path, path))
+        .map_err(|e| format!("Failed to write calendar feed {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, content: &str) -> Note {
+        Note::new_with_date_time(
+            title.to_string(),
+            content.to_string(),
+            "2025-03-04 09:15".to_string(),
+        )
+        .unwrap()
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-03-05T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_should_parse_due_marker() {
+        let due = NaiveDateTime::parse_from_str("2025-03-07 17:00", "%Y-%m-%d %H:%M").unwrap();
+
+        assert_eq!(parse_due("Report, due:2025-03-07 17:00 sharp"), Some(due));
+        assert_eq!(parse_due("(due:bad) due:2025-03-07 17:00"), Some(due));
+        assert_eq!(parse_due("overdue:2025-03-07 17:00"), None);
+        assert_eq!(parse_due("due:2025-03-07"), None);
+        assert_eq!(parse_due("No marker"), None);
+    }
+
+    #[test]
+    fn test_should_render_journals_and_todos() {
+        let mut journal = note("Standup; daily", "Line 1\nLine, 2");
+        journal.add_tag("work").unwrap();
+        let todo = note("Report", "- [x] Draft\ndue:2025-03-07 17:00");
+        let mut locked = note("Secret", "due:2025-03-07 17:00");
+        locked.lock("password").unwrap();
+
+        let ics = to_ics(&[journal.clone(), todo.clone(), locked.clone()], now());
+
+        assert_eq!(
+            ics,
+            format!(
+                "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//note_taker//Notes//EN\r\nCALSCALE:GREGORIAN\r\n\
+                 BEGIN:VJOURNAL\r\nUID:{}@note_taker\r\nDTSTAMP:20250305T100000Z\r\n\
+                 DTSTART:20250304T091500\r\nSTATUS:FINAL\r\nSUMMARY:Standup\\; daily\r\n\
+                 DESCRIPTION:Line 1\\nLine\\, 2\r\nCATEGORIES:work\r\nEND:VJOURNAL\r\n\
+                 BEGIN:VTODO\r\nUID:{}@note_taker\r\nDTSTAMP:20250305T100000Z\r\n\
+                 DTSTART:20250304T091500\r\nDUE:20250307T170000\r\nSTATUS:COMPLETED\r\n\
+                 SUMMARY:Report\r\nDESCRIPTION:- [x] Draft\\ndue:2025-03-07 17:00\r\nEND:VTODO\r\n\
+                 BEGIN:VJOURNAL\r\nUID:{}@note_taker\r\nDTSTAMP:20250305T100000Z\r\n\
+                 DTSTART:20250304T091500\r\nSTATUS:FINAL\r\nSUMMARY:Secret\r\nEND:VJOURNAL\r\n\
+                 END:VCALENDAR\r\n",
+                journal.get_uid(),
+                todo.get_uid(),
+                locked.get_uid()
+            )
+        );
+    }
+
+    #[test]
+    fn test_should_fold_long_lines_and_keep_uids_unique() {
+        let long = note("Long", &"é".repeat(60));
+        let mut twin = note("Long", "Other content");
+        twin.set_uid(long.get_uid());
+
+        let ics = to_ics(&[long.clone(), twin], now());
+
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+        assert!(ics.contains("\r\n é"));
+        assert!(ics.contains(&format!("UID:{}@note_taker", long.get_uid())));
+        assert!(ics.contains(&format!("UID:{}-2@note_taker", long.get_uid())));
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(unfolded.contains(&format!("DESCRIPTION:{}\r\n", "é".repeat(60))));
+    }
+
+    #[test]
+    fn test_uid_should_survive_rename() {
+        let mut note = note("Report", "due:2025-03-07 17:00");
+        let before = to_ics(std::slice::from_ref(&note), now());
+        note.set_title("Quarterly report".to_string()).unwrap();
+
+        let after = to_ics(std::slice::from_ref(&note), now());
+
+        let uid = format!("UID:{}@note_taker", note.get_uid());
+        assert!(before.contains(&uid));
+        assert!(after.contains(&uid));
+    }
+}
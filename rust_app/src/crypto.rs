@@ -0,0 +1,148 @@
+use argon2::Argon2;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+pub use chacha20poly1305::Key;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+
+/// Text encrypted under a passphrase-derived key, stored as a prefix naming
+/// its use followed by the base64 salt, nonce and ciphertext.
+pub struct Envelope {
+    pub salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+pub fn generate_salt() -> Vec<u8> {
+    let mut salt = vec![0; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Returns 16 random bytes as hex, for identifiers that must not collide.
+pub fn random_id() -> String {
+    generate_salt()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Derives a key from a passphrase with Argon2.
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, String> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypts text with XChaCha20-Poly1305 into an envelope starting with
+/// `prefix`. The prefix is authenticated too, so an envelope only opens for
+/// the use it was sealed for.
+pub fn seal(key: &Key, salt: &[u8], plaintext: &str, prefix: &str) -> Result<String, String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(key)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext.as_bytes(),
+                aad: prefix.as_bytes(),
+            },
+        )
+        .map_err(|_| "Failed to encrypt".to_string())?;
+    Ok(format!(
+        "{}{}:{}:{}",
+        prefix,
+        BASE64.encode(salt),
+        BASE64.encode(nonce),
+        BASE64.encode(ciphertext)
+    ))
+}
+
+pub fn is_sealed(text: &str, prefix: &str) -> bool {
+    text.starts_with(prefix)
+}
+
+/// Reads an envelope sealed with `prefix`, or returns `None` if the text is not one.
+pub fn parse(text: &str, prefix: &str) -> Option<Result<Envelope, String>> {
+    let envelope = text.strip_prefix(prefix)?;
+    let parts = envelope
+        .trim()
+        .split(':')
+        .map(|part| BASE64.decode(part))
+        .collect::<Result<Vec<Vec<u8>>, _>>();
+    Some(match parts {
+        Ok(parts) => match <[Vec<u8>; 3]>::try_from(parts) {
+            Ok([salt, nonce, ciphertext]) if nonce.len() == NONCE_LENGTH => Ok(Envelope {
+                salt,
+                nonce,
+                ciphertext,
+            }),
+            _ => Err("Corrupted encrypted data".to_string()),
+        },
+        Err(_) => Err("Corrupted encrypted data".to_string()),
+    })
+}
+
+/// Decrypts an envelope, returning `None` if the key is wrong or the
+/// envelope was tampered with.
+pub fn open(key: &Key, envelope: &Envelope, prefix: &str) -> Option<String> {
+    let plaintext = XChaCha20Poly1305::new(key)
+        .decrypt(
+            XNonce::from_slice(&envelope.nonce),
+            Payload {
+                msg: &envelope.ciphertext,
+                aad: prefix.as_bytes(),
+            },
+        )
+        .ok()?;
+    String::from_utf8(plaintext).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREFIX: &str = "test:v1:";
+
+    #[test]
+    fn test_should_seal_and_open() {
+        let salt = generate_salt();
+        let key = derive_key("passphrase", &salt).unwrap();
+
+        let sealed = seal(&key, &salt, "secret", PREFIX).unwrap();
+        let envelope = parse(&sealed, PREFIX).unwrap().unwrap();
+
+        assert!(is_sealed(&sealed, PREFIX));
+        assert!(!sealed.contains("secret"));
+        assert_eq!(envelope.salt, salt);
+        assert_eq!(open(&key, &envelope, PREFIX), Some("secret".to_string()));
+        assert_eq!(
+            open(&derive_key("wrong", &salt).unwrap(), &envelope, PREFIX),
+            None
+        );
+    }
+
+    #[test]
+    fn test_envelope_should_only_open_for_its_prefix() {
+        let salt = generate_salt();
+        let key = derive_key("passphrase", &salt).unwrap();
+        let sealed = seal(&key, &salt, "secret", PREFIX).unwrap();
+        let other = sealed.replacen(PREFIX, "other:v1:", 1);
+
+        let envelope = parse(&other, "other:v1:").unwrap().unwrap();
+        assert_eq!(open(&key, &envelope, "other:v1:"), None);
+    }
+
+    #[test]
+    fn test_should_reject_malformed_envelopes() {
+        assert!(parse("plain text", PREFIX).is_none());
+        assert!(parse("test:v1:not base64!", PREFIX).unwrap().is_err());
+        assert!(parse("test:v1:AAAA:AAAA", PREFIX).unwrap().is_err());
+    }
+}
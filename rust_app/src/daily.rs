@@ -0,0 +1,221 @@
+use crate::note::Note;
+use crate::note_taker::NoteTaker;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveDate, NaiveDateTime};
+
+pub const DEFAULT_TITLE_FORMAT: &str = "%Y-%m-%d";
+
+/// A daily log kept as one note per day, titled from the date.
+pub struct DailyJournal {
+    title_format: String,
+}
+
+impl DailyJournal {
+    /// Creates a journal whose notes are titled with a chrono format such as
+    /// `Journal %Y-%m-%d`, which must hold the year, month and day.
+    pub fn new(title_format: &str) -> Result<Self, String> {
+        if StrftimeItems::new(title_format).any(|item| item == Item::Error) {
+            return Err(format!("Invalid title format: {}", title_format));
+        }
+        let journal = DailyJournal {
+            title_format: title_format.to_string(),
+        };
+        let sample = NaiveDate::from_ymd_opt(2001, 12, 31).unwrap();
+        if journal.date_of(&journal.title(sample)) != Some(sample) {
+            return Err(format!(
+                "Title format must hold the year, month and day: {}",
+                title_format
+            ));
+        }
+        Ok(journal)
+    }
+
+    /// Uses the title format in `NOTES_DAILY_TITLE`, else `YYYY-MM-DD`.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("NOTES_DAILY_TITLE") {
+            Ok(title_format) => DailyJournal::new(&title_format),
+            Err(_) => DailyJournal::new(DEFAULT_TITLE_FORMAT),
+        }
+    }
+
+    pub fn title(&self, date: NaiveDate) -> String {
+        date.format(&self.title_format).to_string()
+    }
+
+    /// Returns the day a journal note is about, from its title.
+    pub fn date_of(&self, title: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(title, &self.title_format).ok()
+    }
+
+    /// Returns the index of the note of a day, preferring one dated that day
+    /// when several notes have its title.
+    pub fn find(&self, note_taker: &NoteTaker, date: NaiveDate) -> Option<usize> {
+        let indexes = note_taker.get_by_title(&self.title(date));
+        let day = date.format("%Y-%m-%d").to_string();
+        indexes
+            .iter()
+            .find(|&&index| note_taker.get_note(index).get_date_time().starts_with(&day))
+            .or(indexes.first())
+            .copied()
+    }
+
+    /// Returns the index of the note of a day, creating it dated at the start
+    /// of the day if there is none yet.
+    pub fn open(&self, note_taker: &mut NoteTaker, date: NaiveDate) -> Result<usize, String> {
+        if let Some(index) = self.find(note_taker, date) {
+            return Ok(index);
+        }
+        let note = Note::new_with_date_time(
+            self.title(date),
+            String::new(),
+            format!("{} 00:00", date.format("%Y-%m-%d")),
+        )?;
+        note_taker.add_note(note.clone());
+        note_taker
+            .index_of(&note)
+            .ok_or("Note not found".to_string())
+    }
+
+    /// Appends an entry stamped with the time of `at` to the note of its day.
+    pub fn append(
+        &self,
+        note_taker: &mut NoteTaker,
+        entry: &str,
+        at: NaiveDateTime,
+    ) -> Result<usize, String> {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            return Err("Entry cannot be empty".to_string());
+        }
+        let index = self.open(note_taker, at.date())?;
+        let note = note_taker.get_note(index);
+        // Further lines of an entry are indented under its first one
+        let line = format!(
+            "- {} {}",
+            at.format("%H:%M"),
+            entry.lines().collect::<Vec<_>>().join("\n  ")
+        );
+        let content = if note.get_content().is_empty() {
+            line
+        } else {
+            format!("{}\n{}", note.get_content(), line)
+        };
+        note_taker.edit_note(index, note.get_title().to_string(), content)?;
+        self.find(note_taker, at.date())
+            .ok_or("Note not found".to_string())
+    }
+
+    fn days(&self, note_taker: &NoteTaker) -> Vec<NaiveDate> {
+        let mut days: Vec<NaiveDate> = note_taker
+            .get_all_notes()
+            .iter()
+            .filter_map(|note| self.date_of(note.get_title()))
+            .collect();
+        days.sort();
+        days.dedup();
+        days
+    }
+
+    /// Returns the closest day before `date` with a journal note.
+    pub fn previous(&self, note_taker: &NoteTaker, date: NaiveDate) -> Option<NaiveDate> {
+        self.days(note_taker)
+            .into_iter()
+            .rev()
+            .find(|&day| day < date)
+    }
+
+    /// Returns the closest day after `date` with a journal note.
+    pub fn next(&self, note_taker: &NoteTaker, date: NaiveDate) -> Option<NaiveDate> {
+        self.days(note_taker).into_iter().find(|&day| day > date)
+    }
+}
+
+#[cfg(test)]
+use crate::persistence::file_persistence::FilePersistence;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_note_taker() -> NoteTaker {
+        let persistence = Box::new(FilePersistence::new("test_notes.json".to_string()));
+        NoteTaker::new(persistence)
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn date_time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_should_title_notes_from_format() {
+        let journal = DailyJournal::new("Journal %d/%m/%Y").unwrap();
+
+        assert_eq!(journal.title(date("2025-03-04")), "Journal 04/03/2025");
+        assert_eq!(
+            journal.date_of("Journal 04/03/2025"),
+            Some(date("2025-03-04"))
+        );
+        assert_eq!(journal.date_of("Groceries"), None);
+        assert!(DailyJournal::new("%Y-%m").is_err());
+        assert!(DailyJournal::new("%Q").is_err());
+    }
+
+    #[test]
+    fn test_should_append_timestamped_entries_to_day_note() {
+        let mut note_taker = setup_note_taker();
+        let journal = DailyJournal::new(DEFAULT_TITLE_FORMAT).unwrap();
+
+        journal
+            .append(&mut note_taker, "Standup", date_time("2025-03-04 09:15"))
+            .unwrap();
+        let index = journal
+            .append(
+                &mut note_taker,
+                "Lunch\nwith the team",
+                date_time("2025-03-04 12:30"),
+            )
+            .unwrap();
+
+        assert_eq!(note_taker.get_size(), 1);
+        let note = note_taker.get_note(index);
+        assert_eq!(note.get_title(), "2025-03-04");
+        assert_eq!(note.get_date_time(), "2025-03-04 00:00");
+        assert_eq!(
+            note.get_content(),
+            "- 09:15 Standup\n- 12:30 Lunch\n  with the team"
+        );
+        assert!(
+            journal
+                .append(&mut note_taker, " ", date_time("2025-03-04 13:00"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_should_navigate_between_days() {
+        let mut note_taker = setup_note_taker();
+        let journal = DailyJournal::new(DEFAULT_TITLE_FORMAT).unwrap();
+        for day in ["2025-03-01", "2025-03-04", "2025-03-10"] {
+            journal.open(&mut note_taker, date(day)).unwrap();
+        }
+        note_taker.add_note(Note::new("Not a day".to_string(), String::new()).unwrap());
+
+        assert_eq!(
+            journal.previous(&note_taker, date("2025-03-04")),
+            Some(date("2025-03-01"))
+        );
+        assert_eq!(
+            journal.next(&note_taker, date("2025-03-05")),
+            Some(date("2025-03-10"))
+        );
+        assert_eq!(journal.previous(&note_taker, date("2025-03-01")), None);
+        assert_eq!(journal.next(&note_taker, date("2025-03-10")), None);
+        assert_eq!(journal.open(&mut note_taker, date("2025-03-04")), Ok(1));
+        assert_eq!(note_taker.get_size(), 4);
+    }
+}
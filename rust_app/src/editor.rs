@@ -0,0 +1,132 @@
+use crate::note::Note;
+
+use std::fs;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What came out of editing a note in an external editor.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Edited {
+        title: String,
+        content: String,
+    },
+    /// The note was saved as it was.
+    Unchanged,
+    /// The editor failed, or the note was emptied.
+    Aborted,
+}
+
+/// Returns the editor to use from `$VISUAL` or `$EDITOR`, if either is set.
+pub fn editor_command() -> Option<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+}
+
+/// Writes a note for editing: the title on the first line, then a blank
+/// line and the content.
+pub fn to_template(title: &str, content: &str) -> String {
+    if content.is_empty() {
+        format!("{}\n\n", title)
+    } else {
+        format!("{}\n\n{}\n", title, content)
+    }
+}
+
+/// Reads a title and content back from an edited template, or `None` when
+/// nothing is left in it.
+pub fn from_template(text: &str) -> Option<(String, String)> {
+    let text = text.trim_start_matches(['\n', '\r']);
+    if text.trim().is_empty() {
+        return None;
+    }
+    let (title, content) = text.split_once('\n').unwrap_or((text, ""));
+    Some((title.trim().to_string(), content.trim().to_string()))
+}
+
+/// Opens `title` and `content` in `editor` and waits for it to exit. The
+/// editor may carry arguments, such as `code --wait`.
+pub fn edit(editor: &str, title: &str, content: &str) -> Result<Outcome, String> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or("No editor configured")?;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    let path = std::env::temp_dir().join(format!("note_taker_{}_{}.md", std::process::id(), nanos));
+    fs::write(&path, to_template(title, content)).map_err(|e| e.to_string())?;
+
+    let status = Command::new(program).args(words).arg(&path).status();
+    let text = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    let status = status.map_err(|e| format!("Could not run {}: {}", program, e))?;
+    if !status.success() {
+        return Ok(Outcome::Aborted);
+    }
+    let text = text.map_err(|e| e.to_string())?;
+
+    Ok(match from_template(&text) {
+        None => Outcome::Aborted,
+        Some((new_title, new_content))
+            if new_title == title.trim() && new_content == content.trim() =>
+        {
+            Outcome::Unchanged
+        }
+        Some((title, content)) => Outcome::Edited { title, content },
+    })
+}
+
+/// Writes a new note in `editor`, returning `None` if it was left empty.
+pub fn compose(editor: &str) -> Result<Option<Note>, String> {
+    match edit(editor, "", "")? {
+        Outcome::Edited { title, content } => Note::new(title, content).map(Some),
+        Outcome::Unchanged | Outcome::Aborted => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_should_round_trip() {
+        let template = to_template("Title", "Line 1\n\nLine 2");
+
+        assert_eq!(template, "Title\n\nLine 1\n\nLine 2\n");
+        assert_eq!(
+            from_template(&template),
+            Some(("Title".to_string(), "Line 1\n\nLine 2".to_string()))
+        );
+        assert_eq!(
+            from_template("\nOnly title"),
+            Some(("Only title".to_string(), String::new()))
+        );
+        assert_eq!(from_template(" \n\n "), None);
+    }
+
+    #[test]
+    fn test_should_detect_edited_unchanged_and_aborted() {
+        assert_eq!(
+            edit("sed -i s/Draft/Final/", "Draft", "Draft body"),
+            Ok(Outcome::Edited {
+                title: "Final".to_string(),
+                content: "Final body".to_string()
+            })
+        );
+        assert_eq!(edit("true", "Title", "Body"), Ok(Outcome::Unchanged));
+        assert_eq!(edit("false", "Title", "Body"), Ok(Outcome::Aborted));
+        assert_eq!(edit("sed -i d", "Title", "Body"), Ok(Outcome::Aborted));
+        assert!(edit("note_taker_missing_editor", "Title", "Body").is_err());
+    }
+
+    #[test]
+    fn test_should_compose_new_note() {
+        let note = compose("sed -i 1s/^/Written/").unwrap().unwrap();
+        assert_eq!(note.get_title(), "Written");
+        assert_eq!(note.get_content(), "");
+
+        assert_eq!(compose("true"), Ok(None));
+    }
+}
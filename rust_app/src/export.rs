@@ -0,0 +1,351 @@
+use crate::calendar;
+use crate::front_matter;
+use crate::links;
+use crate::markdown::{escape_html, render_note_as_html};
+use crate::note::Note;
+use crate::persistence::vault_persistence::VaultPersistence;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CSV_HEADER: &str = "title,content,date_time,tags,pinned,archived";
+
+const SITE_STYLE: &str = "body{font-family:sans-serif;max-width:48rem;margin:2rem auto;padding:0 1rem;line-height:1.5}\
+time,.tags{color:#666}pre{background:#f4f4f4;padding:.5rem;overflow:auto}\
+ul.notes{list-style:none;padding:0}ul.notes li{margin:.5rem 0}";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// All notes in a single Markdown document.
+    Markdown,
+    /// One Markdown file with front matter per note.
+    MarkdownFiles,
+    Csv,
+    /// A static HTML site with an index page and a page per note.
+    Html,
+    /// An iCalendar feed with a journal entry or to-do per note.
+    Ics,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Format, String> {
+        match name.trim().to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Format::Markdown),
+            "files" => Ok(Format::MarkdownFiles),
+            "csv" => Ok(Format::Csv),
+            "html" | "site" => Ok(Format::Html),
+            "ics" | "ical" | "calendar" => Ok(Format::Ics),
+            other => Err(format!("Unknown export format: {}", other)),
+        }
+    }
+}
+
+/// Writes the notes to `path`, a file for single-document formats or a
+/// directory otherwise, returning the paths of the files written.
+pub fn export(notes: &[Note], format: Format, path: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(path);
+    match format {
+        Format::Markdown => write_file(path, &to_markdown_document(notes)),
+        Format::Csv => write_file(path, &to_csv(notes)),
+        Format::MarkdownFiles => write_markdown_files(notes, path),
+        Format::Html => write_site(notes, path),
+        Format::Ics => write_file(path, &calendar::to_ics(notes, chrono::Utc::now())),
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<Vec<PathBuf>, String> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, content).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(vec![path.to_path_buf()])
+}
+
+// Locked notes are exported without their encrypted content
+fn readable_content(note: &Note) -> &str {
+    if note.is_locked() {
+        "(locked)"
+    } else {
+        note.get_content()
+    }
+}
+
+/// Renders the notes as one Markdown document with a section per note.
+pub fn to_markdown_document(notes: &[Note]) -> String {
+    let mut output = String::from("# Notes\n");
+    for note in notes {
+        output.push_str(&format!(
+            "\n## {}\n\n*{}*",
+            note.get_title(),
+            note.get_date_time()
+        ));
+        if !note.get_tags().is_empty() {
+            output.push_str(&format!(" · Tags: {}", note.get_tags().join(", ")));
+        }
+        output.push_str("\n\n");
+        if !readable_content(note).is_empty() {
+            output.push_str(readable_content(note));
+            output.push_str("\n\n");
+        }
+        output.push_str("---\n");
+    }
+    output
+}
+
+/// Renders the notes as CSV, quoting fields as RFC 4180 requires.
+pub fn to_csv(notes: &[Note]) -> String {
+    let mut output = String::from(CSV_HEADER);
+    output.push_str("\r\n");
+    for note in notes {
+        let fields = [
+            note.get_title().to_string(),
+            readable_content(note).to_string(),
+            note.get_date_time(),
+            note.get_tags().join(","),
+            note.is_pinned().to_string(),
+            note.is_archived().to_string(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        output.push_str(&fields.join(","));
+        output.push_str("\r\n");
+    }
+    output
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Gives each note a distinct file name from `name`, numbering repeated names
+// from 2 on with `numbered`
+fn unique_names(
+    notes: &[Note],
+    name: impl Fn(&Note) -> String,
+    numbered: impl Fn(&str, usize) -> String,
+) -> Vec<String> {
+    let mut taken = HashSet::new();
+    notes
+        .iter()
+        .map(|note| {
+            let base = name(note);
+            let mut candidate = base.clone();
+            let mut n = 2;
+            while !taken.insert(candidate.to_lowercase()) {
+                candidate = numbered(&base, n);
+                n += 1;
+            }
+            candidate
+        })
+        .collect()
+}
+
+fn write_markdown_files(notes: &[Note], directory: &Path) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+    let names = unique_names(
+        notes,
+        |note| VaultPersistence::file_stem(note.get_title()),
+        |name, n| format!("{} ({})", name, n),
+    );
+    let mut written = Vec::new();
+    for (note, name) in notes.iter().zip(names) {
+        let path = directory.join(format!("{}.md", name));
+        fs::write(&path, front_matter::note_to_document(note)).map_err(|e| e.to_string())?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn html_page(title: &str, body: &str, style_path: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<link rel=\"stylesheet\" href=\"{}\">\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        style_path,
+        body
+    )
+}
+
+/// Writes `index.html` listing the notes and `notes/<id>.html` for each note,
+/// with links between notes pointing at their pages.
+fn write_site(notes: &[Note], directory: &Path) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(directory.join("notes")).map_err(|e| e.to_string())?;
+    let pages = unique_names(notes, |note| note.get_id(), |id, n| format!("{}-{}", id, n));
+    // Like `NoteTaker::resolve_link`, links match titles before IDs
+    let mut targets: HashMap<String, &str> = HashMap::new();
+    for (note, page) in notes.iter().zip(&pages).rev() {
+        targets.insert(note.get_id(), page);
+    }
+    for (note, page) in notes.iter().zip(&pages).rev() {
+        targets.insert(links::normalize(note.get_title()), page);
+    }
+
+    let mut written = Vec::new();
+    let mut write = |path: PathBuf, content: String| -> Result<(), String> {
+        fs::write(&path, content).map_err(|e| e.to_string())?;
+        written.push(path);
+        Ok(())
+    };
+    write(directory.join("style.css"), SITE_STYLE.to_string())?;
+
+    let mut index = String::from("<h1>Notes</h1>\n<ul class=\"notes\">\n");
+    for (note, page) in notes.iter().zip(&pages) {
+        index.push_str(&format!(
+            "<li><a href=\"notes/{}.html\">{}</a> <time>{}</time></li>\n",
+            page,
+            escape_html(note.get_title()),
+            note.get_date_time()
+        ));
+    }
+    index.push_str("</ul>\n");
+    write(
+        directory.join("index.html"),
+        html_page("Notes", &index, "style.css"),
+    )?;
+
+    for (note, page) in notes.iter().zip(&pages) {
+        let mut linked = note.clone();
+        linked.set_content(links::replace_links(note.get_content(), |target, alias| {
+            let page = targets.get(&links::normalize(target))?;
+            Some(format!(
+                "[{}]({}.html)",
+                alias.unwrap_or(target).trim(),
+                page
+            ))
+        }));
+        let mut body = String::from("<p><a href=\"../index.html\">All notes</a></p>\n");
+        body.push_str(&render_note_as_html(&linked));
+        if !note.get_tags().is_empty() {
+            body.push_str(&format!(
+                "<p class=\"tags\">Tags: {}</p>\n",
+                escape_html(&note.get_tags().join(", "))
+            ));
+        }
+        write(
+            directory.join("notes").join(format!("{}.html", page)),
+            html_page(note.get_title(), &body, "../style.css"),
+        )?;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, content: &str) -> Note {
+        Note::new_with_date_time(
+            title.to_string(),
+            content.to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap()
+    }
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("note_taker_export_{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn test_should_render_single_markdown_document() {
+        let mut tagged = note("First", "Some *content*");
+        tagged.add_tag("work").unwrap();
+
+        assert_eq!(
+            to_markdown_document(&[tagged, note("Empty", "")]),
+            "# Notes\n\n## First\n\n*2025-01-01 10:00* · Tags: work\n\nSome *content*\n\n---\n\n## Empty\n\n*2025-01-01 10:00*\n\n---\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_should_quote_fields() {
+        let mut tagged = note("Quote \"this\", please", "Line 1\nLine 2");
+        tagged.add_tag("a").unwrap();
+        tagged.add_tag("b").unwrap();
+        tagged.set_pinned(true);
+
+        assert_eq!(
+            to_csv(&[tagged, note("Plain", "Text")]),
+            "title,content,date_time,tags,pinned,archived\r\n\
+             \"Quote \"\"this\"\", please\",\"Line 1\nLine 2\",2025-01-01 10:00,\"a,b\",true,false\r\n\
+             Plain,Text,2025-01-01 10:00,,false,false\r\n"
+        );
+    }
+
+    #[test]
+    fn test_should_leave_locked_content_out() {
+        let mut locked = note("Secret", "Hidden");
+        locked.lock("password").unwrap();
+
+        assert!(to_csv(&[locked.clone()]).contains("Secret,(locked),"));
+        assert!(to_markdown_document(&[locked]).contains("\n\n(locked)\n\n"));
+    }
+
+    #[test]
+    fn test_should_write_one_file_per_note() {
+        let directory = temp_directory("files");
+        let notes = [note("Same", "One"), note("same", "Two"), note("a/b", "")];
+
+        let written = export(&notes, Format::MarkdownFiles, directory.to_str().unwrap()).unwrap();
+
+        let names: Vec<String> = written
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["Same.md", "same (2).md", "a-b.md"]);
+        let loaded = front_matter::note_from_document(
+            &fs::read_to_string(&written[1]).unwrap(),
+            "",
+            chrono::Local::now().naive_local(),
+        )
+        .unwrap();
+        assert_eq!(loaded, notes[1]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_write_static_site() {
+        let directory = temp_directory("site");
+        let notes = [
+            note("Home <page>", "See [[Other|the other]] and [[Missing]]"),
+            note("Other", "# Heading"),
+        ];
+
+        let written = export(&notes, Format::Html, directory.to_str().unwrap()).unwrap();
+
+        assert_eq!(written.len(), 4);
+        let index = fs::read_to_string(directory.join("index.html")).unwrap();
+        let home_page = format!("notes/{}.html", notes[0].get_id());
+        let other_page = format!("{}.html", notes[1].get_id());
+        assert!(index.starts_with("<!DOCTYPE html>"));
+        assert!(index.contains(&format!(
+            "<li><a href=\"{}\">Home &lt;page&gt;</a>",
+            home_page
+        )));
+
+        let home = fs::read_to_string(directory.join(&home_page)).unwrap();
+        assert!(home.contains("<title>Home &lt;page&gt;</title>"));
+        assert!(home.contains(&format!("<a href=\"{}\">the other</a>", other_page)));
+        assert!(home.contains("[[Missing]]"));
+        assert!(home.contains("<a href=\"../index.html\">All notes</a>"));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_parse_format_names() {
+        assert_eq!(Format::parse(" CSV "), Ok(Format::Csv));
+        assert_eq!(Format::parse("site"), Ok(Format::Html));
+        assert_eq!(Format::parse("ical"), Ok(Format::Ics));
+        assert!(Format::parse("pdf").is_err());
+    }
+}
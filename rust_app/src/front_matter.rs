@@ -0,0 +1,288 @@
+use crate::markdown;
+use crate::note::Note;
+use crate::reminders::Reminder;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+/// Splits a Markdown document into its YAML front matter, if it has valid
+/// front matter, and its body.
+pub fn split(text: &str) -> (Option<Yaml>, &str) {
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (None, text);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            let yaml = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+            return match YamlLoader::load_from_str(yaml) {
+                Ok(docs) => match docs.into_iter().next() {
+                    Some(doc @ Yaml::Hash(_)) => (Some(doc), body),
+                    None => (Some(Yaml::Hash(Hash::new())), body),
+                    Some(_) => (None, text),
+                },
+                Err(_) => (None, text),
+            };
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+/// Renders a note as Markdown with its title, date time, states and tags in
+/// YAML front matter and its content as the body.
+pub fn note_to_document(note: &Note) -> String {
+    let mut front_matter = Hash::new();
+    front_matter.insert(
+        Yaml::String("title".to_string()),
+        Yaml::String(note.get_title().to_string()),
+    );
+    front_matter.insert(
+        Yaml::String("date_time".to_string()),
+        Yaml::String(note.get_date_time()),
+    );
+    front_matter.insert(
+        Yaml::String("archived".to_string()),
+        Yaml::Boolean(note.is_archived()),
+    );
+    front_matter.insert(
+        Yaml::String("pinned".to_string()),
+        Yaml::Boolean(note.is_pinned()),
+    );
+    front_matter.insert(
+        Yaml::String("tags".to_string()),
+        Yaml::Array(
+            note.get_tags()
+                .iter()
+                .map(|tag| Yaml::String(tag.clone()))
+                .collect(),
+        ),
+    );
+    if let Some(reminder) = note.get_reminder() {
+        front_matter.insert(
+            Yaml::String("reminder".to_string()),
+            Yaml::String(reminder.to_string()),
+        );
+    }
+    front_matter.insert(
+        Yaml::String("uid".to_string()),
+        Yaml::String(note.get_uid().to_string()),
+    );
+
+    let mut output = String::new();
+    YamlEmitter::new(&mut output)
+        .dump(&Yaml::Hash(front_matter))
+        .unwrap();
+    output.push_str("\n---\n");
+    if !note.get_content().is_empty() {
+        output.push_str(note.get_content());
+        output.push('\n');
+    }
+    output
+}
+
+/// Reads a note from a Markdown document. The title comes from the front
+/// matter, else the first heading, else `fallback_title`; the date time comes
+/// from the front matter, else `fallback_date_time`.
+pub fn note_from_document(
+    text: &str,
+    fallback_title: &str,
+    fallback_date_time: NaiveDateTime,
+) -> Result<Note, String> {
+    let (front_matter, body) = split(text);
+    let front_matter = front_matter.unwrap_or(Yaml::Hash(Hash::new()));
+
+    let title = front_matter["title"]
+        .as_str()
+        .map(|title| title.to_string())
+        .filter(|title| !title.trim().is_empty())
+        .or_else(|| {
+            markdown::extract(body)
+                .headings
+                .into_iter()
+                .next()
+                .map(|heading| heading.text)
+                .filter(|title| !title.trim().is_empty())
+        })
+        .unwrap_or_else(|| fallback_title.to_string());
+
+    let date_time = ["date_time", "date", "created"]
+        .iter()
+        .find_map(|key| parse_date_time(&front_matter[*key]))
+        .unwrap_or(fallback_date_time);
+
+    let mut note = Note::new_with_date_time(
+        title,
+        body.to_string(),
+        date_time.format("%Y-%m-%d %H:%M").to_string(),
+    )?;
+    note.set_archived(front_matter["archived"].as_bool().unwrap_or(false));
+    note.set_pinned(front_matter["pinned"].as_bool().unwrap_or(false));
+    for tag in get_tags(&front_matter["tags"]) {
+        // Tags written by other tools may contain spaces, which notes do not allow
+        let tag = tag.trim().replace(char::is_whitespace, "-");
+        let _ = note.add_tag(tag.trim_start_matches('#'));
+    }
+    if let Some(reminder) = front_matter["reminder"].as_str() {
+        note.set_reminder(Some(Reminder::parse(reminder)?));
+    }
+    note.set_uid(front_matter["uid"].as_str().unwrap_or_default());
+    Ok(note)
+}
+
+fn parse_date_time(value: &Yaml) -> Option<NaiveDateTime> {
+    let value = value.as_str()?.trim();
+    DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        // Joplin writes UTC times such as `2024-03-04 05:06:07Z`
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(&value.replacen(' ', "T", 1))
+                .ok()
+                .map(|date_time| date_time.with_timezone(&Local).naive_local())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+// Tags are either a YAML list or a single comma or space separated string
+fn get_tags(value: &Yaml) -> Vec<String> {
+    match value {
+        Yaml::Array(tags) => tags
+            .iter()
+            .filter_map(|tag| tag.as_str())
+            .map(|tag| tag.to_string())
+            .collect(),
+        Yaml::String(tags) => tags
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fallback_date_time() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2000-01-01 00:00", "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_should_split_front_matter_from_body() {
+        let (front_matter, body) = split("---\ntitle: Hello\n---\nBody\n");
+        assert_eq!(front_matter.unwrap()["title"].as_str(), Some("Hello"));
+        assert_eq!(body, "Body\n");
+    }
+
+    #[test]
+    fn test_should_keep_documents_without_front_matter_whole() {
+        assert_eq!(split("Body\n---\n"), (None, "Body\n---\n"));
+        assert_eq!(
+            split("---\nnot: [closed\n---\nBody"),
+            (None, "---\nnot: [closed\n---\nBody")
+        );
+        assert_eq!(split("---\nnever closed\n"), (None, "---\nnever closed\n"));
+    }
+
+    #[test]
+    fn test_note_should_round_trip_through_document() {
+        let mut note = Note::new_with_date_time(
+            "Title: with \"quotes\"".to_string(),
+            "# Heading\n\nBody".to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+        note.set_pinned(true);
+        note.add_tag("work").unwrap();
+        note.set_reminder(Some(
+            Reminder::parse("2025-01-02 09:00 cron 0 9 * * 1-5").unwrap(),
+        ));
+
+        let document = note_to_document(&note);
+        let loaded = note_from_document(&document, "fallback", fallback_date_time()).unwrap();
+
+        assert!(document.starts_with("---\ntitle: "));
+        assert!(document.ends_with("---\n# Heading\n\nBody\n"));
+        assert_eq!(loaded, note);
+        assert!(loaded.is_pinned());
+        assert!(!loaded.is_archived());
+        assert_eq!(loaded.get_tags(), ["work"]);
+        assert_eq!(loaded.get_reminder(), note.get_reminder());
+    }
+
+    #[test]
+    fn test_title_should_fall_back_to_heading_then_given_title() {
+        let from_heading =
+            note_from_document("Intro\n\n## The Heading\n", "file", fallback_date_time()).unwrap();
+        let from_fallback = note_from_document("No heading", "file", fallback_date_time()).unwrap();
+
+        assert_eq!(from_heading.get_title(), "The Heading");
+        assert_eq!(from_fallback.get_title(), "file");
+        assert_eq!(from_fallback.get_date_time(), "2000-01-01 00:00");
+    }
+
+    #[test]
+    fn test_should_read_front_matter_written_by_other_tools() {
+        let note = note_from_document(
+            "---\ntitle: Meeting\ncreated: 2024-03-04T05:06:07\ntags: [project x, \"#work\"]\n---\nBody",
+            "file",
+            fallback_date_time(),
+        )
+        .unwrap();
+
+        assert_eq!(note.get_title(), "Meeting");
+        assert_eq!(note.get_date_time(), "2024-03-04 05:06");
+        assert_eq!(note.get_tags(), ["project-x", "work"]);
+    }
+
+    #[test]
+    fn test_should_read_date_only_and_string_tags() {
+        let note = note_from_document(
+            "---\ndate: 2024-03-04\ntags: work, home\n---\n# Title\n",
+            "file",
+            fallback_date_time(),
+        )
+        .unwrap();
+
+        assert_eq!(note.get_date_time(), "2024-03-04 00:00");
+        assert_eq!(note.get_tags(), ["home", "work"]);
+    }
+
+    #[test]
+    fn test_should_read_date_times_with_time_zone() {
+        let note = note_from_document(
+            "---\ncreated: 2024-03-04 05:06:07Z\n---\nBody",
+            "file",
+            fallback_date_time(),
+        )
+        .unwrap();
+        let expected = DateTime::parse_from_rfc3339("2024-03-04T05:06:07Z")
+            .unwrap()
+            .with_timezone(&Local);
+
+        assert_eq!(
+            note.get_date_time(),
+            expected.format("%Y-%m-%d %H:%M").to_string()
+        );
+    }
+}
@@ -0,0 +1,384 @@
+use crate::markdown::escape_html;
+use crate::note::Note;
+use crate::note_taker::NoteTaker;
+use crate::templates::TEMPLATE_TAG;
+
+use std::collections::{BTreeSet, VecDeque};
+
+// Words too common to relate two notes by their titles
+const STOP_WORDS: [&str; 12] = [
+    "the", "and", "for", "with", "from", "into", "about", "that", "this", "are", "was", "not",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EdgeKind {
+    /// A `[[Title]]` reference from one note to another.
+    Link,
+    /// Words found in the titles of both notes.
+    SharedWords(Vec<String>),
+}
+
+/// An edge between two nodes, given by their index in `Graph::nodes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// The notes of a `NoteTaker` as nodes, with the references between them as edges.
+pub struct Graph {
+    pub nodes: Vec<Note>,
+    /// The note taker index of each node.
+    pub indexes: Vec<usize>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Builds the graph of every note, archived ones included and templates
+    /// left out.
+    pub fn from_note_taker(note_taker: &NoteTaker) -> Self {
+        let indexes: Vec<usize> = (0..note_taker.get_size())
+            .filter(|&i| !note_taker.get_note(i).has_tag(TEMPLATE_TAG))
+            .collect();
+        let nodes: Vec<Note> = indexes.iter().map(|&i| note_taker.get_note(i)).collect();
+        let mut edges = Vec::new();
+
+        for from in 0..nodes.len() {
+            let targets: BTreeSet<usize> = note_taker
+                .get_links(indexes[from])
+                .into_iter()
+                .filter_map(|(_, resolved)| resolved)
+                .filter_map(|resolved| indexes.iter().position(|&i| i == resolved))
+                .filter(|&to| to != from)
+                .collect();
+            for to in targets {
+                edges.push(Edge {
+                    from,
+                    to,
+                    kind: EdgeKind::Link,
+                });
+            }
+        }
+
+        let title_words: Vec<BTreeSet<String>> = nodes
+            .iter()
+            .map(|note| title_words(note.get_title()))
+            .collect();
+        for from in 0..nodes.len() {
+            for to in from + 1..nodes.len() {
+                let shared: Vec<String> = title_words[from]
+                    .intersection(&title_words[to])
+                    .cloned()
+                    .collect();
+                if !shared.is_empty() {
+                    edges.push(Edge {
+                        from,
+                        to,
+                        kind: EdgeKind::SharedWords(shared),
+                    });
+                }
+            }
+        }
+
+        Graph {
+            nodes,
+            indexes,
+            edges,
+        }
+    }
+
+    /// Returns the node of the note at a note taker index, if it is in the graph.
+    pub fn get_node(&self, index: usize) -> Option<usize> {
+        self.indexes.iter().position(|&i| i == index)
+    }
+
+    /// Returns the nodes with no edge of any kind.
+    pub fn get_orphans(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&node| {
+                !self
+                    .edges
+                    .iter()
+                    .any(|edge| edge.from == node || edge.to == node)
+            })
+            .collect()
+    }
+
+    /// Returns up to `limit` nodes with the most incoming links, most linked first.
+    pub fn get_most_linked(&self, limit: usize) -> Vec<(usize, usize)> {
+        let mut counts = vec![0; self.nodes.len()];
+        for edge in &self.edges {
+            if edge.kind == EdgeKind::Link {
+                counts[edge.to] += 1;
+            }
+        }
+        let mut result: Vec<(usize, usize)> = counts
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        result.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        result.truncate(limit);
+        result
+    }
+
+    /// Returns the nodes on a shortest path between two nodes, both included,
+    /// following edges of any kind in either direction.
+    pub fn get_shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        if from >= self.nodes.len() || to >= self.nodes.len() {
+            return None;
+        }
+        let mut previous: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut visited = vec![false; self.nodes.len()];
+        let mut queue = VecDeque::from([from]);
+        visited[from] = true;
+
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![to];
+                while let Some(prev) = previous[*path.last().unwrap()] {
+                    path.push(prev);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for edge in &self.edges {
+                let next = if edge.from == node {
+                    edge.to
+                } else if edge.to == node {
+                    edge.from
+                } else {
+                    continue;
+                };
+                if !visited[next] {
+                    visited[next] = true;
+                    previous[next] = Some(node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Renders the graph in Graphviz DOT, links as arrows and shared title
+    /// words as dashed undirected edges.
+    pub fn to_dot(&self) -> String {
+        let mut output = String::from("digraph notes {\n");
+        for (i, note) in self.nodes.iter().enumerate() {
+            output.push_str(&format!(
+                "    n{} [label=\"{}\"];\n",
+                i,
+                escape_dot(note.get_title())
+            ));
+        }
+        for edge in &self.edges {
+            match &edge.kind {
+                EdgeKind::Link => {
+                    output.push_str(&format!("    n{} -> n{};\n", edge.from, edge.to));
+                }
+                EdgeKind::SharedWords(words) => {
+                    output.push_str(&format!(
+                        "    n{} -> n{} [dir=none, style=dashed, label=\"{}\"];\n",
+                        edge.from,
+                        edge.to,
+                        escape_dot(&words.join(", "))
+                    ));
+                }
+            }
+        }
+        output.push_str("}\n");
+        output
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let mut output = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+             \x20 <key id=\"title\" for=\"node\" attr.name=\"title\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"date_time\" for=\"node\" attr.name=\"date_time\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"words\" for=\"edge\" attr.name=\"words\" attr.type=\"string\"/>\n\
+             \x20 <graph id=\"notes\" edgedefault=\"directed\">\n",
+        );
+        for (i, note) in self.nodes.iter().enumerate() {
+            output.push_str(&format!(
+                "    <node id=\"n{}\">\n      <data key=\"title\">{}</data>\n      <data key=\"date_time\">{}</data>\n    </node>\n",
+                i,
+                escape_html(note.get_title()),
+                note.get_date_time()
+            ));
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            let data = match &edge.kind {
+                EdgeKind::Link => "      <data key=\"kind\">link</data>\n".to_string(),
+                EdgeKind::SharedWords(words) => format!(
+                    "      <data key=\"kind\">shared_words</data>\n      <data key=\"words\">{}</data>\n",
+                    escape_html(&words.join(" "))
+                ),
+            };
+            let directed = if edge.kind == EdgeKind::Link {
+                ""
+            } else {
+                " directed=\"false\""
+            };
+            output.push_str(&format!(
+                "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\"{}>\n{}    </edge>\n",
+                i, edge.from, edge.to, directed, data
+            ));
+        }
+        output.push_str("  </graph>\n</graphml>\n");
+        output
+    }
+}
+
+fn title_words(title: &str) -> BTreeSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .map(|word| word.to_lowercase())
+        .filter(|word| word.chars().count() >= 3 && !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+use crate::persistence::file_persistence::FilePersistence;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, content: &str) -> Note {
+        Note::new_with_date_time(
+            title.to_string(),
+            content.to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap()
+    }
+
+    // Notes are sorted by title here, so node indexes follow this order
+    fn setup_graph() -> Graph {
+        let persistence = Box::new(FilePersistence::new("test_notes.json".to_string()));
+        let mut note_taker = NoteTaker::new(persistence);
+        note_taker.add_note(note("A", "Links to [[B]] and [[Missing]]"));
+        note_taker.add_note(note("B", "Links to [[C]]"));
+        note_taker.add_note(note("C", "Links back to [[b]]"));
+        note_taker.add_note(note("D Rust notes", ""));
+        note_taker.add_note(note("E Rust tips", ""));
+        note_taker.add_note(note("F", "Alone"));
+        Graph::from_note_taker(&note_taker)
+    }
+
+    #[test]
+    fn test_should_build_edges_from_links_and_title_words() {
+        let graph = setup_graph();
+
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(
+            graph.edges,
+            vec![
+                Edge {
+                    from: 0,
+                    to: 1,
+                    kind: EdgeKind::Link
+                },
+                Edge {
+                    from: 1,
+                    to: 2,
+                    kind: EdgeKind::Link
+                },
+                Edge {
+                    from: 2,
+                    to: 1,
+                    kind: EdgeKind::Link
+                },
+                Edge {
+                    from: 3,
+                    to: 4,
+                    kind: EdgeKind::SharedWords(vec!["rust".to_string()])
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_should_find_orphans() {
+        assert_eq!(setup_graph().get_orphans(), vec![5]);
+    }
+
+    #[test]
+    fn test_should_find_most_linked() {
+        let graph = setup_graph();
+        assert_eq!(graph.get_most_linked(10), vec![(1, 2), (2, 1)]);
+        assert_eq!(graph.get_most_linked(1), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_should_find_shortest_path() {
+        let graph = setup_graph();
+        assert_eq!(graph.get_shortest_path(0, 2), Some(vec![0, 1, 2]));
+        assert_eq!(graph.get_shortest_path(2, 0), Some(vec![2, 1, 0]));
+        assert_eq!(graph.get_shortest_path(3, 4), Some(vec![3, 4]));
+        assert_eq!(graph.get_shortest_path(0, 5), None);
+        assert_eq!(graph.get_shortest_path(0, 99), None);
+    }
+
+    #[test]
+    fn test_should_leave_out_templates() {
+        let persistence = Box::new(FilePersistence::new("test_notes.json".to_string()));
+        let mut note_taker = NoteTaker::new(persistence);
+        let mut template = note("B Rust template", "Links to [[C]]");
+        template.add_tag(TEMPLATE_TAG).unwrap();
+        note_taker.add_note(note("A Rust notes", "Links to [[B Rust template]]"));
+        note_taker.add_note(template);
+        note_taker.add_note(note("C", "Alone"));
+        let graph = Graph::from_note_taker(&note_taker);
+
+        assert_eq!(graph.indexes, vec![0, 2]);
+        assert!(graph.edges.is_empty());
+        assert_eq!(graph.get_node(2), Some(1));
+        assert_eq!(graph.get_node(1), None);
+        assert_eq!(graph.get_orphans(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_should_export_dot() {
+        let dot = setup_graph().to_dot();
+
+        assert!(dot.starts_with("digraph notes {\n"));
+        assert!(dot.contains("    n0 [label=\"A\"];\n"));
+        assert!(dot.contains("    n0 -> n1;\n"));
+        assert!(dot.contains("    n3 -> n4 [dir=none, style=dashed, label=\"rust\"];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_dot_should_escape_titles() {
+        assert_eq!(escape_dot(r#"Say "hi" \ bye"#), r#"Say \"hi\" \\ bye"#);
+    }
+
+    #[test]
+    fn test_should_export_graphml() {
+        let graphml = setup_graph().to_graphml();
+
+        assert!(graphml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml"));
+        assert!(graphml.contains("<node id=\"n5\">\n      <data key=\"title\">F</data>"));
+        assert!(graphml.contains("<edge id=\"e0\" source=\"n0\" target=\"n1\">"));
+        assert!(graphml.contains(
+            "<edge id=\"e3\" source=\"n3\" target=\"n4\" directed=\"false\">\n      <data key=\"kind\">shared_words</data>\n      <data key=\"words\">rust</data>"
+        ));
+        assert!(graphml.ends_with("  </graph>\n</graphml>\n"));
+    }
+
+    #[test]
+    fn test_title_words_should_skip_short_and_common_words() {
+        let words: Vec<String> = title_words("The Rust book, and a guide!")
+            .into_iter()
+            .collect();
+        assert_eq!(words, vec!["book", "guide", "rust"]);
+    }
+}
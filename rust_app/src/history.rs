@@ -0,0 +1,259 @@
+use crate::note::Note;
+
+use std::collections::VecDeque;
+
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/// A reversible change to the collection of notes.
+///
+/// Every `NoteTaker` operation is recorded as one of these: adding a note is an
+/// `Insert`, deleting one is a `Remove`, and editing, tagging, moving, pinning or
+/// archiving one is a `Replace` of the old version by the new one. Operations
+/// touching several notes at once are a `Batch`, undone as a single step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Insert(Note),
+    Remove(Note),
+    Replace { before: Note, after: Note },
+    Batch(Vec<Command>),
+}
+
+impl Command {
+    /// Returns the command that undoes this one.
+    pub fn inverse(&self) -> Command {
+        match self {
+            Command::Insert(note) => Command::Remove(note.clone()),
+            Command::Remove(note) => Command::Insert(note.clone()),
+            Command::Replace { before, after } => Command::Replace {
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Command::Batch(commands) => {
+                Command::Batch(commands.iter().rev().map(Command::inverse).collect())
+            }
+        }
+    }
+
+    /// Returns the command as JSON, e.g. to write it to a journal.
+    pub fn to_json_value(&self) -> serde_json::Value {
+        match self {
+            Command::Insert(note) => serde_json::json!({ "insert": note.to_json_value() }),
+            Command::Remove(note) => serde_json::json!({ "remove": note.to_json_value() }),
+            Command::Replace { before, after } => serde_json::json!({
+                "replace": { "before": before.to_json_value(), "after": after.to_json_value() }
+            }),
+            Command::Batch(commands) => serde_json::json!({
+                "batch": commands.iter().map(Command::to_json_value).collect::<Vec<_>>()
+            }),
+        }
+    }
+
+    pub fn from_json_value(json_value: &serde_json::Value) -> Result<Command, String> {
+        if let Some(note) = json_value.get("insert") {
+            Ok(Command::Insert(Note::from_json_value(note)?))
+        } else if let Some(note) = json_value.get("remove") {
+            Ok(Command::Remove(Note::from_json_value(note)?))
+        } else if let Some(replace) = json_value.get("replace") {
+            Ok(Command::Replace {
+                before: Note::from_json_value(&replace["before"])?,
+                after: Note::from_json_value(&replace["after"])?,
+            })
+        } else if let Some(commands) = json_value.get("batch").and_then(|batch| batch.as_array()) {
+            commands
+                .iter()
+                .map(Command::from_json_value)
+                .collect::<Result<Vec<_>, String>>()
+                .map(Command::Batch)
+        } else {
+            Err(format!("Unknown command: {}", json_value))
+        }
+    }
+}
+
+/// Bounded undo and redo stacks of commands.
+pub struct History {
+    undo_stack: VecDeque<Command>,
+    redo_stack: Vec<Command>,
+    depth: usize,
+}
+
+impl History {
+    pub fn new(depth: usize) -> Self {
+        History {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            depth,
+        }
+    }
+
+    /// Records a command that was just applied, forgetting the oldest one when
+    /// the history is full and discarding anything that could be redone.
+    pub fn record(&mut self, command: Command) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo_stack.len() == self.depth {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(command);
+        self.redo_stack.clear();
+    }
+
+    /// Returns the command `undo` would take, so the caller can revert it
+    /// before changing the history.
+    pub fn peek_undo(&self) -> Option<&Command> {
+        self.undo_stack.back()
+    }
+
+    /// Returns the command `redo` would take.
+    pub fn peek_redo(&self) -> Option<&Command> {
+        self.redo_stack.last()
+    }
+
+    /// Takes the last applied command once the caller reverted it.
+    pub fn undo(&mut self) -> Option<Command> {
+        let command = self.undo_stack.pop_back()?;
+        self.redo_stack.push(command.clone());
+        Some(command)
+    }
+
+    /// Takes the last undone command once the caller applied it again.
+    pub fn redo(&mut self) -> Option<Command> {
+        let command = self.redo_stack.pop()?;
+        self.undo_stack.push_back(command.clone());
+        Some(command)
+    }
+
+    #[allow(dead_code)]
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo_stack.len() > depth {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str) -> Note {
+        Note::new(title.to_string(), "Content".to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_inverse_of_insert_is_remove() {
+        let command = Command::Insert(note("Title"));
+        assert_eq!(command.inverse(), Command::Remove(note("Title")));
+        assert_eq!(command.inverse().inverse(), command);
+    }
+
+    #[test]
+    fn test_inverse_of_replace_swaps_versions() {
+        let command = Command::Replace {
+            before: note("Before"),
+            after: note("After"),
+        };
+        assert_eq!(
+            command.inverse(),
+            Command::Replace {
+                before: note("After"),
+                after: note("Before"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_inverse_of_batch_reverts_in_reverse_order() {
+        let command = Command::Batch(vec![
+            Command::Insert(note("Title 1")),
+            Command::Remove(note("Title 2")),
+        ]);
+        assert_eq!(
+            command.inverse(),
+            Command::Batch(vec![
+                Command::Insert(note("Title 2")),
+                Command::Remove(note("Title 1")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_commands_should_round_trip_through_json() {
+        let command = Command::Batch(vec![
+            Command::Insert(note("Title 1")),
+            Command::Replace {
+                before: note("Before"),
+                after: note("After"),
+            },
+            Command::Remove(note("Title 2")),
+        ]);
+
+        assert_eq!(
+            Command::from_json_value(&command.to_json_value()),
+            Ok(command)
+        );
+        assert!(Command::from_json_value(&serde_json::json!({ "move": {} })).is_err());
+    }
+
+    #[test]
+    fn test_undo_and_redo_should_move_commands_between_stacks() {
+        let mut history = History::new(DEFAULT_HISTORY_DEPTH);
+        history.record(Command::Insert(note("Title")));
+
+        assert_eq!(history.undo(), Some(Command::Insert(note("Title"))));
+        assert!(!history.can_undo());
+        assert_eq!(history.redo(), Some(Command::Insert(note("Title"))));
+        assert!(!history.can_redo());
+        assert!(history.can_undo());
+    }
+
+    #[test]
+    fn test_recording_should_clear_redo_stack() {
+        let mut history = History::new(DEFAULT_HISTORY_DEPTH);
+        history.record(Command::Insert(note("Title 1")));
+        history.undo();
+
+        history.record(Command::Insert(note("Title 2")));
+
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_history_should_be_bounded() {
+        let mut history = History::new(2);
+        history.record(Command::Insert(note("Title 1")));
+        history.record(Command::Insert(note("Title 2")));
+        history.record(Command::Insert(note("Title 3")));
+
+        assert_eq!(history.undo(), Some(Command::Insert(note("Title 3"))));
+        assert_eq!(history.undo(), Some(Command::Insert(note("Title 2"))));
+        assert_eq!(history.undo(), None);
+    }
+
+    #[test]
+    fn test_shrinking_depth_should_forget_oldest_commands() {
+        let mut history = History::new(DEFAULT_HISTORY_DEPTH);
+        history.record(Command::Insert(note("Title 1")));
+        history.record(Command::Insert(note("Title 2")));
+
+        history.set_depth(1);
+
+        assert_eq!(history.undo(), Some(Command::Insert(note("Title 2"))));
+        assert!(!history.can_undo());
+    }
+}
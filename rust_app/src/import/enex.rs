@@ -0,0 +1,496 @@
+use super::{ImportReport, Skipped, import_notes};
+use crate::note::Note;
+use crate::note_taker::NoteTaker;
+use crate::templates::TEMPLATE_TAG;
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use roxmltree::{Document, Node, ParsingOptions};
+
+// Evernote dates are UTC, e.g. 20240131T093000Z
+const ENEX_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+// ENML is XHTML, but its DTD is never loaded, so HTML entities are unknown to an XML parser
+const HTML_ENTITIES: [(&str, &str); 8] = [
+    ("&nbsp;", "&#160;"),
+    ("&ndash;", "&#8211;"),
+    ("&mdash;", "&#8212;"),
+    ("&hellip;", "&#8230;"),
+    ("&lsquo;", "&#8216;"),
+    ("&rsquo;", "&#8217;"),
+    ("&ldquo;", "&#8220;"),
+    ("&rdquo;", "&#8221;"),
+];
+
+fn parsing_options() -> ParsingOptions {
+    ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    }
+}
+
+/// Reads the notes of an Evernote `.enex` export, with their content converted
+/// from ENML to Markdown, their created date as date time, and their tags.
+/// Tags a note cannot have are reported as skipped after their note.
+pub fn parse_enex(xml: &str) -> Result<Vec<Result<Note, Skipped>>, String> {
+    let document = Document::parse_with_options(xml, parsing_options())
+        .map_err(|e| format!("Invalid ENEX file: {}", e))?;
+    let root = document.root_element();
+    if root.tag_name().name() != "en-export" {
+        return Err("Invalid ENEX file: missing en-export element".to_string());
+    }
+    Ok(root
+        .children()
+        .filter(|node| node.has_tag_name("note"))
+        .flat_map(parse_note)
+        .collect())
+}
+
+/// Imports an `.enex` file into the note taker, or only reports what it
+/// would import on a dry run.
+pub fn import_enex(
+    note_taker: &mut NoteTaker,
+    path: &str,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let xml = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(import_notes(note_taker, parse_enex(&xml)?, dry_run))
+}
+
+fn child_text<'a>(note: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    note.children()
+        .find(|node| node.has_tag_name(name))
+        .and_then(|node| node.text())
+}
+
+fn parse_note(node: Node) -> Vec<Result<Note, Skipped>> {
+    let mut skipped_tags = Vec::new();
+    let note = parse_note_with_tags(node, &mut skipped_tags);
+    let title = match &note {
+        Ok(note) => note.get_title().to_string(),
+        Err(_) => return vec![note],
+    };
+    std::iter::once(note)
+        .chain(skipped_tags.into_iter().map(|(tag, reason)| {
+            Err(Skipped {
+                title: title.clone(),
+                reason: format!("tag {} not imported: {}", tag, reason),
+            })
+        }))
+        .collect()
+}
+
+fn parse_note_with_tags(
+    node: Node,
+    skipped_tags: &mut Vec<(String, String)>,
+) -> Result<Note, Skipped> {
+    let title = child_text(node, "title").unwrap_or("").trim().to_string();
+    let skip = |reason: &str| Skipped {
+        title: if title.is_empty() {
+            "(untitled)".to_string()
+        } else {
+            title.clone()
+        },
+        reason: reason.to_string(),
+    };
+
+    if title.is_empty() {
+        return Err(skip("missing title"));
+    }
+    let date_time = ["created", "updated"]
+        .iter()
+        .find_map(|name| child_text(node, name).and_then(parse_enex_date_time))
+        .ok_or_else(|| skip("missing or invalid created date"))?;
+    let content = child_text(node, "content")
+        .map(enml_to_markdown)
+        .unwrap_or_default();
+
+    let mut note = Note::new_with_date_time(
+        title.clone(),
+        content,
+        date_time.format("%Y-%m-%d %H:%M").to_string(),
+    )
+    .map_err(|e| skip(&e))?;
+    for tag in node.children().filter(|node| node.has_tag_name("tag")) {
+        // Evernote tags may contain spaces, which notes do not allow
+        let tag = tag
+            .text()
+            .unwrap_or("")
+            .trim()
+            .replace(char::is_whitespace, "-")
+            .replace(',', "-");
+        // Notes only become templates through the templates command
+        if tag.is_empty() {
+            continue;
+        } else if tag == TEMPLATE_TAG {
+            skipped_tags.push((tag, "kept for templates".to_string()));
+        } else if let Err(e) = note.add_tag(&tag) {
+            skipped_tags.push((tag, e));
+        }
+    }
+    Ok(note)
+}
+
+fn parse_enex_date_time(text: &str) -> Option<NaiveDateTime> {
+    let utc = NaiveDateTime::parse_from_str(text.trim(), ENEX_DATE_TIME_FORMAT).ok()?;
+    Some(
+        Utc.from_utc_datetime(&utc)
+            .with_timezone(&Local)
+            .naive_local(),
+    )
+}
+
+/// Converts ENML note content to Markdown. Content that is not well-formed
+/// is kept as plain text with its tags stripped.
+pub fn enml_to_markdown(enml: &str) -> String {
+    let mut enml = enml.to_string();
+    for (entity, reference) in HTML_ENTITIES {
+        enml = enml.replace(entity, reference);
+    }
+    let markdown = match Document::parse_with_options(&enml, parsing_options()) {
+        Ok(document) => {
+            let mut converter = Converter::default();
+            converter.convert(document.root_element());
+            converter.output
+        }
+        Err(_) => strip_tags(&enml),
+    };
+    tidy(&markdown)
+}
+
+#[derive(Default)]
+struct Converter {
+    output: String,
+    // One entry per open list: `None` for bullets, the next number for ordered lists
+    lists: Vec<Option<usize>>,
+    // Set after a list marker, so a block inside the item stays on its line
+    in_list_item: bool,
+}
+
+impl Converter {
+    fn start_block(&mut self) {
+        if self.in_list_item {
+            return;
+        }
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+    }
+
+    fn end_block(&mut self) {
+        if !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+        self.in_list_item = false;
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let mut last_was_space = false;
+        for c in text.chars() {
+            if c.is_whitespace() && c != '\u{a0}' {
+                if !last_was_space {
+                    self.output.push(' ');
+                }
+                last_was_space = true;
+            } else {
+                self.output.push(if c == '\u{a0}' { ' ' } else { c });
+                last_was_space = false;
+            }
+        }
+        if !text.trim().is_empty() {
+            self.in_list_item = false;
+        }
+    }
+
+    fn convert_children(&mut self, node: Node) {
+        for child in node.children() {
+            self.convert(child);
+        }
+    }
+
+    fn wrap(&mut self, node: Node, marker: &str) {
+        self.output.push_str(marker);
+        self.convert_children(node);
+        self.output.push_str(marker);
+    }
+
+    fn convert(&mut self, node: Node) {
+        if node.is_text() {
+            self.push_text(node.text().unwrap_or(""));
+            return;
+        }
+        if !node.is_element() {
+            return;
+        }
+        match node.tag_name().name() {
+            "br" => self.output.push('\n'),
+            "p" | "blockquote" => {
+                self.start_block();
+                self.convert_children(node);
+                self.end_block();
+                self.output.push('\n');
+            }
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                self.start_block();
+                let level = name[1..].parse().unwrap_or(1);
+                self.output.push_str(&"#".repeat(level));
+                self.output.push(' ');
+                self.convert_children(node);
+                self.end_block();
+                self.output.push('\n');
+            }
+            "b" | "strong" => self.wrap(node, "**"),
+            "i" | "em" => self.wrap(node, "*"),
+            "s" | "strike" | "del" => self.wrap(node, "~~"),
+            "code" => self.wrap(node, "`"),
+            "a" => match node.attribute("href") {
+                Some(href) => {
+                    self.output.push('[');
+                    self.convert_children(node);
+                    self.output.push_str(&format!("]({})", href));
+                }
+                None => self.convert_children(node),
+            },
+            "img" => self.output.push_str(&format!(
+                "![{}]({})",
+                node.attribute("alt").unwrap_or(""),
+                node.attribute("src").unwrap_or("")
+            )),
+            "ul" | "ol" => {
+                self.start_block();
+                self.in_list_item = false;
+                let numbering = (node.tag_name().name() == "ol").then_some(1);
+                self.lists.push(numbering);
+                self.convert_children(node);
+                self.lists.pop();
+                self.end_block();
+            }
+            "li" => {
+                self.in_list_item = false;
+                self.start_block();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.output.push_str(&indent);
+                self.output.push_str(&marker);
+                self.in_list_item = true;
+                self.convert_children(node);
+                self.end_block();
+            }
+            "en-todo" => {
+                if self.lists.is_empty() && (self.output.is_empty() || self.output.ends_with('\n'))
+                {
+                    self.output.push_str("- ");
+                }
+                let checked = node.attribute("checked") == Some("true");
+                self.output.push_str(if checked { "[x] " } else { "[ ] " });
+                self.in_list_item = false;
+            }
+            "en-media" => self.output.push_str(&format!(
+                "[attachment: {}]",
+                node.attribute("type").unwrap_or("file")
+            )),
+            "en-crypt" => self.output.push_str("[encrypted content]"),
+            "hr" => {
+                self.start_block();
+                self.output.push_str("---");
+                self.end_block();
+            }
+            "pre" => {
+                self.start_block();
+                let code: String = node
+                    .descendants()
+                    .filter(|n| n.is_text())
+                    .filter_map(|n| n.text())
+                    .collect();
+                self.output.push_str("```\n");
+                self.output.push_str(code.trim_end_matches('\n'));
+                self.output.push_str("\n```");
+                self.end_block();
+            }
+            "tr" => {
+                self.start_block();
+                let cells: Vec<Node> = node
+                    .children()
+                    .filter(|n| n.has_tag_name("td") || n.has_tag_name("th"))
+                    .collect();
+                for (i, cell) in cells.into_iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(" | ");
+                    }
+                    self.convert_children(cell);
+                }
+                self.end_block();
+            }
+            // Evernote writes one div per line
+            "div" | "en-note" | "table" | "tbody" | "thead" | "section" => {
+                self.start_block();
+                self.convert_children(node);
+                self.end_block();
+            }
+            _ => self.convert_children(node),
+        }
+    }
+}
+
+fn strip_tags(text: &str) -> String {
+    let mut output = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => output.push(c),
+            _ => {}
+        }
+    }
+    output
+}
+
+// Drops trailing spaces and runs of more than one blank line
+fn tidy(text: &str) -> String {
+    let mut output = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_lines += 1;
+            if blank_lines > 1 {
+                continue;
+            }
+        } else {
+            blank_lines = 0;
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    output.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enex(notes: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE en-export SYSTEM \"http://xml.evernote.com/pub/evernote-export3.dtd\">\n<en-export export-date=\"20250101T000000Z\" application=\"Evernote\">{}</en-export>",
+            notes
+        )
+    }
+
+    fn enml(body: &str) -> String {
+        format!(
+            "<![CDATA[<?xml version=\"1.0\" encoding=\"UTF-8\"?><!DOCTYPE en-note SYSTEM \"http://xml.evernote.com/pub/enml2.dtd\"><en-note>{}</en-note>]]>",
+            body
+        )
+    }
+
+    #[test]
+    fn test_should_parse_notes_with_dates_and_tags() {
+        let xml = enex(&format!(
+            "<note><title>Groceries</title><content>{}</content><created>20240131T093000Z</created><tag>home</tag><tag>to buy</tag></note>",
+            enml("<div>Milk</div><div>Eggs</div>")
+        ));
+
+        let notes = parse_enex(&xml).unwrap();
+
+        let note = notes[0].as_ref().unwrap();
+        let expected_date_time = parse_enex_date_time("20240131T093000Z").unwrap();
+        assert_eq!(note.get_title(), "Groceries");
+        assert_eq!(note.get_content(), "Milk\nEggs");
+        assert_eq!(
+            note.get_date_time(),
+            expected_date_time.format("%Y-%m-%d %H:%M").to_string()
+        );
+        assert_eq!(note.get_tags(), ["home", "to-buy"]);
+    }
+
+    #[test]
+    fn test_should_report_tags_not_imported() {
+        let xml = enex(
+            "<note><title>Agenda</title><content></content><created>20240131T093000Z</created>\
+             <tag>template</tag><tag> </tag><tag>work</tag></note>",
+        );
+
+        let notes = parse_enex(&xml).unwrap();
+
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].as_ref().unwrap().get_tags(), ["work"]);
+        assert_eq!(
+            notes[1],
+            Err(Skipped {
+                title: "Agenda".to_string(),
+                reason: "tag template not imported: kept for templates".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_should_skip_notes_without_title_or_date() {
+        let xml = enex(
+            "<note><title> </title><content></content><created>20240131T093000Z</created></note>\
+             <note><title>Dateless</title><content></content></note>\
+             <note><title>Updated only</title><content></content><updated>20240131T093000Z</updated></note>",
+        );
+
+        let notes = parse_enex(&xml).unwrap();
+
+        assert_eq!(
+            notes[0],
+            Err(Skipped {
+                title: "(untitled)".to_string(),
+                reason: "missing title".to_string()
+            })
+        );
+        assert_eq!(
+            notes[1].as_ref().unwrap_err().reason,
+            "missing or invalid created date"
+        );
+        assert!(notes[2].is_ok());
+    }
+
+    #[test]
+    fn test_should_reject_files_that_are_not_enex() {
+        assert!(parse_enex("not xml").is_err());
+        assert_eq!(
+            parse_enex("<notes/>"),
+            Err("Invalid ENEX file: missing en-export element".to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_convert_enml_to_markdown() {
+        let markdown = enml_to_markdown(
+            "<?xml version=\"1.0\"?><!DOCTYPE en-note SYSTEM \"http://xml.evernote.com/pub/enml2.dtd\">\
+             <en-note><h2>Plan</h2><div>Some <b>bold</b> and <i>italic</i>&nbsp;text, \
+             a <a href=\"https://example.com\">link</a>.</div><div><br/></div>\
+             <ul><li><div>One</div></li><li>Two<ol><li>Nested</li></ol></li></ul>\
+             <div><en-todo checked=\"true\"/>Done</div><div><en-todo/>Open</div>\
+             <en-media type=\"image/png\" hash=\"abc\"/><hr/><pre>let x = 1;\nlet y = 2;</pre>\
+             <table><tr><td>a</td><td>b</td></tr></table></en-note>",
+        );
+
+        assert_eq!(
+            markdown,
+            "## Plan\n\n\
+             Some **bold** and *italic* text, a [link](https://example.com).\n\n\
+             - One\n- Two\n  1. Nested\n\
+             - [x] Done\n- [ ] Open\n\
+             [attachment: image/png]\n---\n\
+             ```\nlet x = 1;\nlet y = 2;\n```\n\
+             a | b"
+        );
+    }
+
+    #[test]
+    fn test_should_strip_tags_from_malformed_enml() {
+        assert_eq!(
+            enml_to_markdown("<en-note><div>Unclosed <b>bold</div>"),
+            "Unclosed bold"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use super::{ImportReport, Skipped, import_notes};
+use crate::front_matter;
+use crate::note::Note;
+use crate::note_taker::NoteTaker;
+use crate::persistence::vault_persistence::VaultPersistence;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Reads one note per Markdown file under a directory, such as an Obsidian
+/// vault or a Joplin export. The title comes from the front matter, else the
+/// first heading, else the file name; the date time from the front matter,
+/// else the time the file was last modified.
+pub fn parse_markdown_folder(directory: &str) -> Result<Vec<Result<Note, Skipped>>, String> {
+    let directory = Path::new(directory);
+    if !directory.is_dir() {
+        return Err(format!("{} is not a directory", directory.display()));
+    }
+    let mut files = Vec::new();
+    VaultPersistence::collect_files(directory, &mut files)?;
+    files.sort();
+    Ok(files.into_iter().map(parse_file).collect())
+}
+
+/// Imports a folder of Markdown files into the note taker, or only reports
+/// what it would import on a dry run.
+pub fn import_markdown_folder(
+    note_taker: &mut NoteTaker,
+    directory: &str,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    Ok(import_notes(
+        note_taker,
+        parse_markdown_folder(directory)?,
+        dry_run,
+    ))
+}
+
+fn parse_file(path: PathBuf) -> Result<Note, Skipped> {
+    let file_name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("untitled");
+    let skip = |reason: String| Skipped {
+        title: path.display().to_string(),
+        reason,
+    };
+
+    let bytes = fs::read(&path).map_err(|e| skip(e.to_string()))?;
+    let text = String::from_utf8(bytes).map_err(|_| skip("not valid UTF-8".to_string()))?;
+    front_matter::note_from_document(
+        &text,
+        file_name,
+        VaultPersistence::modified_date_time(&path),
+    )
+    .map_err(skip)
+}
+
+#[cfg(test)]
+use crate::persistence::file_persistence::FilePersistence;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_folder(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("note_taker_import_{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("Projects")).unwrap();
+        fs::create_dir_all(directory.join(".obsidian")).unwrap();
+        fs::write(
+            directory.join("meeting.md"),
+            "---\ntitle: Weekly meeting\ncreated: 2024-03-04 05:06\ntags: [work]\n---\nAgenda",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("Projects/rust.md"),
+            "# Learning Rust\n\nBook",
+        )
+        .unwrap();
+        fs::write(directory.join("Projects/scratch.md"), "No heading").unwrap();
+        fs::write(directory.join("binary.md"), [0xff, 0xfe, 0x00]).unwrap();
+        fs::write(directory.join(".obsidian/app.md"), "Settings").unwrap();
+        fs::write(directory.join("image.png"), "not markdown").unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_should_read_titles_and_date_times() {
+        let directory = setup_folder("titles");
+
+        let parsed = parse_markdown_folder(directory.to_str().unwrap()).unwrap();
+
+        let notes: Vec<&Note> = parsed.iter().filter_map(|r| r.as_ref().ok()).collect();
+        let titles: Vec<&str> = notes.iter().map(|note| note.get_title()).collect();
+        assert_eq!(titles, vec!["Learning Rust", "scratch", "Weekly meeting"]);
+        assert_eq!(notes[2].get_date_time(), "2024-03-04 05:06");
+        assert_eq!(notes[2].get_tags(), ["work"]);
+        assert_eq!(
+            notes[0].get_date_time(),
+            VaultPersistence::modified_date_time(&directory.join("Projects/rust.md"))
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        );
+        let skipped: Vec<&Skipped> = parsed.iter().filter_map(|r| r.as_ref().err()).collect();
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].title.ends_with("binary.md"));
+        assert_eq!(skipped[0].reason, "not valid UTF-8");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_skip_notes_already_imported() {
+        let directory = setup_folder("dedup");
+        let persistence = Box::new(FilePersistence::new("test_notes.json".to_string()));
+        let mut note_taker = NoteTaker::new(persistence);
+
+        let dry_run =
+            import_markdown_folder(&mut note_taker, directory.to_str().unwrap(), true).unwrap();
+        let first =
+            import_markdown_folder(&mut note_taker, directory.to_str().unwrap(), false).unwrap();
+        let second =
+            import_markdown_folder(&mut note_taker, directory.to_str().unwrap(), false).unwrap();
+
+        assert_eq!(dry_run.imported, first.imported);
+        assert_eq!(first.imported.len(), 3);
+        assert!(second.imported.is_empty());
+        assert_eq!(second.skipped.len(), 4);
+        assert_eq!(note_taker.get_size(), 3);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_reject_missing_directory() {
+        assert!(parse_markdown_folder("/nonexistent/note_taker_import").is_err());
+    }
+}
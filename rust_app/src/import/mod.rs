@@ -0,0 +1,151 @@
+pub mod enex;
+pub mod markdown_folder;
+
+use crate::note::Note;
+use crate::note_taker::NoteTaker;
+
+use std::collections::HashSet;
+use std::fmt;
+
+/// An entry of an import source that did not become a note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skipped {
+    pub title: String,
+    pub reason: String,
+}
+
+/// What an import added, or would add on a dry run, and what it skipped.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub imported: Vec<Note>,
+    pub skipped: Vec<Skipped>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} note(s), skipped {}",
+            if self.dry_run {
+                "Would import"
+            } else {
+                "Imported"
+            },
+            self.imported.len(),
+            self.skipped.len()
+        )?;
+        for note in &self.imported {
+            write!(f, "\n  + {} ({})", note.get_title(), note.get_date_time())?;
+        }
+        for skipped in &self.skipped {
+            write!(f, "\n  - {}: {}", skipped.title, skipped.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds the parsed notes to the note taker, skipping those it already has.
+/// On a dry run nothing is added, but the report is the same.
+pub fn import_notes(
+    note_taker: &mut NoteTaker,
+    parsed: Vec<Result<Note, Skipped>>,
+    dry_run: bool,
+) -> ImportReport {
+    let mut report = ImportReport {
+        dry_run,
+        ..ImportReport::default()
+    };
+    // A dry run adds nothing, so duplicates within the source are tracked here
+    let mut seen = HashSet::new();
+
+    for result in parsed {
+        match result {
+            Ok(note) if note_taker.index_of(&note).is_some() || seen.contains(&note) => {
+                report.skipped.push(Skipped {
+                    title: note.get_title().to_string(),
+                    reason: "already exists".to_string(),
+                });
+            }
+            Ok(note) => {
+                seen.insert(note.clone());
+                if !dry_run {
+                    note_taker.add_note(note.clone());
+                }
+                report.imported.push(note);
+            }
+            Err(skipped) => report.skipped.push(skipped),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+use crate::persistence::file_persistence::FilePersistence;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str) -> Note {
+        Note::new_with_date_time(
+            title.to_string(),
+            "Content".to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap()
+    }
+
+    fn parsed() -> Vec<Result<Note, Skipped>> {
+        vec![
+            Ok(note("Existing")),
+            Ok(note("New")),
+            Ok(note("New")),
+            Err(Skipped {
+                title: "Broken".to_string(),
+                reason: "no content".to_string(),
+            }),
+        ]
+    }
+
+    fn setup_note_taker() -> NoteTaker {
+        let persistence = Box::new(FilePersistence::new("test_notes.json".to_string()));
+        let mut note_taker = NoteTaker::new(persistence);
+        note_taker.add_note(note("Existing"));
+        note_taker
+    }
+
+    #[test]
+    fn test_should_import_new_notes_and_skip_duplicates() {
+        let mut note_taker = setup_note_taker();
+
+        let report = import_notes(&mut note_taker, parsed(), false);
+
+        assert_eq!(report.imported, vec![note("New")]);
+        assert_eq!(
+            report
+                .skipped
+                .iter()
+                .map(|s| s.reason.as_str())
+                .collect::<Vec<_>>(),
+            vec!["already exists", "already exists", "no content"]
+        );
+        assert_eq!(note_taker.get_size(), 2);
+        assert_eq!(
+            report.to_string(),
+            "Imported 1 note(s), skipped 3\n  + New (2025-01-01 10:00)\n  - Existing: already exists\n  - New: already exists\n  - Broken: no content"
+        );
+    }
+
+    #[test]
+    fn test_dry_run_should_not_add_notes() {
+        let mut note_taker = setup_note_taker();
+
+        let report = import_notes(&mut note_taker, parsed(), true);
+
+        assert_eq!(report.imported, vec![note("New")]);
+        assert_eq!(report.skipped.len(), 3);
+        assert_eq!(note_taker.get_size(), 1);
+        assert!(report.to_string().starts_with("Would import 1 note(s)"));
+    }
+}
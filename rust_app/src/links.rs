@@ -0,0 +1,115 @@
+/// Returns the targets of the `[[Target]]` and `[[Target|alias]]` links in some content.
+pub fn parse_links(content: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let inner = &rest[..end];
+        // A `[[` inside the link starts a new one, e.g. `[[not closed [[Target]]`
+        if let Some(nested) = inner.rfind("[[") {
+            rest = &rest[nested..];
+            continue;
+        }
+        let target = inner.split('|').next().unwrap_or("").trim();
+        if !target.is_empty() {
+            links.push(target.to_string());
+        }
+        rest = &rest[end + 2..];
+    }
+    links
+}
+
+/// Normalizes a link target or title so links match titles regardless of case.
+pub fn normalize(target: &str) -> String {
+    target.trim().to_lowercase()
+}
+
+/// Points every link to one of `from` at `to` instead, keeping link aliases.
+pub fn rewrite_links(content: &str, from: &[&str], to: &str) -> String {
+    let from: Vec<String> = from.iter().map(|target| normalize(target)).collect();
+    replace_links(content, |target, alias| {
+        if !from.contains(&normalize(target)) {
+            return None;
+        }
+        Some(match alias {
+            Some(alias) => format!("[[{}|{}]]", to, alias),
+            None => format!("[[{}]]", to),
+        })
+    })
+}
+
+/// Replaces each link with what `replace` returns for its target and alias,
+/// leaving links it returns `None` for as they are.
+pub fn replace_links(
+    content: &str,
+    mut replace: impl FnMut(&str, Option<&str>) -> Option<String>,
+) -> String {
+    let mut result = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("[[") {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let inner = &rest[2..end];
+        if inner.contains("[[") {
+            result.push_str("[[");
+            rest = &rest[2..];
+            continue;
+        }
+        let (target, alias) = match inner.split_once('|') {
+            Some((target, alias)) => (target, Some(alias)),
+            None => (inner, None),
+        };
+        match replace(target, alias) {
+            Some(replacement) => result.push_str(&replacement),
+            None => result.push_str(&rest[..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_parse_links() {
+        let links = parse_links("See [[First note]] and [[ Second note | the second ]].");
+        assert_eq!(links, vec!["First note", "Second note"]);
+    }
+
+    #[test]
+    fn test_should_ignore_unclosed_and_empty_links() {
+        assert_eq!(
+            parse_links("[[ ]] [[not closed [[Target]] [[open"),
+            vec!["Target"]
+        );
+    }
+
+    #[test]
+    fn test_should_normalize_case_and_spaces() {
+        assert_eq!(normalize("  Some Title "), "some title");
+    }
+
+    #[test]
+    fn test_should_rewrite_links_keeping_aliases() {
+        let content = "See [[old title]], [[Old Title|this]] and [[Other]].";
+        assert_eq!(
+            rewrite_links(content, &["Old Title"], "New Title"),
+            "See [[New Title]], [[New Title|this]] and [[Other]]."
+        );
+    }
+
+    #[test]
+    fn test_rewrite_should_leave_content_without_links_alone() {
+        let content = "No links [[here";
+        assert_eq!(rewrite_links(content, &["here"], "there"), content);
+    }
+}
@@ -1,74 +1,983 @@
+mod add;
+mod autosave;
+mod backup;
+mod calendar;
+mod crypto;
+mod daily;
+mod editor;
+mod export;
+mod front_matter;
+mod graph;
+mod history;
+mod import;
+mod links;
+mod markdown;
+mod migrate;
 mod note;
 mod note_taker;
 mod persistence;
+mod reminders;
+mod repl;
+mod tasks;
+mod templates;
+mod tui;
+
+use persistence::persistence_trait::PersistenceTrait;
+use std::io::IsTerminal;
+
+// Opens the storage named by NOTES_BACKEND as migrate names backends, e.g.
+// `sqlite:notes.db`, or PostgreSQL by default
+fn open_backend() -> Result<Box<dyn persistence::persistence_trait::PersistenceTrait>, String> {
+    let backend = std::env::var("NOTES_BACKEND").unwrap_or_else(|_| "postgres".to_string());
+    Ok(
+        match (
+            backend.strip_prefix("git:"),
+            std::env::var("NOTES_GIT_REMOTE"),
+        ) {
+            (Some(repository), Ok(url)) => Box::new(
+                persistence::git_persistence::GitPersistence::new(repository)?.with_remote(&url)?,
+            ),
+            _ => migrate::open_backend(&backend)?,
+        },
+    )
+}
+
+// Opens the configured storage, encrypted when a passphrase is set
+fn open_persistence() -> Result<Box<dyn persistence::persistence_trait::PersistenceTrait>, String> {
+    let mut persistence = open_backend()?;
+    // Notes are encrypted at rest when a passphrase is configured
+    if let Ok(passphrase) = std::env::var("NOTES_PASSPHRASE") {
+        persistence = Box::new(
+            persistence::encrypted_persistence::EncryptedPersistence::new(persistence, &passphrase),
+        );
+    }
+    Ok(persistence)
+}
+
+// Opens the configured storage and loads the notes, without autosave
+fn open_storage() -> Result<note_taker::NoteTaker, String> {
+    let mut note_taker = note_taker::NoteTaker::new(open_persistence()?);
+    note_taker.load()?;
+    // The calendar feed is regenerated on every save when a path is configured
+    if let Some(path) = std::env::var_os("NOTES_ICS") {
+        note_taker.set_calendar_feed(Some(std::path::PathBuf::from(path)));
+    }
+    Ok(note_taker)
+}
+
+fn open_note_taker() -> note_taker::NoteTaker {
+    let mut note_taker = open_storage().expect("Failed to load notes");
+    let recovered = autosave::Autosave::from_env()
+        .and_then(|autosave| note_taker.enable_autosave(autosave))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to read the autosave journal: {}", e);
+            std::process::exit(1);
+        });
+    if recovered > 0 {
+        println!(
+            "Recovered {} unsaved change(s) from the last session.",
+            recovered
+        );
+        if let Err(e) = note_taker.save() {
+            eprintln!("Failed to save recovered changes: {}", e);
+        }
+    }
+    note_taker
+}
+
+// Saves before exiting on SIGINT or SIGTERM. The session shares a copy of
+// its notes, updated on every change, which the handler saves in its place
+// through a backend of its own, loaded first so it takes over what the
+// session's backend stored.
+fn save_on_signal(note_taker: &mut note_taker::NoteTaker) {
+    let notes = note_taker.share_notes();
+    let result = ctrlc::set_handler(move || {
+        let notes = notes.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let saved = open_persistence()
+            .and_then(|persistence| {
+                persistence::persistence_trait::load_and_save(persistence.as_ref(), &notes)
+            })
+            .and_then(|()| autosave::Autosave::from_env()?.saved());
+        if let Err(e) = saved {
+            eprintln!("Failed to save notes: {}", e);
+        }
+        std::process::exit(130);
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to handle signals: {}", e);
+    }
+}
+
+// Re-encrypts the stored notes under a new passphrase, read without echo.
+// Notes stored in plaintext are encrypted for the first time.
+fn rekey() -> Result<(), String> {
+    // Journaled changes are sealed with the current passphrase, so they are
+    // recovered into storage first
+    if !autosave::Autosave::from_env()?.read_journal()?.is_empty() {
+        return Err(
+            "Start note_taker once to recover unsaved changes before changing the passphrase"
+                .to_string(),
+        );
+    }
+    let current = std::env::var("NOTES_PASSPHRASE").unwrap_or_default();
+    let persistence =
+        persistence::encrypted_persistence::EncryptedPersistence::new(open_backend()?, &current);
+    // Checks the current passphrase before asking for a new one
+    persistence.load()?;
+    let passphrase = repl::read_secret("Enter new passphrase:").ok_or("Cancelled")?;
+    if passphrase.is_empty() {
+        return Err("Passphrase cannot be empty".to_string());
+    }
+    if repl::read_secret("Repeat new passphrase:").ok_or("Cancelled")? != passphrase {
+        return Err("Passphrases do not match".to_string());
+    }
+    persistence.rotate_passphrase(&passphrase)
+}
 
 fn main() {
-    let mut note_taker = note_taker::NoteTaker::new(Box::new(
-        persistence::postgresql_persistence::PostgresqlPersistence::new().unwrap(),
-    ));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|command| command == "migrate") {
+        match migrate::run(&args[1..]) {
+            Ok(report) => {
+                println!("{}", report);
+                if !report.failed.is_empty() {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
+    if args.first().is_some_and(|command| command == "rekey") {
+        if let Err(e) = rekey() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        println!("Notes encrypted with the new passphrase. Set NOTES_PASSPHRASE to it.");
+        return;
+    }
+
+    if args.first().is_some_and(|command| command == "compact") {
+        if let Err(e) = open_persistence().and_then(|persistence| persistence.compact()) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    note_taker.load().expect("Failed to load notes");
+    if args.first().is_some_and(|command| command == "daemon") {
+        // The daemon loads and saves the notes on its own, leaving the
+        // autosave journal to interactive sessions
+        let once = match &args[1..] {
+            [] => false,
+            [flag] if flag == "--once" => true,
+            _ => {
+                eprintln!("Usage: note_taker daemon [--once]");
+                std::process::exit(2);
+            }
+        };
+        let result = reminders::FiredLog::from_env(chrono::Local::now().naive_local()).and_then(
+            |mut fired| {
+                reminders::run_daemon(
+                    open_storage,
+                    &reminders::Notifier::from_env(),
+                    &mut fired,
+                    once,
+                )
+            },
+        );
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut note_taker = open_note_taker();
+    if args.first().is_some_and(|command| command == "add") {
+        let reads_stdin = !args.iter().any(|arg| arg == "--file");
+        if reads_stdin && std::io::stdin().is_terminal() {
+            eprintln!("Reading content from stdin, end it with Ctrl-D.");
+        }
+        let result = add::run(&args[1..], &mut note_taker).and_then(|note| {
+            note_taker.save()?;
+            Ok(note)
+        });
+        match result {
+            Ok(note) => println!("Added {}", note.get_title()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+    if args.first().is_some_and(|command| command == "journal") {
+        let result = daily::DailyJournal::from_env().and_then(|journal| {
+            let now = chrono::Local::now().naive_local();
+            if args.len() == 1 {
+                return Ok(match journal.find(&note_taker, now.date()) {
+                    Some(index) => display_note(&note_taker.get_note(index)),
+                    None => format!("No entries for {}.", journal.title(now.date())),
+                });
+            }
+            let index = journal.append(&mut note_taker, &args[1..].join(" "), now)?;
+            note_taker.save()?;
+            Ok(format!(
+                "Added to {}",
+                note_taker.get_note(index).get_title()
+            ))
+        });
+        match result {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+    save_on_signal(&mut note_taker);
+    if args.first().is_some_and(|command| command == "tui") {
+        if let Err(e) = tui::run(&mut note_taker) {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+        return;
+    }
 
     println!("Welcome to the Note Taker!");
+    let mut repl = repl::Repl::new().expect("Failed to open the prompt");
+    repl::print_menu();
     loop {
-        println!("1. Add Note");
-        println!("2. View Notes");
-        println!("3. Delete Note");
-        println!("4. Exit");
-
-        let mut choice = String::new();
-        std::io::stdin()
-            .read_line(&mut choice)
-            .expect("Failed to read line");
-        let choice: u32 = choice.trim().parse().expect("Please enter a number");
-
-        match choice {
-            1 => {
-                println!("Enter title:");
-                let mut title = String::new();
-                std::io::stdin()
-                    .read_line(&mut title)
-                    .expect("Failed to read line");
-
-                println!("Enter content:");
-                let mut content = String::new();
-                std::io::stdin()
-                    .read_line(&mut content)
-                    .expect("Failed to read line");
-
-                note_taker
-                    .add_note(note::Note::new(title.to_string(), content.to_string()).unwrap());
+        let choice = match repl.read_command() {
+            repl::Command::Choice(choice) => choice,
+            repl::Command::Help => {
+                repl::print_menu();
+                continue;
             }
-            2 => {
-                let notes = note_taker.get_notes();
-                for (i, note) in notes.iter().enumerate() {
-                    println!("{} \n{}", i + 1, note);
+            repl::Command::Invalid(input) => {
+                if !input.trim().is_empty() {
+                    println!("Invalid choice. Type help to see the commands.");
                 }
+                continue;
             }
-            3 => {
-                println!("Enter note index to delete:");
-                let mut index = String::new();
-                std::io::stdin()
-                    .read_line(&mut index)
-                    .expect("Failed to read line");
-
-                let index: usize = index.trim().parse().expect("Please enter a number");
+            repl::Command::Interrupted => continue,
+            repl::Command::Exit => {
+                // Input has ended, so the notes can only be saved or lost
+                if let Err(e) = note_taker.save() {
+                    eprintln!("Failed to save notes: {}", e);
+                    std::process::exit(1);
+                }
+                println!("Closing!");
+                break;
+            }
+        };
+        if choice == repl::EXIT {
+            match note_taker.save() {
+                Ok(()) => {
+                    println!("Closing!");
+                    break;
+                }
+                Err(e) => println!("Failed to save notes: {}", e),
+            }
+        } else if run_command(choice, &mut note_taker, &mut repl).is_none() {
+            println!("Cancelled.");
+        }
+        if let Err(e) = note_taker.autosave_if_due() {
+            println!("Autosave failed: {}", e);
+        }
+        if let Some(e) = note_taker.take_autosave_error() {
+            println!("{}", e);
+        }
+        if let Some(e) = note_taker.take_calendar_feed_error() {
+            println!("{}", e);
+        }
+    }
+}
 
-                if index > 0 && index <= note_taker.get_size() {
-                    note_taker.delete_note(index - 1);
+// Runs a menu command, returning `None` if the user cancelled one of its prompts
+fn run_command(
+    choice: u32,
+    note_taker: &mut note_taker::NoteTaker,
+    repl: &mut repl::Repl,
+) -> Option<()> {
+    match choice {
+        1 => {
+            let names = titles_of(note_taker, &templates::get_templates(note_taker));
+            if !names.is_empty() {
+                let name = repl
+                    .read_completing("Enter template name, or nothing for a blank note:", names)?;
+                if !name.trim().is_empty() {
+                    return add_from_template(note_taker, repl, &name);
+                }
+            }
+            match editor::editor_command() {
+                // Multi-line notes are written in the user's editor when there is one
+                Some(editor) => match editor::compose(&editor) {
+                    Ok(Some(note)) => {
+                        note_taker.add_note(note);
+                        println!("Note added.");
+                    }
+                    Ok(None) => println!("Empty note, nothing added."),
+                    Err(e) => println!("{}", e),
+                },
+                None => {
+                    let title = repl.read("Enter title:")?;
+                    let content = repl.read("Enter content:")?;
+                    match note::Note::new(title, content) {
+                        Ok(note) => {
+                            note_taker.add_note(note);
+                            println!("Note added.");
+                        }
+                        Err(e) => println!("{}", e),
+                    }
+                }
+            }
+        }
+        2 => {
+            let notes = note_taker.get_notes();
+            for (i, note) in notes.iter().enumerate() {
+                println!("{} \n{}", i + 1, display_note(note));
+            }
+        }
+        3 => {
+            println!("Enter note index to delete:");
+            match read_listed_index(repl, note_taker, &note_taker.get_notes())? {
+                Some(index) => {
+                    note_taker.delete_note(index);
                     println!("Note deleted.");
-                } else {
-                    println!("Invalid index");
                 }
+                None => println!("Invalid index"),
             }
-            4 => {
-                note_taker.save().expect("Failed to save notes");
-                println!("Closing!");
+        }
+        4 => {
+            println!("Enter note index to edit:");
+            match read_listed_index(repl, note_taker, &note_taker.get_notes())? {
+                Some(index) if note_taker.get_note(index).is_locked() => {
+                    println!("Note is locked")
+                }
+                Some(index) => {
+                    let (title, content) = match editor::editor_command() {
+                        Some(editor) => {
+                            let note = note_taker.get_note(index);
+                            match editor::edit(&editor, note.get_title(), note.get_content()) {
+                                Ok(editor::Outcome::Edited { title, content }) => (title, content),
+                                Ok(editor::Outcome::Unchanged) => {
+                                    println!("Note unchanged.");
+                                    return Some(());
+                                }
+                                Ok(editor::Outcome::Aborted) => {
+                                    println!("Edit aborted.");
+                                    return Some(());
+                                }
+                                Err(e) => {
+                                    println!("{}", e);
+                                    return Some(());
+                                }
+                            }
+                        }
+                        None => (
+                            repl.read("Enter new title:")?,
+                            repl.read("Enter new content:")?,
+                        ),
+                    };
+                    match note_taker.edit_note(index, title, content) {
+                        Ok(()) => println!("Note edited."),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                None => println!("Invalid index"),
+            }
+        }
+        5 => {
+            println!("Enter note index to tag or untag:");
+            match read_listed_index(repl, note_taker, &note_taker.get_notes())? {
+                Some(index) => {
+                    let tag = repl.read("Enter tag:")?;
+                    let result = if note_taker.get_note(index).has_tag(tag.trim()) {
+                        note_taker.untag_note(index, &tag).map(|_| "Tag removed.")
+                    } else {
+                        note_taker.tag_note(index, &tag).map(|_| "Tag added.")
+                    };
+                    match result {
+                        Ok(message) => println!("{}", message),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                None => println!("Invalid index"),
+            }
+        }
+        6 => {
+            println!("Enter note index to move:");
+            match read_listed_index(repl, note_taker, &note_taker.get_notes())? {
+                Some(index) => {
+                    let date_time = repl.read("Enter new date time (YYYY-MM-DD HH:MM):")?;
+                    match note_taker.move_note(index, date_time.trim()) {
+                        Ok(()) => println!("Note moved."),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                None => println!("Invalid index"),
+            }
+        }
+        7 => {
+            println!("Enter note index to pin or unpin:");
+            match read_listed_index(repl, note_taker, &note_taker.get_notes())? {
+                Some(index) => {
+                    let pinned = !note_taker.get_note(index).is_pinned();
+                    note_taker.set_pinned(index, pinned);
+                    println!(
+                        "{}",
+                        if pinned {
+                            "Note pinned."
+                        } else {
+                            "Note unpinned."
+                        }
+                    );
+                }
+                None => println!("Invalid index"),
+            }
+        }
+        8 => {
+            println!("Enter note index to archive or unarchive:");
+            match read_listed_index(repl, note_taker, &note_taker.get_all_notes())? {
+                Some(index) => {
+                    let archived = !note_taker.get_note(index).is_archived();
+                    note_taker.set_archived(index, archived);
+                    println!(
+                        "{}",
+                        if archived {
+                            "Note archived."
+                        } else {
+                            "Note unarchived."
+                        }
+                    );
+                }
+                None => println!("Invalid index"),
+            }
+        }
+        9 => {
+            let notes = note_taker.get_archived_notes();
+            for (i, note) in notes.iter().enumerate() {
+                println!("{} \n{}", i + 1, display_note(note));
+            }
+        }
+        10 => match note_taker.undo() {
+            Ok(true) => println!("Undone."),
+            Ok(false) => println!("Nothing to undo."),
+            Err(e) => println!("{}", e),
+        },
+        11 => match note_taker.redo() {
+            Ok(true) => println!("Redone."),
+            Ok(false) => println!("Nothing to redo."),
+            Err(e) => println!("{}", e),
+        },
+        12 => {
+            println!("Enter note index to show links of:");
+            match read_listed_index(repl, note_taker, &note_taker.get_all_notes())? {
+                Some(index) => {
+                    println!("ID: {}", note_taker.get_note(index).get_id());
+                    println!("Links:");
+                    for (target, resolved) in note_taker.get_links(index) {
+                        match resolved {
+                            Some(i) => {
+                                println!("  {} -> {}", target, note_taker.get_note(i).get_title())
+                            }
+                            None => println!("  {} (broken)", target),
+                        }
+                    }
+                    println!("Backlinks:");
+                    for i in note_taker.get_backlinks(index) {
+                        println!("  {}", note_taker.get_note(i).get_title());
+                    }
+                }
+                None => println!("Invalid index"),
+            }
+        }
+        13 => {
+            let broken_links = note_taker.get_broken_links();
+            if broken_links.is_empty() {
+                println!("No broken links.");
+            }
+            for (index, target) in broken_links {
+                println!("{}: [[{}]]", note_taker.get_note(index).get_title(), target);
+            }
+        }
+        14 => {
+            let graph = graph::Graph::from_note_taker(note_taker);
+            let format = repl.read("Enter format (dot or graphml):")?;
+            let output = match format.trim() {
+                "dot" => graph.to_dot(),
+                "graphml" => graph.to_graphml(),
+                _ => {
+                    println!("Invalid format");
+                    return Some(());
+                }
+            };
+            let path = repl.read("Enter file path:")?;
+            match std::fs::write(path.trim(), output) {
+                Ok(()) => println!("Graph exported."),
+                Err(e) => println!("{}", e),
+            }
+        }
+        15 => {
+            let graph = graph::Graph::from_note_taker(note_taker);
+            println!("Orphan notes:");
+            for node in graph.get_orphans() {
+                println!("  {}", graph.nodes[node].get_title());
+            }
+            println!("Most linked notes:");
+            for (node, count) in graph.get_most_linked(10) {
+                println!("  {} ({})", graph.nodes[node].get_title(), count);
+            }
+        }
+        16 => {
+            let graph = graph::Graph::from_note_taker(note_taker);
+            let from = note_taker.resolve_link(&repl.read_completing(
+                "Enter title or ID of first note:",
+                titles(&note_taker.get_all_notes()),
+            )?);
+            let to = note_taker.resolve_link(&repl.read_completing(
+                "Enter title or ID of second note:",
+                titles(&note_taker.get_all_notes()),
+            )?);
+            match (
+                from.and_then(|from| graph.get_node(from)),
+                to.and_then(|to| graph.get_node(to)),
+            ) {
+                (Some(from), Some(to)) => match graph.get_shortest_path(from, to) {
+                    Some(path) => {
+                        let titles: Vec<&str> = path
+                            .iter()
+                            .map(|&node| graph.nodes[node].get_title())
+                            .collect();
+                        println!("{}", titles.join(" -> "));
+                    }
+                    None => println!("No path between these notes."),
+                },
+                _ => println!("Note not found"),
+            }
+        }
+        17 => {
+            println!("Enter note index to lock or unlock:");
+            match read_listed_index(repl, note_taker, &note_taker.get_notes())? {
+                Some(index) if note_taker.get_note(index).is_locked() => {
+                    let password = repl.read_secret("Enter password:")?;
+                    let password = password.trim_end_matches(['\r', '\n']);
+                    let unlock = repl.read("Unlock it for good? (y/n, n only shows it):")?;
+                    if unlock.trim().eq_ignore_ascii_case("y") {
+                        match note_taker.unlock_note(index, password) {
+                            Ok(()) => println!("Note unlocked."),
+                            Err(e) => println!("{}", e),
+                        }
+                    } else {
+                        match note_taker.get_note(index).get_unlocked_content(password) {
+                            Ok(content) => println!("{}", content),
+                            Err(e) => println!("{}", e),
+                        }
+                    }
+                }
+                Some(index) => {
+                    let password = repl.read_secret("Enter new password:")?;
+                    let password = password.trim_end_matches(['\r', '\n']);
+                    match note_taker.lock_note(index, password) {
+                        Ok(()) => println!("Note locked."),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                None => println!("Invalid index"),
+            }
+        }
+        18 => {
+            let path = repl.read("Enter path of Evernote export (.enex) or Markdown folder:")?;
+            let path = path.trim();
+            let dry_run = repl
+                .read("Dry run only? (y/n):")?
+                .trim()
+                .eq_ignore_ascii_case("y");
+            let report = if std::path::Path::new(path).is_dir() {
+                import::markdown_folder::import_markdown_folder(note_taker, path, dry_run)
+            } else {
+                import::enex::import_enex(note_taker, path, dry_run)
+            };
+            match report {
+                Ok(report) => println!("{}", report),
+                Err(e) => println!("{}", e),
+            }
+        }
+        19 => {
+            let format = repl.read("Enter format (markdown, files, csv, html or ics):")?;
+            match export::Format::parse(&format) {
+                Ok(format) => {
+                    let path = repl.read("Enter output path:")?;
+                    match export::export(&note_taker.get_notes(), format, path.trim()) {
+                        Ok(written) => println!("Wrote {} file(s).", written.len()),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                Err(e) => println!("{}", e),
+            }
+        }
+        20 => {
+            let path = repl.read("Enter backup file path (e.g. notes.tar.gz):")?;
+            match backup::create_backup(&note_taker.get_all_notes(), path.trim()) {
+                Ok(manifest) => println!("Backed up {} note(s).", manifest.note_count),
+                Err(e) => println!("{}", e),
+            }
+        }
+        21 => {
+            let path = repl.read("Enter backup file path:")?;
+            match backup::read_backup(path.trim()) {
+                Ok((manifest, notes)) => {
+                    note_taker.replace_notes(notes);
+                    match note_taker.save() {
+                        Ok(()) => println!(
+                            "Restored {} note(s) from backup of {}.",
+                            manifest.note_count, manifest.created_at
+                        ),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                Err(e) => println!("{}", e),
+            }
+        }
+        22 => {
+            let journal = match daily::DailyJournal::from_env() {
+                Ok(journal) => journal,
+                Err(e) => {
+                    println!("{}", e);
+                    return Some(());
+                }
+            };
+            let today = chrono::Local::now().date_naive();
+            let mut date = today;
+            loop {
+                match journal.find(note_taker, date) {
+                    Some(index) => println!("{}", display_note(&note_taker.get_note(index))),
+                    None => println!("No entries for {}.", journal.title(date)),
+                }
+                let input = repl.read(
+                    "Write an entry for today, < or > for the previous or next day, . for today, or nothing to finish:",
+                )?;
+                match input.trim() {
+                    "" => break,
+                    "<" => match journal.previous(note_taker, date) {
+                        Some(previous) => date = previous,
+                        None => println!("No earlier days."),
+                    },
+                    ">" => match journal.next(note_taker, date) {
+                        Some(next) => date = next,
+                        None => println!("No later days."),
+                    },
+                    "." => date = today,
+                    entry => {
+                        let now = chrono::Local::now().naive_local();
+                        match journal.append(note_taker, entry, now) {
+                            Ok(_) => date = now.date(),
+                            Err(e) => println!("{}", e),
+                        }
+                    }
+                }
+            }
+        }
+        23 => {
+            let templates = templates::get_templates(note_taker);
+            let action = repl.read("Enter l to list, c to create or e to edit a template:")?;
+            match action.trim() {
+                "l" if templates.is_empty() => println!("No templates."),
+                "l" => {
+                    for index in templates {
+                        let template = note_taker.get_note(index);
+                        let prompts = templates::get_prompts(template.get_content());
+                        if prompts.is_empty() {
+                            println!("{}", template.get_title());
+                        } else {
+                            println!("{} (asks for {})", template.get_title(), prompts.join(", "));
+                        }
+                    }
+                }
+                "c" => {
+                    let name = repl.read("Enter template name:")?;
+                    if let Some(body) = read_template_body(repl, &name, "")? {
+                        match templates::create_template(note_taker, &name, &body) {
+                            Ok(_) => println!("Template created."),
+                            Err(e) => println!("{}", e),
+                        }
+                    }
+                }
+                "e" => {
+                    let name = repl.read_completing(
+                        "Enter template name:",
+                        titles_of(note_taker, &templates),
+                    )?;
+                    match templates::find_template(note_taker, &name) {
+                        Some(index) => {
+                            let template = note_taker.get_note(index);
+                            let body = read_template_body(
+                                repl,
+                                template.get_title(),
+                                template.get_content(),
+                            )?;
+                            if let Some(body) = body {
+                                match templates::edit_template(note_taker, index, &body) {
+                                    Ok(()) => println!("Template edited."),
+                                    Err(e) => println!("{}", e),
+                                }
+                            }
+                        }
+                        None => println!("Template not found"),
+                    }
+                }
+                _ => println!("Invalid choice"),
+            }
+        }
+        24 => loop {
+            let open_tasks = note_taker.get_open_tasks();
+            if open_tasks.is_empty() {
+                println!("No open tasks.");
                 break;
             }
-            _ => {
-                println!("Invalid choice. Please try again.");
+            let mut last_note = None;
+            for (i, (index, task)) in open_tasks.iter().enumerate() {
+                if last_note != Some(*index) {
+                    let (done, total) = note_taker.get_task_progress(*index);
+                    println!(
+                        "{} ({}/{} done)",
+                        note_taker.get_note(*index).get_title(),
+                        done,
+                        total
+                    );
+                    last_note = Some(*index);
+                }
+                println!("  {}. [ ] {}", i + 1, task.text);
+            }
+            let input = repl.read("Enter task number to check, or nothing to finish:")?;
+            if input.trim().is_empty() {
+                break;
+            }
+            match input.trim().parse::<usize>() {
+                Ok(number) if number > 0 && number <= open_tasks.len() => {
+                    let (index, task) = &open_tasks[number - 1];
+                    match note_taker.toggle_task(*index, task.line) {
+                        Ok(()) => println!("Checked {}.", task.text),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                _ => println!("Invalid task number"),
+            }
+        },
+        25 => {
+            let reminders = note_taker.get_reminders();
+            if reminders.is_empty() {
+                println!("No reminders.");
+            }
+            for index in reminders {
+                let note = note_taker.get_note(index);
+                println!("{}: {}", note.get_title(), note.get_reminder().unwrap());
+            }
+            println!("Enter note index to set a reminder for:");
+            let Some(index) = read_listed_index(repl, note_taker, &note_taker.get_notes())? else {
+                println!("Invalid index");
+                return Some(());
+            };
+            let input = repl.read(
+                "Enter reminder time (YYYY-MM-DD HH:MM), +minutes to snooze, or nothing to clear:",
+            )?;
+            let input = input.trim();
+            let result = if input.is_empty() {
+                note_taker
+                    .set_reminder(index, None)
+                    .map(|()| "Reminder cleared.".to_string())
+            } else if let Some(minutes) = input.strip_prefix('+') {
+                match minutes.trim().parse::<i64>() {
+                    Ok(minutes) if minutes > 0 => {
+                        let until =
+                            chrono::Local::now().naive_local() + chrono::Duration::minutes(minutes);
+                        note_taker
+                            .snooze_reminder(index, until)
+                            .map(|()| format!("Snoozed until {}.", until.format("%Y-%m-%d %H:%M")))
+                    }
+                    _ => Err("Invalid number of minutes".to_string()),
+                }
+            } else {
+                let repeat = repl
+                    .read("Repeat (never, daily, weekly or cron MINUTE HOUR DAY MONTH WEEKDAY):")?;
+                chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+                    .map_err(|_| "Invalid date time format".to_string())
+                    .and_then(|at| reminders::Reminder::new(at, reminders::Repeat::parse(&repeat)?))
+                    .and_then(|reminder| {
+                        let message = format!("Reminder set for {}.", reminder);
+                        note_taker.set_reminder(index, Some(reminder))?;
+                        Ok(message)
+                    })
+            };
+            match result {
+                Ok(message) => println!("{}", message),
+                Err(e) => println!("{}", e),
+            }
+        }
+        26 => {
+            let revisions = match note_taker.get_revisions() {
+                Ok(revisions) => revisions,
+                Err(e) => {
+                    println!("{}", e);
+                    return Some(());
+                }
+            };
+            if revisions.is_empty() {
+                println!("No history yet.");
+                return Some(());
+            }
+            for revision in &revisions {
+                println!(
+                    "{} {} {}",
+                    revision.id, revision.date_time, revision.message
+                );
+            }
+            let ids: Vec<String> = revisions
+                .iter()
+                .map(|revision| revision.id.clone())
+                .collect();
+            let revision =
+                repl.read_completing("Enter revision to restore, or nothing to go back:", ids)?;
+            let revision = revision.trim();
+            if revision.is_empty() {
+                return Some(());
+            }
+            match note_taker.restore_revision(revision) {
+                Ok(()) => println!("Notes restored from {}.", revision),
+                Err(e) => println!("{}", e),
             }
         }
+        _ => println!("Invalid choice. Type help to see the commands."),
     }
+    Some(())
+}
+
+// Creates a note from a template, asking for its title and prompts, then lets
+// the user finish it in their editor when there is one
+fn add_from_template(
+    note_taker: &mut note_taker::NoteTaker,
+    repl: &mut repl::Repl,
+    name: &str,
+) -> Option<()> {
+    let Some(index) = templates::find_template(note_taker, name) else {
+        println!("Template not found");
+        return Some(());
+    };
+    let template = note_taker.get_note(index);
+    let title = repl.read("Enter title:")?;
+    let now = chrono::Local::now().naive_local();
+    let note = templates::note_from_template(&template, &title, now, |prompt| {
+        repl.read(&format!("{}:", prompt))
+    });
+    let mut note = match note {
+        Ok(note) => note?,
+        Err(e) => {
+            println!("{}", e);
+            return Some(());
+        }
+    };
+    if let Some(editor) = editor::editor_command() {
+        match editor::edit(&editor, note.get_title(), note.get_content()) {
+            Ok(editor::Outcome::Edited { title, content }) => {
+                if let Err(e) = note.set_title(title) {
+                    println!("{}", e);
+                    return Some(());
+                }
+                note.set_content(content);
+            }
+            Ok(editor::Outcome::Unchanged) => {}
+            Ok(editor::Outcome::Aborted) => {
+                println!("Empty note, nothing added.");
+                return Some(());
+            }
+            Err(e) => {
+                println!("{}", e);
+                return Some(());
+            }
+        }
+    }
+    note_taker.add_note(note);
+    println!("Note added.");
+    Some(())
+}
+
+// Reads the body of a template in the user's editor, or as a line without
+// one. Returns `Some(None)` if the editor left it unchanged or aborted.
+fn read_template_body(repl: &mut repl::Repl, name: &str, body: &str) -> Option<Option<String>> {
+    match editor::editor_command() {
+        Some(editor) => match editor::edit(&editor, name, body) {
+            Ok(editor::Outcome::Edited { content, .. }) => Some(Some(content)),
+            Ok(_) => {
+                println!("Template unchanged.");
+                Some(None)
+            }
+            Err(e) => {
+                println!("{}", e);
+                Some(None)
+            }
+        },
+        None => repl
+            .read("Enter template, with {{date}}, {{time}}, {{title}} or {{anything to ask}}:")
+            .map(Some),
+    }
+}
+
+// Styles the Markdown content of notes when printing to a terminal
+fn display_note(note: &note::Note) -> String {
+    if std::io::stdout().is_terminal() {
+        markdown::render_note_for_terminal(note)
+    } else {
+        note.to_string()
+    }
+}
+
+fn titles_of(note_taker: &note_taker::NoteTaker, indexes: &[usize]) -> Vec<String> {
+    indexes
+        .iter()
+        .map(|&index| note_taker.get_note(index).get_title().to_string())
+        .collect()
+}
+
+fn titles(notes: &[note::Note]) -> Vec<String> {
+    notes
+        .iter()
+        .map(|note| note.get_title().to_string())
+        .collect()
+}
+
+// Reads a 1-based position or a title in `listed` and maps it to the note taker's
+// own index, or returns `None` if the user cancelled
+fn read_listed_index(
+    repl: &mut repl::Repl,
+    note_taker: &note_taker::NoteTaker,
+    listed: &[note::Note],
+) -> Option<Option<usize>> {
+    if listed.is_empty() {
+        return Some(None);
+    }
+    for (i, note) in listed.iter().enumerate() {
+        if note.is_locked() {
+            println!("{} - {} (locked)", i + 1, note.get_title());
+        } else {
+            println!("{} - {}", i + 1, note.get_title());
+        }
+    }
+
+    let input = repl.read_completing(">", titles(listed))?;
+    let input = input.trim();
+    let position = match input.parse::<usize>() {
+        Ok(index) if index > 0 && index <= listed.len() => Some(index - 1),
+        Ok(_) => None,
+        Err(_) => listed
+            .iter()
+            .position(|note| note.get_title().eq_ignore_ascii_case(input)),
+    };
+    Some(position.and_then(|position| note_taker.index_of(&listed[position])))
 }
@@ -0,0 +1,558 @@
+use crate::note::Note;
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd, html};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const STRIKETHROUGH: &str = "\x1b[9m";
+const CYAN: &str = "\x1b[36m";
+const BLUE: &str = "\x1b[34m";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    pub level: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub text: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    pub language: Option<String>,
+    pub code: String,
+}
+
+/// The parts of a Markdown document other features care about.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Structure {
+    pub headings: Vec<Heading>,
+    pub links: Vec<Link>,
+    pub code_blocks: Vec<CodeBlock>,
+}
+
+fn parse(content: &str) -> Parser<'_> {
+    Parser::new_ext(
+        content,
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS,
+    )
+}
+
+fn heading_level(level: HeadingLevel) -> usize {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+// Tells whether a link or image URL is relative or uses a scheme that cannot
+// run scripts. Browsers ignore whitespace and control characters in schemes,
+// so those are dropped before checking.
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect();
+    match url.find(['/', '?', '#', ':']) {
+        Some(end) if url[end..].starts_with(':') => {
+            ["http", "https", "mailto"].contains(&url[..end].to_lowercase().as_str())
+        }
+        _ => true,
+    }
+}
+
+/// Renders Markdown as HTML. Raw HTML in the content is escaped and shown
+/// as text, and links and images only keep relative, http, https and mailto
+/// URLs, so notes cannot inject scripts into a page.
+pub fn to_html(content: &str) -> String {
+    let mut output = String::new();
+    let events = parse(content).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: "#".into(),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: "".into(),
+            title,
+            id,
+        }),
+        event => event,
+    });
+    html::push_html(&mut output, events);
+    output
+}
+
+/// Renders Markdown as text styled with ANSI escape codes for a terminal.
+pub fn to_ansi(content: &str) -> String {
+    let mut output = String::new();
+    // Styles currently open, re-applied after each reset so they can nest
+    let mut styles: Vec<&str> = Vec::new();
+    // The next item number of each open list, `None` for bullet lists
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut quote_depth = 0;
+    let mut in_code_block = false;
+    let mut link_urls: Vec<String> = Vec::new();
+
+    let separate_block = |output: &mut String| {
+        if !output.is_empty() {
+            if !output.ends_with('\n') {
+                output.push('\n');
+            }
+            if !output.ends_with("\n\n") {
+                output.push('\n');
+            }
+        }
+    };
+    let start_line = |output: &mut String, quote_depth: usize| {
+        if !output.is_empty() && !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push_str(&format!("{DIM}│{RESET} ").repeat(quote_depth));
+    };
+
+    for event in parse(content) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => {
+                    if lists.is_empty() {
+                        separate_block(&mut output);
+                        start_line(&mut output, quote_depth);
+                    } else if !output.ends_with(' ') {
+                        start_line(&mut output, quote_depth);
+                        output.push_str(&"  ".repeat(lists.len()));
+                    }
+                }
+                Tag::Heading { level, .. } => {
+                    separate_block(&mut output);
+                    start_line(&mut output, quote_depth);
+                    output.push_str(&"#".repeat(heading_level(level)));
+                    output.push(' ');
+                    styles.push(BOLD);
+                    styles.push(UNDERLINE);
+                    output.push_str(BOLD);
+                    output.push_str(UNDERLINE);
+                }
+                Tag::BlockQuote(_) => {
+                    separate_block(&mut output);
+                    quote_depth += 1;
+                }
+                Tag::CodeBlock(_) => {
+                    separate_block(&mut output);
+                    in_code_block = true;
+                }
+                Tag::List(first) => {
+                    if lists.is_empty() {
+                        separate_block(&mut output);
+                    }
+                    lists.push(first);
+                }
+                Tag::Item => {
+                    start_line(&mut output, quote_depth);
+                    output.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                    match lists.last_mut() {
+                        Some(Some(number)) => {
+                            output.push_str(&format!("{}. ", number));
+                            *number += 1;
+                        }
+                        _ => output.push_str("• "),
+                    }
+                }
+                Tag::Emphasis => {
+                    styles.push(ITALIC);
+                    output.push_str(ITALIC);
+                }
+                Tag::Strong => {
+                    styles.push(BOLD);
+                    output.push_str(BOLD);
+                }
+                Tag::Strikethrough => {
+                    styles.push(STRIKETHROUGH);
+                    output.push_str(STRIKETHROUGH);
+                }
+                Tag::Link { dest_url, .. } => {
+                    link_urls.push(dest_url.to_string());
+                    styles.push(UNDERLINE);
+                    styles.push(BLUE);
+                    output.push_str(UNDERLINE);
+                    output.push_str(BLUE);
+                }
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Heading(_) => {
+                    styles.truncate(styles.len().saturating_sub(2));
+                    output.push_str(RESET);
+                }
+                TagEnd::BlockQuote(_) => quote_depth -= 1,
+                TagEnd::CodeBlock => in_code_block = false,
+                TagEnd::List(_) => {
+                    lists.pop();
+                }
+                TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                    styles.pop();
+                    output.push_str(RESET);
+                    output.push_str(&styles.concat());
+                }
+                TagEnd::Link => {
+                    styles.truncate(styles.len().saturating_sub(2));
+                    output.push_str(RESET);
+                    output.push_str(&styles.concat());
+                    if let Some(url) = link_urls.pop() {
+                        output.push_str(&format!(" {DIM}({}){RESET}", url));
+                        output.push_str(&styles.concat());
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(text) => {
+                if in_code_block {
+                    for line in text.lines() {
+                        output.push_str(&format!("{DIM}│{RESET} ").repeat(quote_depth));
+                        output.push_str(&format!("    {CYAN}{}{RESET}\n", line));
+                    }
+                } else {
+                    output.push_str(&text);
+                }
+            }
+            Event::Code(code) => {
+                output.push_str(&format!("{CYAN}{}{RESET}", code));
+                output.push_str(&styles.concat());
+            }
+            Event::TaskListMarker(checked) => {
+                output.push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            Event::SoftBreak => output.push(' '),
+            Event::HardBreak => start_line(&mut output, quote_depth),
+            Event::Rule => {
+                separate_block(&mut output);
+                start_line(&mut output, quote_depth);
+                output.push_str(&format!("{DIM}{}{RESET}\n", "─".repeat(40)));
+            }
+            Event::Html(text) | Event::InlineHtml(text) => output.push_str(&text),
+            _ => {}
+        }
+    }
+
+    output.trim_end().to_string()
+}
+
+/// Extracts the headings, links and code blocks of a Markdown document.
+pub fn extract(content: &str) -> Structure {
+    let mut structure = Structure::default();
+    let mut heading: Option<Heading> = None;
+    let mut link: Option<Link> = None;
+    let mut code_block: Option<CodeBlock> = None;
+
+    for event in parse(content) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                heading = Some(Heading {
+                    level: heading_level(level),
+                    text: String::new(),
+                });
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some(heading) = heading.take() {
+                    structure.headings.push(heading);
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                link = Some(Link {
+                    text: String::new(),
+                    url: dest_url.to_string(),
+                });
+            }
+            Event::End(TagEnd::Link) => {
+                if let Some(link) = link.take() {
+                    structure.links.push(link);
+                }
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) if !info.is_empty() => Some(info.to_string()),
+                    _ => None,
+                };
+                code_block = Some(CodeBlock {
+                    language,
+                    code: String::new(),
+                });
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some(code_block) = code_block.take() {
+                    structure.code_blocks.push(code_block);
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(code_block) = code_block.as_mut() {
+                    code_block.code.push_str(&text);
+                }
+                if let Some(heading) = heading.as_mut() {
+                    heading.text.push_str(&text);
+                }
+                if let Some(link) = link.as_mut() {
+                    link.text.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    structure
+}
+
+/// Renders a note like its `Display` implementation, with the content styled.
+pub fn render_note_for_terminal(note: &Note) -> String {
+    let mut output = format!(
+        "{BOLD}Title: {}{RESET}\n{}\n{DIM}Date Time: {}{RESET}",
+        note.get_title(),
+        if note.is_locked() {
+            format!("{DIM}(locked){RESET}")
+        } else {
+            to_ansi(note.get_content())
+        },
+        note.get_date_time()
+    );
+    if !note.get_tags().is_empty() {
+        output.push_str(&format!(
+            "\n{DIM}Tags: {}{RESET}",
+            note.get_tags().join(", ")
+        ));
+    }
+    let tasks = note.get_tasks();
+    if !tasks.is_empty() {
+        let done = tasks.iter().filter(|task| task.done).count();
+        output.push_str(&format!("\nTasks: {}/{} done", done, tasks.len()));
+    }
+    if let Some(reminder) = note.get_reminder() {
+        output.push_str(&format!("\nReminder: {}", reminder));
+    }
+    if note.is_pinned() {
+        output.push_str("\nPinned");
+    }
+    if note.is_archived() {
+        output.push_str("\nArchived");
+    }
+    if note.is_locked() {
+        output.push_str("\nLocked");
+    }
+    output
+}
+
+/// Renders a note as an HTML fragment, with the content converted from Markdown.
+pub fn render_note_as_html(note: &Note) -> String {
+    format!(
+        "<article>\n<h1>{}</h1>\n<time>{}</time>\n{}</article>\n",
+        escape_html(note.get_title()),
+        note.get_date_time(),
+        if note.is_locked() {
+            "<p><em>(locked)</em></p>\n".to_string()
+        } else {
+            to_html(note.get_content())
+        }
+    )
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut output = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reminders::Reminder;
+
+    // Removes ANSI escape codes so tests can check the plain text layout
+    fn strip_ansi(text: &str) -> String {
+        let mut output = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                for c in chars.by_ref() {
+                    if c == 'm' {
+                        break;
+                    }
+                }
+            } else {
+                output.push(c);
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_should_render_html() {
+        let html = to_html("# Title\n\nSome *emphasis* and `code`.");
+        assert_eq!(
+            html,
+            "<h1>Title</h1>\n<p>Some <em>emphasis</em> and <code>code</code>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_html_should_escape_raw_html() {
+        let html = to_html("<script>alert(1)</script>\n\nInline <img src=x onerror=alert(1)> tag");
+        assert_eq!(
+            html,
+            "&lt;script&gt;alert(1)&lt;/script&gt;\n<p>Inline &lt;img src=x onerror=alert(1)&gt; tag</p>\n"
+        );
+        assert_eq!(
+            to_html("[click](JavaScript:alert(1))"),
+            "<p><a href=\"#\">click</a></p>\n"
+        );
+        for url in [
+            "vbscript:msgbox(1)",
+            "data:text/html;base64,PHNjcmlwdD4=",
+            "java\tscript:alert(1)",
+        ] {
+            assert_eq!(
+                to_html(&format!("[click](<{}>)", url)),
+                "<p><a href=\"#\">click</a></p>\n"
+            );
+        }
+        assert_eq!(
+            to_html("![x](javascript:alert(1))"),
+            "<p><img src=\"\" alt=\"x\" /></p>\n"
+        );
+        assert_eq!(
+            to_html("[a](https://example.com) [b](notes/b.html#top) [c](mailto:a@b.c) ![d](d.png)"),
+            "<p><a href=\"https://example.com\">a</a> <a href=\"notes/b.html#top\">b</a> \
+             <a href=\"mailto:a@b.c\">c</a> <img src=\"d.png\" alt=\"d\" /></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_should_style_text_for_terminal() {
+        let ansi = to_ansi("Some **bold** text");
+        assert_eq!(ansi, format!("Some {BOLD}bold{RESET} text"));
+    }
+
+    #[test]
+    fn test_should_layout_blocks_for_terminal() {
+        let ansi = to_ansi(
+            "# Title\n\nParagraph\n\n- one\n- two\n\n1. first\n\n> quoted\n\n```\nlet x = 1;\n```",
+        );
+        assert_eq!(
+            strip_ansi(&ansi),
+            "# Title\n\nParagraph\n\n• one\n• two\n\n1. first\n\n│ quoted\n\n    let x = 1;"
+        );
+    }
+
+    #[test]
+    fn test_should_show_link_targets_for_terminal() {
+        let ansi = to_ansi("[Rust](https://www.rust-lang.org)");
+        assert_eq!(strip_ansi(&ansi), "Rust (https://www.rust-lang.org)");
+    }
+
+    #[test]
+    fn test_should_extract_structure() {
+        let structure = extract(
+            "# Intro\n\nSee [the docs](https://docs.rs) and [`std`](https://doc.rust-lang.org/std).\n\n## Usage\n\n```rust\nfn main() {}\n```\n",
+        );
+
+        assert_eq!(
+            structure.headings,
+            vec![
+                Heading {
+                    level: 1,
+                    text: "Intro".to_string()
+                },
+                Heading {
+                    level: 2,
+                    text: "Usage".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            structure.links,
+            vec![
+                Link {
+                    text: "the docs".to_string(),
+                    url: "https://docs.rs".to_string()
+                },
+                Link {
+                    text: "std".to_string(),
+                    url: "https://doc.rust-lang.org/std".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            structure.code_blocks,
+            vec![CodeBlock {
+                language: Some("rust".to_string()),
+                code: "fn main() {}\n".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_plain_text_has_no_structure() {
+        assert_eq!(extract("Just some words"), Structure::default());
+    }
+
+    #[test]
+    fn test_should_render_note_as_html() {
+        let note = Note::new_with_date_time(
+            "Tom & Jerry".to_string(),
+            "**Hello**".to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            render_note_as_html(&note),
+            "<article>\n<h1>Tom &amp; Jerry</h1>\n<time>2025-01-01 10:00</time>\n<p><strong>Hello</strong></p>\n</article>\n"
+        );
+    }
+
+    #[test]
+    fn test_should_render_note_for_terminal() {
+        let mut note = Note::new_with_date_time(
+            "Title".to_string(),
+            "*Hello*".to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+        note.add_tag("work").unwrap();
+        note.set_reminder(Some(Reminder::parse("2025-01-02 09:00 daily").unwrap()));
+
+        assert_eq!(
+            strip_ansi(&render_note_for_terminal(&note)),
+            "Title: Title\nHello\nDate Time: 2025-01-01 10:00\nTags: work\nReminder: 2025-01-02 09:00 daily"
+        );
+    }
+}
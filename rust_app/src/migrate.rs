@@ -0,0 +1,288 @@
+use crate::note::Note;
+use crate::persistence::event_log_persistence::EventLogPersistence;
+use crate::persistence::file_persistence::FilePersistence;
+use crate::persistence::git_persistence::GitPersistence;
+use crate::persistence::persistence_trait::PersistenceTrait;
+use crate::persistence::postgresql_persistence::PostgresqlPersistence;
+use crate::persistence::rusqlite_persistence::RusqlitePersistence;
+use crate::persistence::vault_persistence::VaultPersistence;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const USAGE: &str = "Usage: note_taker migrate --from <backend> --to <backend>\n\
+    Backends: file:<path>, sqlite:<path>, postgres[:<connection string>], vault:<directory>, git:<directory>, events:<path>\n\
+    Both backends are read into memory whole, so the notes must fit in memory.";
+
+/// Opens the backend named by a spec such as `file:notes.json` or
+/// `sqlite:notes.db`. A bare `postgres` uses `DATABASE_URL`.
+pub fn open_backend(spec: &str) -> Result<Box<dyn PersistenceTrait>, String> {
+    let (kind, location) = spec.split_once(':').unwrap_or((spec, ""));
+    if location.is_empty() && kind != "postgres" {
+        return Err(format!("Missing location in backend {}", spec));
+    }
+    Ok(match kind {
+        "file" => Box::new(FilePersistence::new(location.to_string())),
+        "sqlite" => Box::new(RusqlitePersistence::new(location).map_err(|e| e.to_string())?),
+        "postgres" if location.is_empty() => Box::new(PostgresqlPersistence::new()?),
+        "postgres" => Box::new(PostgresqlPersistence::with_connection_string(location)),
+        "vault" => Box::new(VaultPersistence::new(location)?),
+        "git" => Box::new(GitPersistence::new(location)?),
+        "events" => Box::new(EventLogPersistence::new(location)),
+        _ => return Err(format!("Unknown backend: {}", kind)),
+    })
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct MigrationReport {
+    pub read: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Titles of notes that were not found as written in the target afterwards, with why.
+    pub failed: Vec<(String, String)>,
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Read {} note(s): {} added, {} updated, {} already present, {} failed",
+            self.read,
+            self.added,
+            self.updated,
+            self.unchanged,
+            self.failed.len()
+        )?;
+        for (title, reason) in &self.failed {
+            write!(f, "\n  {}: {}", title, reason)?;
+        }
+        Ok(())
+    }
+}
+
+// Notes are equal by date time, title and content; this also covers their states and tags
+fn full_json(note: &Note) -> String {
+    note.to_json_value().to_string()
+}
+
+/// Copies every note of `from` into `to`, keeping the notes `to` already has.
+/// A note `to` already has takes the states and tags of the one in `from`, so
+/// running a migration again changes nothing. The target is read back
+/// afterwards, and notes it did not keep as written are reported as failed.
+pub fn migrate(
+    from: &dyn PersistenceTrait,
+    to: &dyn PersistenceTrait,
+) -> Result<MigrationReport, String> {
+    let source = from.load()?;
+    let mut target: BTreeMap<Note, Note> = to
+        .load()?
+        .into_iter()
+        .map(|note| (note.clone(), note))
+        .collect();
+    let mut report = MigrationReport {
+        read: source.len(),
+        ..MigrationReport::default()
+    };
+
+    let mut changed = false;
+    for note in &source {
+        match target.get(note) {
+            Some(existing) if full_json(existing) == full_json(note) => report.unchanged += 1,
+            Some(_) => {
+                report.updated += 1;
+                changed = true;
+                target.insert(note.clone(), note.clone());
+            }
+            None => {
+                report.added += 1;
+                changed = true;
+                target.insert(note.clone(), note.clone());
+            }
+        }
+    }
+    if changed {
+        let notes: Vec<Note> = target.into_values().collect();
+        to.save(&notes)?;
+    }
+
+    let written: BTreeMap<Note, Note> = to
+        .load()?
+        .into_iter()
+        .map(|note| (note.clone(), note))
+        .collect();
+    for note in &source {
+        let reason = match written.get(note) {
+            None => "missing from target",
+            Some(stored) if full_json(stored) != full_json(note) => "stored differently in target",
+            Some(_) => continue,
+        };
+        report
+            .failed
+            .push((note.get_title().to_string(), reason.to_string()));
+    }
+    Ok(report)
+}
+
+/// Runs `migrate --from <backend> --to <backend>` from command line arguments.
+pub fn run(args: &[String]) -> Result<MigrationReport, String> {
+    let mut from = None;
+    let mut to = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => from = args.next(),
+            "--to" => to = args.next(),
+            _ => return Err(format!("Unknown argument: {}\n{}", arg, USAGE)),
+        }
+    }
+    let (Some(from), Some(to)) = (from, to) else {
+        return Err(USAGE.to_string());
+    };
+    if from == to {
+        return Err("Source and target backends are the same".to_string());
+    }
+    migrate(open_backend(from)?.as_ref(), open_backend(to)?.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("note_taker_migrate_{}", name));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    fn note(title: &str, content: &str, date_time: &str) -> Note {
+        Note::new_with_date_time(
+            title.to_string(),
+            content.to_string(),
+            date_time.to_string(),
+        )
+        .unwrap()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_should_migrate_file_to_sqlite_idempotently() {
+        let file = temp_path("source.json");
+        let database = temp_path("target.db");
+        let mut pinned = note("Pinned", "Content", "2024-02-03 04:05");
+        pinned.set_pinned(true);
+        pinned.add_tag("work").unwrap();
+        FilePersistence::new(file.clone())
+            .save(&[note("Old", "Content", "2020-01-01 00:00"), pinned.clone()])
+            .unwrap();
+        let from = format!("file:{}", file);
+        let to = format!("sqlite:{}", database);
+
+        let first = run(&args(&["--from", &from, "--to", &to])).unwrap();
+        let second = run(&args(&["--from", &from, "--to", &to])).unwrap();
+
+        assert_eq!(
+            (first.read, first.added, first.unchanged, first.failed.len()),
+            (2, 2, 0, 0)
+        );
+        assert_eq!((second.added, second.updated, second.unchanged), (0, 0, 2));
+        let migrated = RusqlitePersistence::new(&database).unwrap().load().unwrap();
+        assert_eq!(
+            migrated,
+            vec![note("Old", "Content", "2020-01-01 00:00"), pinned]
+        );
+        assert!(migrated[1].is_pinned());
+        assert_eq!(migrated[1].get_tags(), ["work"]);
+
+        std::fs::remove_file(file).unwrap();
+        std::fs::remove_file(database).unwrap();
+    }
+
+    #[test]
+    fn test_should_keep_target_notes_and_update_states() {
+        let (source_path, target_path) =
+            (temp_path("states_source.db"), temp_path("states_target.db"));
+        let source = RusqlitePersistence::new(&source_path).unwrap();
+        let target = RusqlitePersistence::new(&target_path).unwrap();
+        let mut archived = note("Shared", "", "2025-01-01 10:00");
+        archived.set_archived(true);
+        source.save(&[archived.clone()]).unwrap();
+        target
+            .save(&[
+                note("Shared", "", "2025-01-01 10:00"),
+                note("Only in target", "", "2025-01-01 10:00"),
+            ])
+            .unwrap();
+
+        let report = migrate(&source, &target).unwrap();
+
+        assert_eq!((report.added, report.updated), (0, 1));
+        let notes = target.load().unwrap();
+        assert_eq!(notes.len(), 2);
+        assert!(notes.iter().any(|n| n == &archived && n.is_archived()));
+
+        std::fs::remove_file(source_path).unwrap();
+        std::fs::remove_file(target_path).unwrap();
+    }
+
+    // Keeps only untagged notes, like a backend without a place for tags
+    struct UntaggedPersistence(std::cell::RefCell<Vec<Note>>);
+
+    impl PersistenceTrait for UntaggedPersistence {
+        fn save(&self, notes: &[Note]) -> Result<(), String> {
+            *self.0.borrow_mut() = notes
+                .iter()
+                .filter(|note| note.get_tags().is_empty())
+                .cloned()
+                .collect();
+            Ok(())
+        }
+
+        fn load(&self) -> Result<Vec<Note>, String> {
+            Ok(self.0.borrow().clone())
+        }
+    }
+
+    #[test]
+    fn test_should_report_notes_the_target_did_not_keep() {
+        let mut tagged = note("Tagged", "", "2025-01-01 10:00");
+        tagged.add_tag("work").unwrap();
+        let source = UntaggedPersistence(std::cell::RefCell::new(vec![
+            tagged,
+            note("Plain", "", "2025-01-01 10:00"),
+        ]));
+        let target = UntaggedPersistence(std::cell::RefCell::new(Vec::new()));
+
+        let report = migrate(&source, &target).unwrap();
+
+        assert_eq!(report.added, 2);
+        assert_eq!(
+            report.failed,
+            vec![("Tagged".to_string(), "missing from target".to_string())]
+        );
+        assert_eq!(
+            report.to_string(),
+            "Read 2 note(s): 2 added, 0 updated, 0 already present, 1 failed\n  Tagged: missing from target"
+        );
+    }
+
+    #[test]
+    fn test_should_reject_bad_arguments() {
+        assert_eq!(
+            run(&args(&["--from", "file:a.json"])),
+            Err(USAGE.to_string())
+        );
+        assert!(run(&args(&["--from", "file:a", "--to", "file:a"])).is_err());
+        assert_eq!(
+            open_backend("ftp:host").err(),
+            Some("Unknown backend: ftp".to_string())
+        );
+        assert_eq!(
+            open_backend("sqlite:").err(),
+            Some("Missing location in backend sqlite:".to_string())
+        );
+    }
+}
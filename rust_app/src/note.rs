@@ -1,23 +1,33 @@
+use crate::crypto;
+use crate::links;
+use crate::reminders::Reminder;
+use crate::tasks::{self, Task};
+
 use chrono::prelude::*;
 use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+
+// Marks the content of a note locked with its own password
+const LOCK_PREFIX: &str = "note_taker:locked:v1:";
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct Note {
     title: String,
     content: String,
     date_time: NaiveDateTime,
+    archived: bool,
+    pinned: bool,
+    tags: Vec<String>,
+    // Boxed to keep notes small, as most have no reminder
+    reminder: Option<Box<Reminder>>,
+    uid: String,
 }
 
 impl Note {
-    pub fn new(mut title: String, mut content: String) -> Result<Note, String> {
-        title = title.trim().to_string();
-
-        if title.is_empty() {
-            return Err("Title cannot be empty or blank".to_string());
-        }
-
-        content = content.trim().to_string();
+    pub fn new(title: String, content: String) -> Result<Note, String> {
+        let title = Note::validate_title(title)?;
+        let content = content.trim().to_string();
 
         Ok(Note::new_private(
             title,
@@ -27,22 +37,30 @@ impl Note {
     }
 
     pub fn new_with_date_time(
-        mut title: String,
-        mut content: String,
+        title: String,
+        content: String,
         date_time: String,
     ) -> Result<Note, String> {
-        title = title.trim().to_string();
+        let title = Note::validate_title(title)?;
+        let content = content.trim().to_string();
+        let date_time = Note::parse_date_time(&date_time)?;
+
+        Ok(Note::new_private(title, content, date_time))
+    }
+
+    fn validate_title(title: String) -> Result<String, String> {
+        let title = title.trim().to_string();
 
         if title.is_empty() {
             return Err("Title cannot be empty or blank".to_string());
         }
 
-        content = content.trim().to_string();
-
-        let date_time = NaiveDateTime::parse_from_str(&date_time, "%Y-%m-%d %H:%M")
-            .map_err(|_| "Invalid date time format")?;
+        Ok(title)
+    }
 
-        Ok(Note::new_private(title, content, date_time))
+    fn parse_date_time(date_time: &str) -> Result<NaiveDateTime, String> {
+        NaiveDateTime::parse_from_str(date_time, "%Y-%m-%d %H:%M")
+            .map_err(|_| "Invalid date time format".to_string())
     }
 
     fn new_private(title: String, content: String, date_time: NaiveDateTime) -> Self {
@@ -50,6 +68,11 @@ impl Note {
             title,
             content,
             date_time,
+            archived: false,
+            pinned: false,
+            tags: Vec::new(),
+            reminder: None,
+            uid: crypto::random_id(),
         }
     }
 
@@ -65,13 +88,187 @@ impl Note {
         Self::format_date_time(self.date_time)
     }
 
-    pub fn get_as_json(&self) -> String {
-        format!(
-            r#"{{"title":"{}" ,"content":"{}", "date_time":"{}"}}"#,
-            self.get_title(),
-            self.get_content(),
-            self.get_date_time()
-        )
+    /// Returns a short identifier derived from the date time and title, so it
+    /// is the same across loads and backends but changes when the note is renamed.
+    pub fn get_id(&self) -> String {
+        // FNV-1a, which unlike the standard library hasher is stable across releases
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in format!("{}|{}", self.get_date_time(), self.title).bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:08x}", (hash ^ (hash >> 32)) as u32)
+    }
+
+    /// Returns an identifier given to the note when it was created, which
+    /// unlike `get_id` stays the same when it is renamed or moved.
+    pub fn get_uid(&self) -> &str {
+        &self.uid
+    }
+
+    /// Sets the identifier kept by a backend, or for notes saved before they
+    /// had one, the ID, so it stays the same until they are saved again.
+    pub fn set_uid(&mut self, uid: &str) {
+        self.uid = match uid.trim() {
+            "" => self.get_id(),
+            uid => uid.to_string(),
+        };
+    }
+
+    /// Returns the targets of the `[[Other note]]` links in the content,
+    /// none while the note is locked.
+    pub fn get_links(&self) -> Vec<String> {
+        if self.is_locked() {
+            return Vec::new();
+        }
+        links::parse_links(&self.content)
+    }
+
+    /// Returns the checklist items in the content, none while the note is locked.
+    pub fn get_tasks(&self) -> Vec<Task> {
+        if self.is_locked() {
+            return Vec::new();
+        }
+        tasks::parse_tasks(&self.content)
+    }
+
+    pub fn set_title(&mut self, title: String) -> Result<(), String> {
+        self.title = Note::validate_title(title)?;
+        Ok(())
+    }
+
+    pub fn set_content(&mut self, content: String) {
+        self.content = content.trim().to_string();
+    }
+
+    pub fn set_date_time(&mut self, date_time: &str) -> Result<(), String> {
+        self.date_time = Note::parse_date_time(date_time)?;
+        Ok(())
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
+
+    pub fn set_archived(&mut self, archived: bool) {
+        self.archived = archived;
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    pub fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+    }
+
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Adds a tag, returning `false` if the note already had it.
+    pub fn add_tag(&mut self, tag: &str) -> Result<bool, String> {
+        let tag = tag.trim();
+
+        if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+            return Err("Tag cannot be empty or contain spaces or commas".to_string());
+        }
+        if self.has_tag(tag) {
+            return Ok(false);
+        }
+
+        let idx = self.tags.partition_point(|t| t.as_str() < tag);
+        self.tags.insert(idx, tag.to_string());
+        Ok(true)
+    }
+
+    /// Removes a tag, returning `false` if the note did not have it.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let len = self.tags.len();
+        self.tags.retain(|t| t != tag.trim());
+        self.tags.len() != len
+    }
+
+    pub fn get_reminder(&self) -> Option<&Reminder> {
+        self.reminder.as_deref()
+    }
+
+    pub fn set_reminder(&mut self, reminder: Option<Reminder>) {
+        self.reminder = reminder.map(Box::new);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        crypto::is_sealed(&self.content, LOCK_PREFIX)
+    }
+
+    /// Encrypts the content with a password of its own; the title, date time
+    /// and tags stay readable.
+    pub fn lock(&mut self, password: &str) -> Result<(), String> {
+        if self.is_locked() {
+            return Err("Note is already locked".to_string());
+        }
+        if password.is_empty() {
+            return Err("Password cannot be empty".to_string());
+        }
+        let salt = crypto::generate_salt();
+        let key = crypto::derive_key(password, &salt)?;
+        self.content = crypto::seal(&key, &salt, &self.content, LOCK_PREFIX)?;
+        Ok(())
+    }
+
+    /// Decrypts the content of a locked note without unlocking it.
+    pub fn get_unlocked_content(&self, password: &str) -> Result<String, String> {
+        let envelope = crypto::parse(&self.content, LOCK_PREFIX).ok_or("Note is not locked")??;
+        let key = crypto::derive_key(password, &envelope.salt)?;
+        crypto::open(&key, &envelope, LOCK_PREFIX).ok_or("Wrong password".to_string())
+    }
+
+    pub fn unlock(&mut self, password: &str) -> Result<(), String> {
+        self.content = self.get_unlocked_content(password)?;
+        Ok(())
+    }
+
+    /// Returns the note as a JSON object, with strings escaped as needed.
+    pub fn to_json_value(&self) -> serde_json::Value {
+        serde_json::json!({
+            "title": self.get_title(),
+            "content": self.get_content(),
+            "date_time": self.get_date_time(),
+            "archived": self.is_archived(),
+            "pinned": self.is_pinned(),
+            "tags": self.get_tags(),
+            "reminder": self.get_reminder().map(|reminder| reminder.to_string()),
+            "uid": self.get_uid(),
+        })
+    }
+
+    /// Reads a note from a JSON object like the ones `to_json_value` returns.
+    pub fn from_json_value(json_value: &serde_json::Value) -> Result<Note, String> {
+        let field = |key: &str| {
+            json_value[key]
+                .as_str()
+                .map(|value| value.to_string())
+                .ok_or(format!("Missing {} in note", key))
+        };
+        let mut note =
+            Note::new_with_date_time(field("title")?, field("content")?, field("date_time")?)?;
+        // Notes saved before they could be archived, pinned or tagged lack these keys
+        note.set_archived(json_value["archived"].as_bool().unwrap_or(false));
+        note.set_pinned(json_value["pinned"].as_bool().unwrap_or(false));
+        if let Some(tags) = json_value["tags"].as_array() {
+            for tag in tags.iter().filter_map(|tag| tag.as_str()) {
+                note.add_tag(tag)?;
+            }
+        }
+        if let Some(reminder) = json_value["reminder"].as_str() {
+            note.set_reminder(Some(Reminder::parse(reminder)?));
+        }
+        note.set_uid(json_value["uid"].as_str().unwrap_or_default());
+        Ok(note)
     }
 
     fn get_rounded_date_time() -> NaiveDateTime {
@@ -93,9 +290,53 @@ impl fmt::Display for Note {
             f,
             "Title: {}\nContent: {}\nDate Time: {}",
             self.get_title(),
-            self.get_content(),
+            if self.is_locked() {
+                "(locked)"
+            } else {
+                self.get_content()
+            },
             self.get_date_time()
-        )
+        )?;
+        if !self.tags.is_empty() {
+            write!(f, "\nTags: {}", self.tags.join(", "))?;
+        }
+        let tasks = self.get_tasks();
+        if !tasks.is_empty() {
+            let done = tasks.iter().filter(|task| task.done).count();
+            write!(f, "\nTasks: {}/{} done", done, tasks.len())?;
+        }
+        if let Some(reminder) = &self.reminder {
+            write!(f, "\nReminder: {}", reminder)?;
+        }
+        if self.is_pinned() {
+            write!(f, "\nPinned")?;
+        }
+        if self.is_archived() {
+            write!(f, "\nArchived")?;
+        }
+        if self.is_locked() {
+            write!(f, "\nLocked")?;
+        }
+        Ok(())
+    }
+}
+
+// A note is identified by its date time, title and content; its tags, reminder,
+// UID and the archived and pinned states can change without turning it into a
+// different note.
+impl PartialEq for Note {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Note {}
+
+impl Hash for Note {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.date_time.hash(state);
+        self.title.hash(state);
+        self.content.hash(state);
     }
 }
 
@@ -233,13 +474,263 @@ mod tests {
         let now = Utc::now().naive_utc();
         let note = Note::new_private("Test Title".to_string(), "Test Content".to_string(), now);
 
-        let expected_json = format!(
-            r#"{{"title":"{}" ,"content":"{}", "date_time":"{}"}}"#,
-            note.get_title(),
-            note.get_content(),
-            note.get_date_time()
+        let expected_json = serde_json::json!({
+            "title": note.get_title(),
+            "content": note.get_content(),
+            "date_time": note.get_date_time(),
+            "archived": false,
+            "pinned": false,
+            "tags": [],
+            "reminder": null,
+            "uid": note.get_uid(),
+        });
+
+        assert_eq!(note.to_json_value(), expected_json);
+    }
+
+    #[test]
+    fn test_new_note_should_not_be_archived_or_pinned() {
+        let note = Note::new("Test Title".to_string(), "Test Content".to_string()).unwrap();
+
+        assert!(!note.is_archived());
+        assert!(!note.is_pinned());
+    }
+
+    #[test]
+    fn test_archived_and_pinned_states_do_not_change_equality() {
+        let now = Utc::now().naive_utc();
+        let note1 = Note::new_private("Test Title".to_string(), "Test Content".to_string(), now);
+        let mut note2 = note1.clone();
+
+        note2.set_archived(true);
+        note2.set_pinned(true);
+
+        assert_eq!(note1, note2);
+        assert!(note2.is_archived());
+        assert!(note2.is_pinned());
+    }
+
+    #[test]
+    fn test_note_to_string_should_show_states() {
+        let now = Utc::now().naive_utc();
+        let mut note = Note::new_private("Test Title".to_string(), "Test Content".to_string(), now);
+        note.set_pinned(true);
+        note.set_archived(true);
+
+        assert!(note.to_string().ends_with("\nPinned\nArchived"));
+    }
+
+    #[test]
+    fn test_should_edit_title_and_content() {
+        let mut note = Note::new("Test Title".to_string(), "Test Content".to_string()).unwrap();
+
+        note.set_title(" New Title ".to_string()).unwrap();
+        note.set_content(" New Content ".to_string());
+
+        assert_eq!(note.get_title(), "New Title");
+        assert_eq!(note.get_content(), "New Content");
+        assert!(note.set_title("  ".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_should_change_date_time() {
+        let mut note = Note::new("Test Title".to_string(), "Test Content".to_string()).unwrap();
+
+        note.set_date_time("2024-05-06 07:08").unwrap();
+
+        assert_eq!(note.get_date_time(), "2024-05-06 07:08");
+        assert!(note.set_date_time("tomorrow").is_err());
+    }
+
+    #[test]
+    fn test_tags_should_be_sorted_and_unique() {
+        let mut note = Note::new("Test Title".to_string(), "Test Content".to_string()).unwrap();
+
+        assert!(note.add_tag("work").unwrap());
+        assert!(note.add_tag(" home ").unwrap());
+        assert!(!note.add_tag("work").unwrap());
+
+        assert_eq!(note.get_tags(), ["home", "work"]);
+        assert!(note.has_tag("home"));
+    }
+
+    #[test]
+    fn test_should_reject_invalid_tags() {
+        let mut note = Note::new("Test Title".to_string(), "Test Content".to_string()).unwrap();
+
+        assert!(note.add_tag(" ").is_err());
+        assert!(note.add_tag("two words").is_err());
+        assert!(note.add_tag("a,b").is_err());
+    }
+
+    #[test]
+    fn test_should_remove_tag() {
+        let mut note = Note::new("Test Title".to_string(), "Test Content".to_string()).unwrap();
+        note.add_tag("work").unwrap();
+
+        assert!(note.remove_tag("work"));
+        assert!(!note.remove_tag("work"));
+        assert!(note.get_tags().is_empty());
+    }
+
+    #[test]
+    fn test_tagged_note_to_json_and_string() {
+        let now = Utc::now().naive_utc();
+        let mut note = Note::new_private("Test Title".to_string(), "Test Content".to_string(), now);
+        note.add_tag("work").unwrap();
+        note.add_tag("home").unwrap();
+
+        assert_eq!(
+            note.to_json_value()["tags"],
+            serde_json::json!(["home", "work"])
+        );
+        assert!(note.to_string().ends_with("\nTags: home, work"));
+    }
+
+    #[test]
+    fn test_note_with_reminder_to_json_and_string() {
+        let mut note = Note::new_with_date_time(
+            "Standup".to_string(),
+            String::new(),
+            "2025-03-04 08:00".to_string(),
+        )
+        .unwrap();
+        note.set_reminder(Some(Reminder::parse("2025-03-04 09:00 daily").unwrap()));
+
+        assert_eq!(note.to_json_value()["reminder"], "2025-03-04 09:00 daily");
+        assert!(
+            note.to_string()
+                .ends_with("\nReminder: 2025-03-04 09:00 daily")
+        );
+        let loaded = Note::from_json_value(&note.to_json_value()).unwrap();
+        assert_eq!(loaded.get_reminder(), note.get_reminder());
+    }
+
+    #[test]
+    fn test_uid_should_be_kept_and_survive_rename() {
+        let mut note = Note::new("Title".to_string(), "Content".to_string()).unwrap();
+        let other = Note::new("Title".to_string(), "Content".to_string()).unwrap();
+        let uid = note.get_uid().to_string();
+
+        assert_ne!(other.get_uid(), uid);
+        assert_eq!(
+            Note::from_json_value(&note.to_json_value())
+                .unwrap()
+                .get_uid(),
+            uid
         );
+        note.set_title("Renamed".to_string()).unwrap();
+        assert_eq!(note.get_uid(), uid);
+
+        // Notes saved before they had UIDs use their IDs
+        let mut json = note.to_json_value();
+        json.as_object_mut().unwrap().remove("uid");
+        let loaded = Note::from_json_value(&json).unwrap();
+        assert_eq!(loaded.get_uid(), loaded.get_id());
+    }
+
+    #[test]
+    fn test_id_should_depend_on_date_time_and_title() {
+        let note = Note::new_with_date_time(
+            "Title".to_string(),
+            "Content".to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+        let mut edited = note.clone();
+        edited.set_content("Other content".to_string());
+        let mut renamed = note.clone();
+        renamed.set_title("Other title".to_string()).unwrap();
+
+        assert_eq!(note.get_id().len(), 8);
+        assert_eq!(note.get_id(), edited.get_id());
+        assert_ne!(note.get_id(), renamed.get_id());
+    }
+
+    #[test]
+    fn test_should_have_links() {
+        let note = Note::new(
+            "Title".to_string(),
+            "See [[Other]] and [[Another|it]]".to_string(),
+        )
+        .unwrap();
+        assert_eq!(note.get_links(), vec!["Other", "Another"]);
+    }
+
+    #[test]
+    fn test_should_show_task_progress() {
+        let mut note =
+            Note::new("Title".to_string(), "- [x] Done\n- [ ] Open".to_string()).unwrap();
+        assert_eq!(note.get_tasks().len(), 2);
+        assert!(note.to_string().ends_with("\nTasks: 1/2 done"));
+
+        note.lock("password").unwrap();
+        assert!(note.get_tasks().is_empty());
+    }
+
+    #[test]
+    fn test_note_should_round_trip_through_json_value() {
+        let mut note = Note::new(
+            "Title with \"quotes\"".to_string(),
+            "Line 1\nLine 2".to_string(),
+        )
+        .unwrap();
+        note.set_pinned(true);
+        note.add_tag("work").unwrap();
 
-        assert_eq!(note.get_as_json(), expected_json);
+        let loaded = Note::from_json_value(&note.to_json_value()).unwrap();
+
+        assert_eq!(loaded, note);
+        assert!(loaded.is_pinned());
+        assert_eq!(loaded.get_tags(), ["work"]);
+    }
+
+    #[test]
+    fn test_should_reject_json_value_without_title() {
+        let json_value = serde_json::json!({"content": "Content", "date_time": "2025-01-01 10:00"});
+        assert_eq!(
+            Note::from_json_value(&json_value),
+            Err("Missing title in note".to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_lock_and_unlock_content() {
+        let mut note = Note::new_with_date_time(
+            "Bank".to_string(),
+            "PIN 1234, see [[Accounts]]".to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+
+        note.lock("secret").unwrap();
+
+        assert!(note.is_locked());
+        assert!(!note.get_content().contains("1234"));
+        assert!(note.get_links().is_empty());
+        assert_eq!(
+            note.to_string(),
+            "Title: Bank\nContent: (locked)\nDate Time: 2025-01-01 10:00\nLocked"
+        );
+        assert_eq!(
+            note.lock("secret"),
+            Err("Note is already locked".to_string())
+        );
+        assert_eq!(
+            note.get_unlocked_content("wrong"),
+            Err("Wrong password".to_string())
+        );
+        assert_eq!(
+            note.get_unlocked_content("secret").unwrap(),
+            "PIN 1234, see [[Accounts]]"
+        );
+
+        note.unlock("secret").unwrap();
+        assert!(!note.is_locked());
+        assert_eq!(note.get_content(), "PIN 1234, see [[Accounts]]");
+        assert_eq!(
+            note.get_unlocked_content("secret"),
+            Err("Note is not locked".to_string())
+        );
     }
 }
@@ -1,10 +1,32 @@
+use crate::autosave::Autosave;
+use crate::calendar;
+use crate::history::{Command, DEFAULT_HISTORY_DEPTH, History};
+use crate::links;
 use crate::note::Note;
-use crate::persistence::persistence_trait::PersistenceTrait;
+use crate::persistence::persistence_trait::{PersistenceTrait, Revision};
+use crate::reminders::{Reminder, Repeat};
+use crate::tasks::{self, Task};
+use crate::templates::TEMPLATE_TAG;
+
+use chrono::NaiveDateTime;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 pub struct NoteTaker {
     notes: Vec<Note>,
     size: usize,
     persistence: Box<dyn PersistenceTrait>,
+    history: History,
+    // Normalized link targets to the notes linking to them
+    backlinks: HashMap<String, BTreeSet<Note>>,
+    unsaved: Cell<bool>,
+    autosave: RefCell<Option<Autosave>>,
+    calendar_feed: Option<PathBuf>,
+    calendar_feed_error: RefCell<Option<String>>,
+    // A copy of the notes kept up to date for other threads
+    shared: Option<Arc<Mutex<Vec<Note>>>>,
 }
 
 impl NoteTaker {
@@ -13,20 +35,83 @@ impl NoteTaker {
             notes: Vec::new(),
             size: 0,
             persistence,
+            history: History::new(DEFAULT_HISTORY_DEPTH),
+            backlinks: HashMap::new(),
+            unsaved: Cell::new(false),
+            autosave: RefCell::new(None),
+            calendar_feed: None,
+            calendar_feed_error: RefCell::new(None),
+            shared: None,
         }
     }
 
     pub fn add_note(&mut self, note: Note) {
+        if self.insert_note(note.clone()) {
+            let command = Command::Insert(note);
+            self.changed(&command);
+            self.history.record(command);
+        }
+    }
+
+    fn insert_note(&mut self, note: Note) -> bool {
         if self.notes.binary_search(&note).is_ok() {
-            return;
+            return false;
+        }
+        for target in note.get_links() {
+            self.backlinks
+                .entry(links::normalize(&target))
+                .or_default()
+                .insert(note.clone());
         }
         let idx = self.notes.partition_point(|n| n < &note);
-        self.notes.insert(idx, note.clone());
+        self.notes.insert(idx, note);
         self.size += 1;
+        true
+    }
+
+    fn remove_note(&mut self, index: usize) -> Note {
+        let note = self.notes.remove(index);
+        self.size -= 1;
+        for target in note.get_links() {
+            let target = links::normalize(&target);
+            if let Some(sources) = self.backlinks.get_mut(&target) {
+                sources.remove(&note);
+                if sources.is_empty() {
+                    self.backlinks.remove(&target);
+                }
+            }
+        }
+        note
     }
 
+    /// Returns the notes that are neither archived nor templates, with
+    /// pinned notes first.
     pub fn get_notes(&self) -> Vec<Note> {
-        self.notes.clone()
+        self.list_notes(|note| !note.is_archived() && !note.has_tag(TEMPLATE_TAG))
+    }
+
+    /// Returns every note, archived ones included, with pinned notes first.
+    pub fn get_all_notes(&self) -> Vec<Note> {
+        self.list_notes(|_| true)
+    }
+
+    pub fn get_archived_notes(&self) -> Vec<Note> {
+        self.list_notes(|note| note.is_archived())
+    }
+
+    fn list_notes(&self, filter: impl Fn(&Note) -> bool) -> Vec<Note> {
+        let (pinned, unpinned): (Vec<Note>, Vec<Note>) = self
+            .notes
+            .iter()
+            .filter(|note| filter(note))
+            .cloned()
+            .partition(|note| note.is_pinned());
+        pinned.into_iter().chain(unpinned).collect()
+    }
+
+    /// Returns the index of a note as used by `get_note` and `delete_note`.
+    pub fn index_of(&self, note: &Note) -> Option<usize> {
+        self.notes.binary_search(note).ok()
     }
 
     #[allow(dead_code)]
@@ -51,21 +136,489 @@ impl NoteTaker {
     pub fn load(&mut self) -> Result<(), String> {
         let loaded_notes = self.persistence.load()?;
         for note in loaded_notes {
-            self.add_note(note);
+            self.insert_note(note);
         }
+        self.history.clear();
+        self.unsaved.set(false);
         Ok(())
     }
 
+    /// Journals every change from now on and saves it as `autosave` says,
+    /// after replaying the changes a previous session left unsaved in its
+    /// journal. Returns how many changes were replayed.
+    pub fn enable_autosave(&mut self, mut autosave: Autosave) -> Result<usize, String> {
+        let commands = autosave.read_journal()?;
+        for command in &commands {
+            // Changes already saved before the journal was emptied are skipped
+            let _ = self.apply(command);
+        }
+        self.history.clear();
+        if !commands.is_empty() {
+            self.unsaved.set(true);
+            autosave.set_pending(commands.len());
+        }
+        *self.autosave.borrow_mut() = Some(autosave);
+        Ok(commands.len())
+    }
+
+    /// Returns a copy of the notes that is updated on every change, which a
+    /// signal handler can save while this note taker is in use.
+    pub fn share_notes(&mut self) -> Arc<Mutex<Vec<Note>>> {
+        let shared = Arc::new(Mutex::new(self.notes.clone()));
+        self.shared = Some(Arc::clone(&shared));
+        shared
+    }
+
+    // Journals a change that was just made, saving it if autosave is due
+    fn changed(&self, command: &Command) {
+        self.unsaved.set(true);
+        if let Some(shared) = &self.shared {
+            // A thread that panicked holding the lock left a copy that is
+            // replaced whole here anyway
+            let mut notes = shared.lock().unwrap_or_else(|e| e.into_inner());
+            *notes = self.notes.clone();
+        }
+        if let Some(autosave) = self.autosave.borrow_mut().as_mut()
+            && let Err(e) = autosave.record(command)
+        {
+            autosave.set_error(format!("Failed to journal change: {}", e));
+        }
+        if let Err(e) = self.autosave_if_due()
+            && let Some(autosave) = self.autosave.borrow_mut().as_mut()
+        {
+            autosave.set_error(format!("Autosave failed: {}", e));
+        }
+    }
+
+    /// Saves the notes if autosave is enabled and due, returning whether it saved.
+    pub fn autosave_if_due(&self) -> Result<bool, String> {
+        let due = self
+            .autosave
+            .borrow()
+            .as_ref()
+            .is_some_and(|autosave| autosave.is_due());
+        if due {
+            self.save()?;
+        }
+        Ok(due)
+    }
+
+    /// Returns the last error autosave ran into, if any, to report it.
+    pub fn take_autosave_error(&self) -> Option<String> {
+        self.autosave
+            .borrow_mut()
+            .as_mut()
+            .and_then(|autosave| autosave.take_error())
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.unsaved.get()
+    }
+
+    /// Replaces every note, e.g. with those of a restored backup. This cannot
+    /// be undone, so the history is cleared.
+    pub fn replace_notes(&mut self, notes: Vec<Note>) {
+        let removed = self.notes.iter().cloned().map(Command::Remove);
+        let inserted = notes.iter().cloned().map(Command::Insert);
+        let command = Command::Batch(removed.chain(inserted).collect());
+        self.notes.clear();
+        self.size = 0;
+        self.backlinks.clear();
+        for note in notes {
+            self.insert_note(note);
+        }
+        self.history.clear();
+        self.changed(&command);
+    }
+
+    /// Lists the past states of the notes kept by the storage, most recent first.
+    pub fn get_revisions(&self) -> Result<Vec<Revision>, String> {
+        self.persistence.get_history()
+    }
+
+    /// Brings the notes back to a revision from `get_revisions`. Unsaved
+    /// changes are saved first, so the storage keeps them in its history.
+    pub fn restore_revision(&mut self, revision: &str) -> Result<(), String> {
+        if self.has_unsaved_changes() {
+            self.save()?;
+        }
+        let notes = self.persistence.restore(revision)?;
+        self.replace_notes(notes);
+        Ok(())
+    }
+
+    /// Writes an iCalendar feed of the notes to `path` on every save, or
+    /// stops writing one with `None`.
+    pub fn set_calendar_feed(&mut self, path: Option<PathBuf>) {
+        self.calendar_feed = path;
+    }
+
+    /// Returns the error of the last calendar feed that failed to be written.
+    /// The notes are saved all the same.
+    pub fn take_calendar_feed_error(&self) -> Option<String> {
+        self.calendar_feed_error.borrow_mut().take()
+    }
+
     pub fn save(&self) -> Result<(), String> {
-        self.persistence.save(&self.notes)
+        self.persistence.save(&self.notes)?;
+        self.unsaved.set(false);
+        if let Some(path) = &self.calendar_feed
+            && let Err(e) = calendar::write_feed(&self.notes, path)
+        {
+            *self.calendar_feed_error.borrow_mut() = Some(e);
+        }
+        match self.autosave.borrow_mut().as_mut() {
+            Some(autosave) => autosave.saved(),
+            None => Ok(()),
+        }
     }
 
     pub fn delete_note(&mut self, index: usize) {
         if index >= self.get_size() {
             panic!("Index out of bounds");
         }
-        self.notes.remove(index);
-        self.size -= 1;
+        let command = Command::Remove(self.remove_note(index));
+        self.changed(&command);
+        self.history.record(command);
+    }
+
+    /// Edits a note; when it is renamed, links to it from other notes are
+    /// rewritten to the new title as part of the same undoable operation.
+    pub fn edit_note(
+        &mut self,
+        index: usize,
+        title: String,
+        content: String,
+    ) -> Result<(), String> {
+        let before = self.get_note(index);
+        if before.is_locked() {
+            return Err("Note is locked".to_string());
+        }
+        let mut note = before.clone();
+        note.set_title(title)?;
+        note.set_content(content);
+
+        if note.get_id() == before.get_id() {
+            return self.replace_note(index, note);
+        }
+
+        let old_id = before.get_id();
+        let old_title = before.get_title().to_string();
+        let mut old_targets = vec![old_id.as_str()];
+        // Links by title only pointed here if no other note has that title
+        let title_is_unique = self
+            .notes
+            .iter()
+            .filter(|n| links::normalize(n.get_title()) == links::normalize(before.get_title()))
+            .count()
+            == 1;
+        if title_is_unique {
+            old_targets.push(old_title.as_str());
+        }
+
+        let linking_notes: BTreeSet<Note> = old_targets
+            .iter()
+            .filter_map(|target| self.backlinks.get(&links::normalize(target)))
+            .flatten()
+            .filter(|n| **n != before)
+            .cloned()
+            .collect();
+
+        let rewritten = links::rewrite_links(note.get_content(), &old_targets, note.get_title());
+        note.set_content(rewritten);
+        let mut commands = vec![Command::Replace {
+            before,
+            after: note.clone(),
+        }];
+        for linking_note in linking_notes {
+            let mut updated = linking_note.clone();
+            updated.set_content(links::rewrite_links(
+                linking_note.get_content(),
+                &old_targets,
+                note.get_title(),
+            ));
+            commands.push(Command::Replace {
+                before: linking_note,
+                after: updated,
+            });
+        }
+        self.execute(Command::Batch(commands))
+    }
+
+    /// Moves a note to another date time, and so to another place in the list.
+    pub fn move_note(&mut self, index: usize, date_time: &str) -> Result<(), String> {
+        let mut note = self.get_note(index);
+        note.set_date_time(date_time)?;
+        self.replace_note(index, note)
+    }
+
+    /// Tags a note. The template tag is kept for templates, so notes only
+    /// become templates through the templates command.
+    pub fn tag_note(&mut self, index: usize, tag: &str) -> Result<(), String> {
+        if tag.trim() == TEMPLATE_TAG {
+            return Err(format!("The {} tag is kept for templates", TEMPLATE_TAG));
+        }
+        let mut note = self.get_note(index);
+        if note.add_tag(tag)? {
+            self.replace_note(index, note)?;
+        }
+        Ok(())
+    }
+
+    pub fn untag_note(&mut self, index: usize, tag: &str) -> Result<(), String> {
+        let mut note = self.get_note(index);
+        if note.remove_tag(tag) {
+            self.replace_note(index, note)?;
+        }
+        Ok(())
+    }
+
+    /// Locks the content of a note with a password. While locked, its content
+    /// is left out of the link index, so its links give no backlinks.
+    pub fn lock_note(&mut self, index: usize, password: &str) -> Result<(), String> {
+        let mut note = self.get_note(index);
+        note.lock(password)?;
+        self.replace_note(index, note)
+    }
+
+    pub fn unlock_note(&mut self, index: usize, password: &str) -> Result<(), String> {
+        let mut note = self.get_note(index);
+        note.unlock(password)?;
+        self.replace_note(index, note)
+    }
+
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) {
+        let mut note = self.get_note(index);
+        if note.is_pinned() != pinned {
+            note.set_pinned(pinned);
+            self.replace_note(index, note).unwrap();
+        }
+    }
+
+    pub fn set_archived(&mut self, index: usize, archived: bool) {
+        let mut note = self.get_note(index);
+        if note.is_archived() != archived {
+            note.set_archived(archived);
+            self.replace_note(index, note).unwrap();
+        }
+    }
+
+    fn replace_note(&mut self, index: usize, note: Note) -> Result<(), String> {
+        self.execute(Command::Replace {
+            before: self.notes[index].clone(),
+            after: note,
+        })
+    }
+
+    fn execute(&mut self, command: Command) -> Result<(), String> {
+        self.apply(&command)?;
+        self.changed(&command);
+        self.history.record(command);
+        Ok(())
+    }
+
+    fn apply(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::Insert(note) => {
+                if !self.insert_note(note.clone()) {
+                    return Err("Note already exists".to_string());
+                }
+            }
+            Command::Remove(note) => {
+                let index = self.index_of(note).ok_or("Note not found")?;
+                self.remove_note(index);
+            }
+            Command::Replace { before, after } => {
+                let index = self.index_of(before).ok_or("Note not found")?;
+                if before != after && self.index_of(after).is_some() {
+                    return Err("Note already exists".to_string());
+                }
+                self.remove_note(index);
+                self.insert_note(after.clone());
+            }
+            Command::Batch(commands) => {
+                for (applied, command) in commands.iter().enumerate() {
+                    if let Err(e) = self.apply(command) {
+                        // Leave the notes as they were, so the batch is all or nothing
+                        for command in commands[..applied].iter().rev() {
+                            self.apply(&command.inverse())?;
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the index of the note a link points to, matching titles
+    /// regardless of case before falling back to note IDs.
+    pub fn resolve_link(&self, target: &str) -> Option<usize> {
+        let target = links::normalize(target);
+        self.notes
+            .iter()
+            .position(|note| links::normalize(note.get_title()) == target)
+            .or_else(|| self.notes.iter().position(|note| note.get_id() == target))
+    }
+
+    /// Returns the links of a note with the index of the note each points to.
+    pub fn get_links(&self, index: usize) -> Vec<(String, Option<usize>)> {
+        self.get_note(index)
+            .get_links()
+            .into_iter()
+            .map(|target| {
+                let resolved = self.resolve_link(&target);
+                (target, resolved)
+            })
+            .collect()
+    }
+
+    /// Returns the indexes of the notes linking to a note by title or ID.
+    pub fn get_backlinks(&self, index: usize) -> Vec<usize> {
+        let note = self.get_note(index);
+        let mut result: Vec<usize> = [links::normalize(note.get_title()), note.get_id()]
+            .iter()
+            .filter_map(|target| self.backlinks.get(target))
+            .flatten()
+            .filter_map(|source| self.index_of(source))
+            .filter(|&i| i != index)
+            .collect();
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    /// Returns the links that do not point to any note, with the index of the
+    /// note they are in.
+    pub fn get_broken_links(&self) -> Vec<(usize, String)> {
+        let mut result = Vec::new();
+        for (i, note) in self.notes.iter().enumerate() {
+            for target in note.get_links() {
+                if self.resolve_link(&target).is_none() {
+                    result.push((i, target));
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns the open checklist items of the notes that are not archived,
+    /// listed as `get_notes` lists the notes, with the index of their note.
+    pub fn get_open_tasks(&self) -> Vec<(usize, Task)> {
+        self.get_notes()
+            .iter()
+            .filter_map(|note| self.index_of(note))
+            .flat_map(|index| {
+                self.notes[index]
+                    .get_tasks()
+                    .into_iter()
+                    .filter(|task| !task.done)
+                    .map(move |task| (index, task))
+            })
+            .collect()
+    }
+
+    /// Checks or unchecks the task on a line of a note's content.
+    pub fn toggle_task(&mut self, index: usize, line: usize) -> Result<(), String> {
+        let mut note = self.get_note(index);
+        if note.is_locked() {
+            return Err("Note is locked".to_string());
+        }
+        note.set_content(tasks::toggle_task(note.get_content(), line)?);
+        self.replace_note(index, note)
+    }
+
+    /// Counts the done tasks and all tasks of a note.
+    pub fn get_task_progress(&self, index: usize) -> (usize, usize) {
+        let tasks = self.get_note(index).get_tasks();
+        (tasks.iter().filter(|task| task.done).count(), tasks.len())
+    }
+
+    /// Sets or, with `None`, clears the reminder of a note.
+    pub fn set_reminder(&mut self, index: usize, reminder: Option<Reminder>) -> Result<(), String> {
+        let mut note = self.get_note(index);
+        if note.get_reminder() != reminder.as_ref() {
+            note.set_reminder(reminder);
+            self.replace_note(index, note)?;
+        }
+        Ok(())
+    }
+
+    /// Puts off the reminder of a note until a later time, or sets one for
+    /// then when the note has none, as once it has fired.
+    pub fn snooze_reminder(&mut self, index: usize, until: NaiveDateTime) -> Result<(), String> {
+        let reminder = match self.get_note(index).get_reminder() {
+            Some(reminder) => {
+                let mut reminder = reminder.clone();
+                reminder.snooze(until);
+                reminder
+            }
+            None => Reminder::new(until, Repeat::Never)?,
+        };
+        self.set_reminder(index, Some(reminder))
+    }
+
+    /// Returns the indexes of the notes that are neither archived nor
+    /// templates with a reminder due by `now`.
+    pub fn get_due_reminders(&self, now: NaiveDateTime) -> Vec<usize> {
+        (0..self.notes.len())
+            .filter(|&index| {
+                let note = &self.notes[index];
+                !note.is_archived()
+                    && !note.has_tag(TEMPLATE_TAG)
+                    && note
+                        .get_reminder()
+                        .is_some_and(|reminder| reminder.is_due(now))
+            })
+            .collect()
+    }
+
+    /// Returns the indexes of the notes other than templates with a
+    /// reminder, soonest due first.
+    pub fn get_reminders(&self) -> Vec<usize> {
+        let mut indexes: Vec<usize> = (0..self.notes.len())
+            .filter(|&index| {
+                let note = &self.notes[index];
+                note.get_reminder().is_some() && !note.has_tag(TEMPLATE_TAG)
+            })
+            .collect();
+        indexes.sort_by_key(|&index| self.notes[index].get_reminder().unwrap().get_due());
+        indexes
+    }
+
+    /// Reverts the last operation, returning `false` if there was none.
+    pub fn undo(&mut self) -> Result<bool, String> {
+        // The history only changes once the operation is reverted, so one
+        // that cannot be stays to be undone
+        match self.history.peek_undo() {
+            Some(command) => {
+                let inverse = command.inverse();
+                self.apply(&inverse)?;
+                self.history.undo();
+                self.changed(&inverse);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Applies the last undone operation again, returning `false` if there was none.
+    pub fn redo(&mut self) -> Result<bool, String> {
+        match self.history.peek_redo() {
+            Some(command) => {
+                let command = command.clone();
+                self.apply(&command)?;
+                self.history.redo();
+                self.changed(&command);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.set_depth(depth);
     }
 
     pub fn get_size(&self) -> usize {
@@ -73,8 +626,14 @@ impl NoteTaker {
     }
 }
 
+#[cfg(test)]
+use crate::autosave::Policy;
+#[cfg(test)]
+use crate::persistence::event_log_persistence::EventLogPersistence;
 #[cfg(test)]
 use crate::persistence::file_persistence::FilePersistence;
+#[cfg(test)]
+use crate::persistence::rusqlite_persistence::RusqlitePersistence;
 
 #[cfg(test)]
 mod tests {
@@ -209,4 +768,645 @@ mod tests {
         assert_eq!(note_taker.get_notes().len(), 1);
         assert_eq!(note_taker.get_note(0), note2);
     }
+
+    #[test]
+    fn test_pinned_notes_should_be_listed_first() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        let note2 = Note::new("Title 2".to_string(), "Content 2".to_string()).unwrap();
+
+        note_taker.add_note(note1.clone());
+        note_taker.add_note(note2.clone());
+        note_taker.set_pinned(1, true);
+
+        let notes = note_taker.get_notes();
+        assert_eq!(notes[0], note2);
+        assert!(notes[0].is_pinned());
+        assert_eq!(notes[1], note1);
+    }
+
+    #[test]
+    fn test_archived_notes_should_be_hidden_by_default() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        let note2 = Note::new("Title 2".to_string(), "Content 2".to_string()).unwrap();
+
+        note_taker.add_note(note1.clone());
+        note_taker.add_note(note2.clone());
+        note_taker.set_archived(0, true);
+
+        assert_eq!(note_taker.get_notes(), vec![note2.clone()]);
+        assert_eq!(note_taker.get_archived_notes(), vec![note1.clone()]);
+        assert_eq!(note_taker.get_all_notes(), vec![note1, note2]);
+        assert_eq!(note_taker.get_size(), 2);
+    }
+
+    #[test]
+    fn test_should_find_index_of_listed_note() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        let note2 = Note::new("Title 2".to_string(), "Content 2".to_string()).unwrap();
+
+        note_taker.add_note(note1.clone());
+        note_taker.add_note(note2.clone());
+        note_taker.set_pinned(1, true);
+
+        let listed = note_taker.get_notes();
+        assert_eq!(note_taker.index_of(&listed[0]), Some(1));
+        assert_eq!(note_taker.index_of(&listed[1]), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_should_panic_if_pinning_out_of_bounds_note() {
+        let mut note_taker = setup_note_taker();
+        note_taker.set_pinned(0, true); // This should panic
+    }
+
+    #[test]
+    fn test_should_undo_and_redo_add() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+
+        note_taker.add_note(note1.clone());
+
+        assert!(note_taker.undo().unwrap());
+        assert_eq!(note_taker.get_size(), 0);
+        assert!(note_taker.redo().unwrap());
+        assert_eq!(note_taker.get_notes(), vec![note1]);
+    }
+
+    #[test]
+    fn test_failed_undo_should_keep_history() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        note_taker.add_note(note1.clone());
+        // Take the note out behind the history's back, so undoing fails
+        note_taker.remove_note(0);
+
+        assert!(note_taker.undo().is_err());
+        assert!(note_taker.history.can_undo());
+        assert!(!note_taker.history.can_redo());
+
+        note_taker.insert_note(note1);
+        assert!(note_taker.undo().unwrap());
+        assert_eq!(note_taker.get_size(), 0);
+    }
+
+    #[test]
+    fn test_failed_batch_should_roll_back() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        let note2 = Note::new("Title 2".to_string(), "Content 2".to_string()).unwrap();
+        note_taker.add_note(note1.clone());
+
+        let batch = Command::Batch(vec![
+            Command::Insert(note2),
+            Command::Remove(note1.clone()),
+            Command::Insert(note1.clone()),
+            Command::Insert(note1.clone()),
+        ]);
+
+        assert!(note_taker.apply(&batch).is_err());
+        assert_eq!(note_taker.get_notes(), vec![note1]);
+        assert_eq!(note_taker.get_size(), 1);
+    }
+
+    #[test]
+    fn test_should_undo_delete() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        let note2 = Note::new("Title 2".to_string(), "Content 2".to_string()).unwrap();
+        note_taker.add_note(note1.clone());
+        note_taker.add_note(note2.clone());
+
+        note_taker.delete_note(0);
+        note_taker.undo().unwrap();
+
+        assert_eq!(note_taker.get_notes(), vec![note1, note2]);
+        assert_eq!(note_taker.get_size(), 2);
+    }
+
+    #[test]
+    fn test_should_edit_and_undo_edit() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        note_taker.add_note(note1.clone());
+
+        note_taker
+            .edit_note(0, "New Title".to_string(), "New Content".to_string())
+            .unwrap();
+        assert_eq!(note_taker.get_note(0).get_title(), "New Title");
+        assert_eq!(note_taker.get_note(0).get_content(), "New Content");
+
+        note_taker.undo().unwrap();
+        assert_eq!(note_taker.get_notes(), vec![note1]);
+    }
+
+    #[test]
+    fn test_edit_should_not_create_duplicate() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new_with_date_time(
+            "Title 1".to_string(),
+            "Content".to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+        let note2 = Note::new_with_date_time(
+            "Title 2".to_string(),
+            "Content".to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+        note_taker.add_note(note1);
+        note_taker.add_note(note2);
+
+        let result = note_taker.edit_note(1, "Title 1".to_string(), "Content".to_string());
+
+        assert_eq!(result, Err("Note already exists".to_string()));
+        assert_eq!(note_taker.get_size(), 2);
+    }
+
+    #[test]
+    fn test_should_undo_tag_and_pin() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        note_taker.add_note(note1);
+
+        note_taker.tag_note(0, "work").unwrap();
+        note_taker.set_pinned(0, true);
+        assert!(note_taker.get_note(0).is_pinned());
+        assert_eq!(note_taker.get_note(0).get_tags(), ["work"]);
+
+        note_taker.undo().unwrap();
+        assert!(!note_taker.get_note(0).is_pinned());
+        note_taker.undo().unwrap();
+        assert!(note_taker.get_note(0).get_tags().is_empty());
+        note_taker.redo().unwrap();
+        assert_eq!(note_taker.get_note(0).get_tags(), ["work"]);
+    }
+
+    #[test]
+    fn test_should_move_note_and_undo_move() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        let note2 = Note::new("Title 2".to_string(), "Content 2".to_string()).unwrap();
+        note_taker.add_note(note1.clone());
+        note_taker.add_note(note2.clone());
+
+        note_taker.move_note(1, "2000-01-01 00:00").unwrap();
+        assert_eq!(note_taker.get_note(0).get_title(), "Title 2");
+        assert_eq!(note_taker.get_note(0).get_date_time(), "2000-01-01 00:00");
+
+        note_taker.undo().unwrap();
+        assert_eq!(note_taker.get_notes(), vec![note1, note2]);
+    }
+
+    #[test]
+    fn test_new_operation_should_clear_redo() {
+        let mut note_taker = setup_note_taker();
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        let note2 = Note::new("Title 2".to_string(), "Content 2".to_string()).unwrap();
+
+        note_taker.add_note(note1);
+        note_taker.undo().unwrap();
+        note_taker.add_note(note2);
+
+        assert!(!note_taker.redo().unwrap());
+    }
+
+    #[test]
+    fn test_undo_should_be_bounded_by_history_depth() {
+        let mut note_taker = setup_note_taker();
+        note_taker.set_history_depth(1);
+        let note1 = Note::new("Title 1".to_string(), "Content 1".to_string()).unwrap();
+        let note2 = Note::new("Title 2".to_string(), "Content 2".to_string()).unwrap();
+
+        note_taker.add_note(note1.clone());
+        note_taker.add_note(note2);
+
+        assert!(note_taker.undo().unwrap());
+        assert!(!note_taker.undo().unwrap());
+        assert_eq!(note_taker.get_notes(), vec![note1]);
+    }
+
+    fn linked_note(title: &str, content: &str) -> Note {
+        Note::new_with_date_time(
+            title.to_string(),
+            content.to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_should_resolve_links_by_title_and_id() {
+        let mut note_taker = setup_note_taker();
+        let target = linked_note("Target", "");
+        note_taker.add_note(linked_note("Source", "[[target]]"));
+        note_taker.add_note(target.clone());
+
+        assert_eq!(note_taker.resolve_link("TARGET"), Some(1));
+        assert_eq!(note_taker.resolve_link(&target.get_id()), Some(1));
+        assert_eq!(note_taker.resolve_link("Missing"), None);
+        assert_eq!(
+            note_taker.get_links(0),
+            vec![("target".to_string(), Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_should_find_backlinks() {
+        let mut note_taker = setup_note_taker();
+        let target = linked_note("Target", "");
+        note_taker.add_note(linked_note("By title", "[[Target]]"));
+        note_taker.add_note(linked_note("By id", &format!("[[{}]]", target.get_id())));
+        note_taker.add_note(linked_note("Unrelated", "[[Other]]"));
+        note_taker.add_note(target);
+
+        let target_index = note_taker.resolve_link("Target").unwrap();
+        let backlinks: Vec<String> = note_taker
+            .get_backlinks(target_index)
+            .into_iter()
+            .map(|i| note_taker.get_note(i).get_title().to_string())
+            .collect();
+
+        assert_eq!(backlinks, vec!["By id", "By title"]);
+    }
+
+    #[test]
+    fn test_backlinks_should_follow_edits_and_deletes() {
+        let mut note_taker = setup_note_taker();
+        note_taker.add_note(linked_note("Source", "[[Target]]"));
+        note_taker.add_note(linked_note("Target", ""));
+
+        note_taker
+            .edit_note(0, "Source".to_string(), "No links".to_string())
+            .unwrap();
+        assert!(note_taker.get_backlinks(1).is_empty());
+
+        note_taker.undo().unwrap();
+        assert_eq!(note_taker.get_backlinks(1), vec![0]);
+
+        note_taker.delete_note(0);
+        assert!(note_taker.get_backlinks(0).is_empty());
+    }
+
+    #[test]
+    fn test_should_list_and_toggle_tasks() {
+        let mut note_taker = setup_note_taker();
+        let mut archived = Note::new("Old".to_string(), "- [ ] Forgotten".to_string()).unwrap();
+        archived.set_archived(true);
+        note_taker.add_note(archived);
+        note_taker.add_note(
+            Note::new(
+                "Actions".to_string(),
+                "- [ ] Call Bob\n- [x] Book room\n- [ ] Send notes".to_string(),
+            )
+            .unwrap(),
+        );
+        let index = note_taker.get_by_title("Actions")[0];
+
+        let open: Vec<String> = note_taker
+            .get_open_tasks()
+            .into_iter()
+            .map(|(i, task)| format!("{}:{}", note_taker.get_note(i).get_title(), task.text))
+            .collect();
+        assert_eq!(open, vec!["Actions:Call Bob", "Actions:Send notes"]);
+        assert_eq!(note_taker.get_task_progress(index), (1, 3));
+
+        note_taker.toggle_task(index, 0).unwrap();
+        let index = note_taker.get_by_title("Actions")[0];
+        assert_eq!(note_taker.get_task_progress(index), (2, 3));
+        assert_eq!(note_taker.get_open_tasks().len(), 1);
+        assert!(note_taker.toggle_task(index, 5).is_err());
+
+        note_taker.undo().unwrap();
+        let index = note_taker.get_by_title("Actions")[0];
+        assert_eq!(note_taker.get_task_progress(index), (1, 3));
+    }
+
+    #[test]
+    fn test_should_set_and_snooze_reminders() {
+        let at = |text: &str| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        let mut note_taker = setup_note_taker();
+        let mut archived = Note::new("Old".to_string(), String::new()).unwrap();
+        archived.set_archived(true);
+        archived.set_reminder(Some(Reminder::parse("2025-03-04 08:00").unwrap()));
+        note_taker.add_note(archived);
+        note_taker.add_note(Note::new("Call".to_string(), String::new()).unwrap());
+        let index = note_taker.get_by_title("Call")[0];
+
+        note_taker
+            .set_reminder(index, Some(Reminder::parse("2025-03-04 09:00").unwrap()))
+            .unwrap();
+        assert_eq!(
+            note_taker.get_due_reminders(at("2025-03-04 09:00")),
+            vec![index]
+        );
+        assert_eq!(note_taker.get_reminders().len(), 2);
+
+        note_taker
+            .snooze_reminder(index, at("2025-03-04 09:15"))
+            .unwrap();
+        assert!(
+            note_taker
+                .get_due_reminders(at("2025-03-04 09:00"))
+                .is_empty()
+        );
+        assert_eq!(
+            note_taker.get_due_reminders(at("2025-03-04 09:15")),
+            vec![index]
+        );
+
+        note_taker.set_reminder(index, None).unwrap();
+        note_taker
+            .snooze_reminder(index, at("2025-03-04 10:00"))
+            .unwrap();
+        assert_eq!(
+            note_taker
+                .get_note(index)
+                .get_reminder()
+                .unwrap()
+                .to_string(),
+            "2025-03-04 10:00"
+        );
+
+        note_taker.undo().unwrap();
+        note_taker.undo().unwrap();
+        assert_eq!(
+            note_taker
+                .get_note(index)
+                .get_reminder()
+                .unwrap()
+                .to_string(),
+            "2025-03-04 09:00 snoozed 2025-03-04 09:15"
+        );
+    }
+
+    #[test]
+    fn test_should_report_broken_links() {
+        let mut note_taker = setup_note_taker();
+        note_taker.add_note(linked_note("Source", "[[Target]] and [[Missing]]"));
+        note_taker.add_note(linked_note("Target", ""));
+
+        assert_eq!(
+            note_taker.get_broken_links(),
+            vec![(0, "Missing".to_string())]
+        );
+
+        note_taker.delete_note(1);
+        assert_eq!(note_taker.get_broken_links().len(), 2);
+    }
+
+    #[test]
+    fn test_renaming_should_rewrite_links() {
+        let mut note_taker = setup_note_taker();
+        let target = linked_note("Target", "Links to [[Target]] itself");
+        note_taker.add_note(linked_note(
+            "Source",
+            &format!("[[target|alias]] and [[{}]]", target.get_id()),
+        ));
+        note_taker.add_note(target);
+
+        note_taker
+            .edit_note(
+                1,
+                "Renamed".to_string(),
+                "Links to [[Target]] itself".to_string(),
+            )
+            .unwrap();
+
+        let renamed = note_taker.resolve_link("Renamed").unwrap();
+        let source = note_taker.resolve_link("Source").unwrap();
+        assert_eq!(
+            note_taker.get_note(source).get_content(),
+            "[[Renamed|alias]] and [[Renamed]]"
+        );
+        assert_eq!(
+            note_taker.get_note(renamed).get_content(),
+            "Links to [[Renamed]] itself"
+        );
+        assert!(note_taker.get_broken_links().is_empty());
+
+        note_taker.undo().unwrap();
+        let source = note_taker.resolve_link("Source").unwrap();
+        assert!(
+            note_taker
+                .get_note(source)
+                .get_content()
+                .starts_with("[[target|alias]]")
+        );
+        assert!(note_taker.resolve_link("Target").is_some());
+    }
+
+    #[test]
+    fn test_renaming_should_keep_links_to_a_shared_title() {
+        let mut note_taker = setup_note_taker();
+        note_taker.add_note(linked_note("Source", "[[Target]]"));
+        note_taker.add_note(linked_note("Target", "First"));
+        note_taker.add_note(linked_note("Target", "Second"));
+
+        note_taker
+            .edit_note(1, "Renamed".to_string(), "First".to_string())
+            .unwrap();
+
+        let source = note_taker.resolve_link("Source").unwrap();
+        assert_eq!(note_taker.get_note(source).get_content(), "[[Target]]");
+    }
+
+    #[test]
+    fn test_locked_note_should_leave_link_index() {
+        let mut note_taker = setup_note_taker();
+        note_taker.add_note(linked_note("Target", ""));
+        note_taker.add_note(linked_note("Secret", "See [[Target]]"));
+        let target = note_taker.get_by_title("Target")[0];
+        let secret = note_taker.get_by_title("Secret")[0];
+
+        note_taker.lock_note(secret, "password").unwrap();
+        assert!(note_taker.get_backlinks(target).is_empty());
+        assert_eq!(
+            note_taker.edit_note(secret, "Secret".to_string(), "Changed".to_string()),
+            Err("Note is locked".to_string())
+        );
+        assert_eq!(
+            note_taker.unlock_note(secret, "wrong"),
+            Err("Wrong password".to_string())
+        );
+
+        note_taker.unlock_note(secret, "password").unwrap();
+        assert_eq!(note_taker.get_note(secret).get_content(), "See [[Target]]");
+        assert_eq!(note_taker.get_backlinks(target), vec![secret]);
+
+        note_taker.undo().unwrap();
+        assert!(note_taker.get_note(secret).is_locked());
+    }
+
+    #[test]
+    fn test_should_replace_all_notes() {
+        let mut note_taker = setup_note_taker();
+        note_taker.add_note(linked_note("Old", "See [[Target]]"));
+
+        note_taker.replace_notes(vec![linked_note("Target", ""), linked_note("New", "")]);
+
+        assert_eq!(note_taker.get_size(), 2);
+        assert!(note_taker.get_by_title("Old").is_empty());
+        assert!(
+            note_taker
+                .get_backlinks(note_taker.get_by_title("Target")[0])
+                .is_empty()
+        );
+        assert_eq!(note_taker.undo(), Ok(false));
+    }
+
+    fn autosaving_note_taker(database: &str, journal: &str, policy: Policy) -> NoteTaker {
+        let persistence = Box::new(RusqlitePersistence::new(database).unwrap());
+        let mut note_taker = NoteTaker::new(persistence);
+        note_taker.load().unwrap();
+        note_taker
+            .enable_autosave(Autosave::new(journal, policy))
+            .unwrap();
+        note_taker
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("note_taker_autosave_{}", name));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_should_autosave_on_change() {
+        let (database, journal) = (temp_path("on_change.db"), temp_path("on_change.jsonl"));
+        let mut note_taker = autosaving_note_taker(&database, &journal, Policy::OnChange);
+
+        note_taker.add_note(Note::new("Title".to_string(), "Content".to_string()).unwrap());
+
+        assert!(!note_taker.has_unsaved_changes());
+        assert_eq!(note_taker.take_autosave_error(), None);
+        let stored = RusqlitePersistence::new(&database).unwrap().load().unwrap();
+        assert_eq!(stored.len(), 1);
+        assert!(!std::path::Path::new(&journal).exists());
+
+        std::fs::remove_file(database).unwrap();
+    }
+
+    #[test]
+    fn test_should_restore_stored_revision() {
+        let log = temp_path("revisions.jsonl");
+        let _ = std::fs::remove_file(format!("{}.snapshot.json", log));
+        let mut note_taker = NoteTaker::new(Box::new(EventLogPersistence::new(&log)));
+        let first = Note::new("First".to_string(), "Content".to_string()).unwrap();
+        note_taker.add_note(first.clone());
+        note_taker.save().unwrap();
+        note_taker.add_note(Note::new("Second".to_string(), "Content".to_string()).unwrap());
+
+        assert_eq!(note_taker.get_revisions().unwrap().len(), 1);
+        note_taker.restore_revision("1").unwrap();
+
+        assert_eq!(note_taker.get_notes(), vec![first]);
+        // The unsaved note was saved before restoring, so it can be restored too
+        assert_eq!(note_taker.get_revisions().unwrap().len(), 3);
+        assert!(
+            NoteTaker::new(Box::new(FilePersistence::new(temp_path("none.json"))))
+                .get_revisions()
+                .is_err()
+        );
+
+        std::fs::remove_file(log).unwrap();
+    }
+
+    #[test]
+    fn test_should_write_calendar_feed_on_save() {
+        let (database, feed) = (temp_path("feed.db"), temp_path("feed.ics"));
+        let mut note_taker = NoteTaker::new(Box::new(RusqlitePersistence::new(&database).unwrap()));
+        note_taker.set_calendar_feed(Some(PathBuf::from(&feed)));
+        note_taker
+            .add_note(Note::new("Report".to_string(), "due:2025-03-07 17:00".to_string()).unwrap());
+
+        note_taker.save().unwrap();
+        let ics = std::fs::read_to_string(&feed).unwrap();
+        assert!(ics.contains("BEGIN:VTODO\r\n"));
+        assert!(ics.contains("DUE:20250307T170000\r\n"));
+
+        note_taker.delete_note(0);
+        note_taker.save().unwrap();
+        assert!(!std::fs::read_to_string(&feed).unwrap().contains("VTODO"));
+        assert_eq!(note_taker.take_calendar_feed_error(), None);
+
+        note_taker.set_calendar_feed(Some(PathBuf::from(&feed).join("missing").join("feed.ics")));
+        note_taker.save().unwrap();
+        assert!(note_taker.take_calendar_feed_error().is_some());
+        std::fs::remove_file(database).unwrap();
+        std::fs::remove_file(feed).unwrap();
+    }
+
+    #[test]
+    fn test_should_autosave_replaced_notes() {
+        let (database, journal) = (temp_path("replace.db"), temp_path("replace.jsonl"));
+        let mut note_taker = autosaving_note_taker(&database, &journal, Policy::OnChange);
+        note_taker.add_note(Note::new("Old".to_string(), String::new()).unwrap());
+
+        note_taker.replace_notes(vec![
+            Note::new("Restored".to_string(), String::new()).unwrap(),
+        ]);
+
+        assert!(!note_taker.has_unsaved_changes());
+        let stored = RusqlitePersistence::new(&database).unwrap().load().unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].get_title(), "Restored");
+        std::fs::remove_file(database).unwrap();
+    }
+
+    #[test]
+    fn test_shared_notes_should_follow_changes() {
+        let mut note_taker = setup_note_taker();
+        note_taker.add_note(Note::new("First".to_string(), String::new()).unwrap());
+        let shared = note_taker.share_notes();
+        assert_eq!(shared.lock().unwrap().len(), 1);
+
+        note_taker.add_note(Note::new("Second".to_string(), String::new()).unwrap());
+        note_taker.delete_note(0);
+        let notes = shared.lock().unwrap().clone();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].get_title(), "Second");
+
+        note_taker.undo().unwrap();
+        assert_eq!(shared.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_should_replay_unsaved_changes_from_journal() {
+        let (database, journal) = (temp_path("replay.db"), temp_path("replay.jsonl"));
+        let hour = Policy::Every(std::time::Duration::from_secs(3600));
+        let mut note_taker = autosaving_note_taker(&database, &journal, hour);
+        note_taker.add_note(Note::new("Kept".to_string(), "".to_string()).unwrap());
+        note_taker.add_note(Note::new("Deleted".to_string(), "".to_string()).unwrap());
+        note_taker.delete_note(note_taker.get_by_title("Deleted")[0]);
+        note_taker.tag_note(0, "work").unwrap();
+        assert!(note_taker.has_unsaved_changes());
+        // The session ends without saving, as if the process was killed
+        drop(note_taker);
+
+        let recovered = autosaving_note_taker(&database, &journal, Policy::OnChange);
+
+        assert_eq!(recovered.get_size(), 1);
+        assert_eq!(recovered.get_note(0).get_title(), "Kept");
+        assert_eq!(recovered.get_note(0).get_tags(), ["work"]);
+        assert!(recovered.has_unsaved_changes());
+        assert_eq!(recovered.autosave_if_due(), Ok(true));
+        assert_eq!(
+            RusqlitePersistence::new(&database).unwrap().load().unwrap(),
+            recovered.get_all_notes()
+        );
+        let persistence = Box::new(RusqlitePersistence::new(&database).unwrap());
+        let mut reopened = NoteTaker::new(persistence);
+        reopened.load().unwrap();
+        assert_eq!(
+            reopened.enable_autosave(Autosave::new(&journal, Policy::OnChange)),
+            Ok(0)
+        );
+
+        std::fs::remove_file(database).unwrap();
+    }
 }
@@ -0,0 +1,249 @@
+use super::persistence_trait::{PersistenceTrait, Revision};
+use crate::crypto::{self, Key};
+use crate::note::Note;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// Marks the content of an encrypted note
+const ENVELOPE_PREFIX: &str = "note_taker:encrypted:v1:";
+const ENCRYPTED_TITLE: &str = "Encrypted note";
+const ENCRYPTED_DATE_TIME: &str = "1970-01-01 00:00";
+
+/// Encrypts notes before handing them to another backend, so that backend
+/// only ever stores ciphertext.
+///
+/// Each note is stored as a note with a placeholder title and date time whose
+/// content holds the whole note encrypted with XChaCha20-Poly1305, under a key
+/// derived from the passphrase with Argon2. Notes the backend already holds in
+/// plaintext are loaded as they are and encrypted on the next save.
+pub struct EncryptedPersistence {
+    inner: Box<dyn PersistenceTrait>,
+    passphrase: RefCell<String>,
+    salt: RefCell<Vec<u8>>,
+    keys: RefCell<HashMap<Vec<u8>, Key>>,
+    // Notes as last loaded or saved, by their plaintext, so unchanged notes
+    // are not encrypted again with a new nonce on every save
+    sealed: RefCell<HashMap<String, Note>>,
+}
+
+impl EncryptedPersistence {
+    pub fn new(inner: Box<dyn PersistenceTrait>, passphrase: &str) -> Self {
+        EncryptedPersistence {
+            inner,
+            passphrase: RefCell::new(passphrase.to_string()),
+            salt: RefCell::new(crypto::generate_salt()),
+            keys: RefCell::new(HashMap::new()),
+            sealed: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn get_key(&self, salt: &[u8]) -> Result<Key, String> {
+        if let Some(key) = self.keys.borrow().get(salt) {
+            return Ok(*key);
+        }
+        let key = crypto::derive_key(&self.passphrase.borrow(), salt)?;
+        self.keys.borrow_mut().insert(salt.to_vec(), key);
+        Ok(key)
+    }
+
+    fn encrypt(&self, note: &Note) -> Result<Note, String> {
+        let plaintext = note.to_json_value().to_string();
+        if let Some(sealed) = self.sealed.borrow().get(&plaintext) {
+            return Ok(sealed.clone());
+        }
+
+        let salt = self.salt.borrow().clone();
+        let envelope = crypto::seal(&self.get_key(&salt)?, &salt, &plaintext, ENVELOPE_PREFIX)?;
+        let sealed = Note::new_with_date_time(
+            ENCRYPTED_TITLE.to_string(),
+            envelope,
+            ENCRYPTED_DATE_TIME.to_string(),
+        )?;
+        self.sealed.borrow_mut().insert(plaintext, sealed.clone());
+        Ok(sealed)
+    }
+
+    fn decrypt(&self, note: &Note) -> Result<Note, String> {
+        let Some(envelope) = crypto::parse(note.get_content(), ENVELOPE_PREFIX) else {
+            return Ok(note.clone());
+        };
+        let envelope = envelope?;
+        let plaintext = crypto::open(&self.get_key(&envelope.salt)?, &envelope, ENVELOPE_PREFIX)
+            .ok_or("Wrong passphrase, or the encrypted notes are corrupted")?;
+        let json_value: serde_json::Value =
+            serde_json::from_str(&plaintext).map_err(|e| e.to_string())?;
+        let decrypted = Note::from_json_value(&json_value)?;
+
+        // Later notes are encrypted with the salt the store already uses
+        *self.salt.borrow_mut() = envelope.salt;
+        self.sealed.borrow_mut().insert(plaintext, note.clone());
+        Ok(decrypted)
+    }
+
+    /// Re-encrypts every note under a new passphrase and a new salt. The old
+    /// passphrase must be the current one.
+    pub fn rotate_passphrase(&self, new_passphrase: &str) -> Result<(), String> {
+        let notes = self.load()?;
+        *self.passphrase.borrow_mut() = new_passphrase.to_string();
+        *self.salt.borrow_mut() = crypto::generate_salt();
+        self.keys.borrow_mut().clear();
+        self.sealed.borrow_mut().clear();
+        self.save(&notes)
+    }
+}
+
+impl PersistenceTrait for EncryptedPersistence {
+    fn save(&self, notes: &[Note]) -> Result<(), String> {
+        let sealed = notes
+            .iter()
+            .map(|note| self.encrypt(note))
+            .collect::<Result<Vec<Note>, String>>()?;
+        self.inner.save(&sealed)
+    }
+
+    fn load(&self) -> Result<Vec<Note>, String> {
+        self.sealed.borrow_mut().clear();
+        self.inner
+            .load()?
+            .iter()
+            .map(|note| self.decrypt(note))
+            .collect()
+    }
+
+    fn get_history(&self) -> Result<Vec<Revision>, String> {
+        self.inner.get_history()
+    }
+
+    fn restore(&self, revision: &str) -> Result<Vec<Note>, String> {
+        self.sealed.borrow_mut().clear();
+        self.inner
+            .restore(revision)?
+            .iter()
+            .map(|note| self.decrypt(note))
+            .collect()
+    }
+
+    fn compact(&self) -> Result<(), String> {
+        self.inner.compact()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::file_persistence::FilePersistence;
+
+    fn file_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("note_taker_encrypted_{}.json", name));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    fn encrypted(path: &str, passphrase: &str) -> EncryptedPersistence {
+        EncryptedPersistence::new(Box::new(FilePersistence::new(path.to_string())), passphrase)
+    }
+
+    fn note(title: &str, content: &str) -> Note {
+        Note::new_with_date_time(
+            title.to_string(),
+            content.to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_should_store_only_ciphertext() {
+        let path = file_path("ciphertext");
+        let mut secret = note("Bank", "PIN 1234");
+        secret.add_tag("private").unwrap();
+
+        encrypted(&path, "correct horse")
+            .save(&[secret.clone()])
+            .unwrap();
+
+        let stored = std::fs::read_to_string(&path).unwrap();
+        assert!(!stored.contains("Bank"));
+        assert!(!stored.contains("1234"));
+        assert!(!stored.contains("private"));
+        assert!(stored.contains(ENVELOPE_PREFIX));
+
+        let loaded = encrypted(&path, "correct horse").load().unwrap();
+        assert_eq!(loaded, vec![secret]);
+        assert_eq!(loaded[0].get_tags(), ["private"]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_should_reject_wrong_passphrase() {
+        let path = file_path("wrong_passphrase");
+        encrypted(&path, "correct horse")
+            .save(&[note("Bank", "PIN 1234")])
+            .unwrap();
+
+        assert_eq!(
+            encrypted(&path, "battery staple").load(),
+            Err("Wrong passphrase, or the encrypted notes are corrupted".to_string())
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_should_not_reencrypt_unchanged_notes() {
+        let path = file_path("unchanged");
+        let persistence = encrypted(&path, "correct horse");
+        persistence.save(&[note("First", "")]).unwrap();
+        let first_save = std::fs::read_to_string(&path).unwrap();
+
+        let persistence = encrypted(&path, "correct horse");
+        let notes = persistence.load().unwrap();
+        persistence.save(&notes).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), first_save);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_should_rotate_passphrase() {
+        let path = file_path("rotate");
+        let persistence = encrypted(&path, "old passphrase");
+        persistence
+            .save(&[note("First", "One"), note("Second", "Two")])
+            .unwrap();
+
+        persistence.rotate_passphrase("new passphrase").unwrap();
+
+        assert!(encrypted(&path, "old passphrase").load().is_err());
+        assert_eq!(
+            encrypted(&path, "new passphrase").load().unwrap(),
+            vec![note("First", "One"), note("Second", "Two")]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_should_encrypt_plaintext_notes_on_next_save() {
+        let path = file_path("plaintext");
+        FilePersistence::new(path.clone())
+            .save(&[note("Old", "Plaintext")])
+            .unwrap();
+
+        let persistence = encrypted(&path, "correct horse");
+        let notes = persistence.load().unwrap();
+        assert_eq!(notes, vec![note("Old", "Plaintext")]);
+        persistence.save(&notes).unwrap();
+
+        assert!(
+            !std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("Plaintext")
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,599 @@
+use super::persistence_trait::{PersistenceTrait, Revision};
+use crate::note::Note;
+
+use chrono::Local;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 100;
+
+/// A change to the notes, as recorded in the log.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Event {
+    NoteCreated(Note),
+    NoteUpdated { previous: Note, note: Note },
+    NoteDeleted(Note),
+}
+
+impl Event {
+    fn to_json_value(&self, sequence: u64) -> serde_json::Value {
+        let mut json_value = serde_json::json!({
+            "sequence": sequence,
+            "recorded_at": Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        });
+        match self {
+            Event::NoteCreated(note) => {
+                json_value["event"] = "NoteCreated".into();
+                json_value["note"] = note.to_json_value();
+            }
+            Event::NoteUpdated { previous, note } => {
+                json_value["event"] = "NoteUpdated".into();
+                json_value["previous"] = previous.to_json_value();
+                json_value["note"] = note.to_json_value();
+            }
+            Event::NoteDeleted(note) => {
+                json_value["event"] = "NoteDeleted".into();
+                json_value["note"] = note.to_json_value();
+            }
+        }
+        json_value
+    }
+
+    fn from_json_value(json_value: &serde_json::Value) -> Result<(u64, String, Event), String> {
+        let sequence = json_value["sequence"]
+            .as_u64()
+            .ok_or("Missing sequence in event")?;
+        let recorded_at = json_value["recorded_at"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let note = Note::from_json_value(&json_value["note"])?;
+        let event = match json_value["event"].as_str() {
+            Some("NoteCreated") => Event::NoteCreated(note),
+            Some("NoteUpdated") => Event::NoteUpdated {
+                previous: Note::from_json_value(&json_value["previous"])?,
+                note,
+            },
+            Some("NoteDeleted") => Event::NoteDeleted(note),
+            _ => return Err(format!("Unknown event in event {}", sequence)),
+        };
+        Ok((sequence, recorded_at, event))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Event::NoteCreated(note) => format!("Create note \"{}\"", note.get_title()),
+            Event::NoteUpdated { note, .. } => format!("Update note \"{}\"", note.get_title()),
+            Event::NoteDeleted(note) => format!("Delete note \"{}\"", note.get_title()),
+        }
+    }
+
+    fn apply(&self, notes: &mut BTreeSet<Note>) {
+        match self {
+            Event::NoteCreated(note) => {
+                notes.replace(note.clone());
+            }
+            Event::NoteUpdated { previous, note } => {
+                notes.remove(previous);
+                notes.replace(note.clone());
+            }
+            Event::NoteDeleted(note) => {
+                notes.remove(note);
+            }
+        }
+    }
+}
+
+struct LogState {
+    notes: BTreeSet<Note>,
+    sequence: u64,
+    snapshot_sequence: u64,
+}
+
+/// Appends every change as an event to a JSON Lines file instead of
+/// rewriting all notes on each save, and rebuilds the notes on load by
+/// replaying the events.
+///
+/// The notes are also written to a snapshot file every
+/// `DEFAULT_SNAPSHOT_INTERVAL` events, so loading only replays the events
+/// recorded after it. `compact` writes a snapshot and drops the events it covers.
+pub struct EventLogPersistence {
+    log_path: PathBuf,
+    snapshot_path: PathBuf,
+    snapshot_interval: u64,
+    state: RefCell<Option<LogState>>,
+}
+
+impl EventLogPersistence {
+    pub fn new(log_path: &str) -> Self {
+        EventLogPersistence {
+            log_path: PathBuf::from(log_path),
+            snapshot_path: PathBuf::from(format!("{}.snapshot.json", log_path)),
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            state: RefCell::new(None),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_snapshot_interval(mut self, snapshot_interval: u64) -> Self {
+        self.snapshot_interval = snapshot_interval.max(1);
+        self
+    }
+
+    /// Works out the events turning `previous` into `notes`. A note whose title
+    /// or content changed is a different note, so a note removed and a note added
+    /// at the same date time with the same title or content count as an update.
+    fn diff(previous: &BTreeSet<Note>, notes: &[Note]) -> Vec<Event> {
+        let current: BTreeSet<&Note> = notes.iter().collect();
+        let mut events = Vec::new();
+        let mut created = Vec::new();
+        let mut deleted: Vec<&Note> = previous.iter().filter(|n| !current.contains(n)).collect();
+
+        for note in notes {
+            match previous.get(note) {
+                Some(old) if old.to_json_value() != note.to_json_value() => {
+                    events.push(Event::NoteUpdated {
+                        previous: old.clone(),
+                        note: note.clone(),
+                    });
+                }
+                Some(_) => {}
+                None => created.push(note),
+            }
+        }
+
+        for note in created {
+            let edited = deleted.iter().position(|old| {
+                old.get_date_time() == note.get_date_time()
+                    && (old.get_title() == note.get_title()
+                        || old.get_content() == note.get_content())
+            });
+            match edited {
+                Some(i) => events.push(Event::NoteUpdated {
+                    previous: deleted.remove(i).clone(),
+                    note: note.clone(),
+                }),
+                None => events.push(Event::NoteCreated(note.clone())),
+            }
+        }
+        for note in deleted {
+            events.push(Event::NoteDeleted(note.clone()));
+        }
+        events
+    }
+
+    fn read_snapshot(&self) -> Result<(BTreeSet<Note>, u64), String> {
+        let Ok(content) = fs::read_to_string(&self.snapshot_path) else {
+            return Ok((BTreeSet::new(), 0));
+        };
+        let json_value: serde_json::Value =
+            serde_json::from_str(&content).map_err(|e| format!("Invalid snapshot: {}", e))?;
+        let sequence = json_value["sequence"]
+            .as_u64()
+            .ok_or("Missing sequence in snapshot")?;
+        let notes = json_value["notes"]
+            .as_array()
+            .ok_or("Missing notes in snapshot")?
+            .iter()
+            .map(Note::from_json_value)
+            .collect::<Result<BTreeSet<Note>, String>>()?;
+        Ok((notes, sequence))
+    }
+
+    fn read_events(&self) -> Result<Vec<(u64, Event)>, String> {
+        Ok(self
+            .read_records()?
+            .into_iter()
+            .map(|(sequence, _, event)| (sequence, event))
+            .collect())
+    }
+
+    /// Reads the logged events in order with the time each was recorded. A
+    /// last line that cannot be read was cut short by a crash while writing
+    /// it, and is ignored.
+    fn read_records(&self) -> Result<Vec<(u64, String, Event)>, String> {
+        let file = match File::open(&self.log_path) {
+            Ok(file) => file,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let lines: Vec<String> = BufReader::new(file)
+            .lines()
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        let lines: Vec<&String> = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        let mut events = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let parsed = serde_json::from_str::<serde_json::Value>(line)
+                .map_err(|e| e.to_string())
+                .and_then(|json_value| Event::from_json_value(&json_value));
+            match parsed {
+                Ok(event) => events.push(event),
+                Err(_) if i == lines.len() - 1 => break,
+                Err(e) => return Err(format!("Invalid event on line {}: {}", i + 1, e)),
+            }
+        }
+        Ok(events)
+    }
+
+    fn read_state(&self) -> Result<LogState, String> {
+        let (mut notes, snapshot_sequence) = self.read_snapshot()?;
+        let mut sequence = snapshot_sequence;
+        for (event_sequence, event) in self.read_events()? {
+            if event_sequence > snapshot_sequence {
+                event.apply(&mut notes);
+                sequence = event_sequence;
+            }
+        }
+        Ok(LogState {
+            notes,
+            sequence,
+            snapshot_sequence,
+        })
+    }
+
+    // Cuts off a line torn by a crash, so events appended next start on a
+    // line of their own
+    fn truncate_torn_line(&self) -> Result<(), String> {
+        let content = match fs::read(&self.log_path) {
+            Ok(content) => content,
+            Err(_) => return Ok(()),
+        };
+        if content.is_empty() || content.ends_with(b"\n") {
+            return Ok(());
+        }
+        let length = content
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |i| i + 1);
+        OpenOptions::new()
+            .write(true)
+            .open(&self.log_path)
+            .and_then(|file| file.set_len(length as u64))
+            .map_err(|e| e.to_string())
+    }
+
+    // Writes to a temporary file first, so a crash never leaves a partial file
+    fn write_atomically(path: &Path, content: &str) -> Result<(), String> {
+        let temporary_path = path.with_extension("tmp");
+        let mut file = File::create(&temporary_path).map_err(|e| e.to_string())?;
+        file.write_all(content.as_bytes())
+            .map_err(|e| e.to_string())?;
+        file.sync_all().map_err(|e| e.to_string())?;
+        fs::rename(&temporary_path, path).map_err(|e| e.to_string())
+    }
+
+    fn write_snapshot(&self, state: &mut LogState) -> Result<(), String> {
+        let notes: Vec<serde_json::Value> = state.notes.iter().map(Note::to_json_value).collect();
+        let snapshot = serde_json::json!({
+            "sequence": state.sequence,
+            "notes": notes,
+        });
+        Self::write_atomically(&self.snapshot_path, &snapshot.to_string())?;
+        state.snapshot_sequence = state.sequence;
+        Ok(())
+    }
+
+    fn with_state<T>(
+        &self,
+        f: impl FnOnce(&mut LogState) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut state = self.state.borrow_mut();
+        if state.is_none() {
+            *state = Some(self.read_state()?);
+        }
+        f(state.as_mut().unwrap())
+    }
+
+    /// Returns the logged events with their sequence numbers, oldest first.
+    #[allow(dead_code)]
+    pub fn get_events(&self) -> Result<Vec<(u64, Event)>, String> {
+        self.read_events()
+    }
+}
+
+impl PersistenceTrait for EventLogPersistence {
+    fn save(&self, notes: &[Note]) -> Result<(), String> {
+        self.with_state(|state| {
+            let events = Self::diff(&state.notes, notes);
+            if events.is_empty() {
+                return Ok(());
+            }
+
+            let mut lines = String::new();
+            for (i, event) in events.iter().enumerate() {
+                lines.push_str(
+                    &event
+                        .to_json_value(state.sequence + 1 + i as u64)
+                        .to_string(),
+                );
+                lines.push('\n');
+            }
+            self.truncate_torn_line()?;
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.log_path)
+                .map_err(|e| e.to_string())?;
+            file.write_all(lines.as_bytes())
+                .map_err(|e| e.to_string())?;
+            file.sync_data().map_err(|e| e.to_string())?;
+
+            for event in &events {
+                event.apply(&mut state.notes);
+            }
+            state.sequence += events.len() as u64;
+
+            if state.sequence - state.snapshot_sequence >= self.snapshot_interval {
+                self.write_snapshot(state)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn load(&self) -> Result<Vec<Note>, String> {
+        let state = self.read_state()?;
+        let notes = state.notes.iter().cloned().collect();
+        *self.state.borrow_mut() = Some(state);
+        Ok(notes)
+    }
+
+    /// Lists the logged events, most recent first, each revision being the
+    /// notes right after its event.
+    fn get_history(&self) -> Result<Vec<Revision>, String> {
+        Ok(self
+            .read_records()?
+            .into_iter()
+            .rev()
+            .map(|(sequence, recorded_at, event)| Revision {
+                id: sequence.to_string(),
+                date_time: recorded_at,
+                message: event.describe(),
+            })
+            .collect())
+    }
+
+    /// Replays the events up to a revision and saves the notes that gives,
+    /// logging the changes back to them as new events. Events dropped by
+    /// `compact` or covered by a snapshot can no longer be restored.
+    fn restore(&self, revision: &str) -> Result<Vec<Note>, String> {
+        let sequence: u64 = revision
+            .trim()
+            .parse()
+            .map_err(|_| format!("Unknown revision: {}", revision))?;
+        let (mut notes, snapshot_sequence) = self.read_snapshot()?;
+        if sequence < snapshot_sequence {
+            return Err(format!("Revision {} is older than the snapshot", sequence));
+        }
+        let events = self.read_events()?;
+        if sequence > snapshot_sequence && !events.iter().any(|(s, _)| *s == sequence) {
+            return Err(format!("Unknown revision: {}", revision));
+        }
+        for (_, event) in events
+            .iter()
+            .filter(|(s, _)| *s > snapshot_sequence && *s <= sequence)
+        {
+            event.apply(&mut notes);
+        }
+        let notes: Vec<Note> = notes.into_iter().collect();
+        self.save(&notes)?;
+        Ok(notes)
+    }
+
+    /// Writes a snapshot of the notes and drops the events it makes redundant.
+    fn compact(&self) -> Result<(), String> {
+        self.with_state(|state| {
+            self.write_snapshot(state)?;
+            Self::write_atomically(&self.log_path, "")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("note_taker_events_{}.jsonl", name));
+        let path = path.to_str().unwrap().to_string();
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(format!("{}.snapshot.json", path));
+        path
+    }
+
+    fn clean_up(path: &str) {
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(format!("{}.snapshot.json", path));
+    }
+
+    // Versions of a note share its UID, as they do when it is edited
+    fn note(title: &str, content: &str) -> Note {
+        let mut note = Note::new_with_date_time(
+            title.to_string(),
+            content.to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+        note.set_uid(&note.get_id());
+        note
+    }
+
+    fn events(persistence: &EventLogPersistence) -> Vec<Event> {
+        persistence
+            .get_events()
+            .unwrap()
+            .into_iter()
+            .map(|(_, event)| event)
+            .collect()
+    }
+
+    #[test]
+    fn test_save_should_append_events() {
+        let path = log_path("append");
+        let persistence = EventLogPersistence::new(&path);
+        let mut pinned = note("First", "Edited");
+        pinned.set_pinned(true);
+
+        persistence.save(&[note("First", "Original")]).unwrap();
+        persistence.save(&[note("First", "Original")]).unwrap();
+        persistence
+            .save(&[note("First", "Edited"), note("Second", "")])
+            .unwrap();
+        persistence.save(&[pinned.clone()]).unwrap();
+
+        assert_eq!(
+            events(&persistence),
+            vec![
+                Event::NoteCreated(note("First", "Original")),
+                Event::NoteUpdated {
+                    previous: note("First", "Original"),
+                    note: note("First", "Edited")
+                },
+                Event::NoteCreated(note("Second", "")),
+                Event::NoteUpdated {
+                    previous: note("First", "Edited"),
+                    note: pinned
+                },
+                Event::NoteDeleted(note("Second", "")),
+            ]
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 5);
+
+        clean_up(&path);
+    }
+
+    #[test]
+    fn test_load_should_replay_events() {
+        let path = log_path("replay");
+        let persistence = EventLogPersistence::new(&path);
+        let mut tagged = note("Renamed", "Content");
+        tagged.add_tag("work").unwrap();
+        persistence
+            .save(&[note("Title", "Content"), note("Other", "")])
+            .unwrap();
+        persistence.save(&[tagged.clone()]).unwrap();
+
+        let loaded = EventLogPersistence::new(&path).load().unwrap();
+
+        assert_eq!(loaded, vec![tagged]);
+        assert_eq!(loaded[0].get_tags(), ["work"]);
+
+        clean_up(&path);
+    }
+
+    #[test]
+    fn test_should_snapshot_periodically() {
+        let path = log_path("snapshot");
+        let persistence = EventLogPersistence::new(&path).with_snapshot_interval(2);
+
+        persistence.save(&[note("First", "")]).unwrap();
+        assert!(!Path::new(&format!("{}.snapshot.json", path)).exists());
+        persistence
+            .save(&[note("First", ""), note("Second", "")])
+            .unwrap();
+        assert!(Path::new(&format!("{}.snapshot.json", path)).exists());
+        persistence.save(&[note("Second", "")]).unwrap();
+
+        let loaded = EventLogPersistence::new(&path).load().unwrap();
+        assert_eq!(loaded, vec![note("Second", "")]);
+        assert_eq!(persistence.get_events().unwrap().len(), 3);
+
+        clean_up(&path);
+    }
+
+    #[test]
+    fn test_compact_should_drop_covered_events() {
+        let path = log_path("compact");
+        let persistence = EventLogPersistence::new(&path);
+        persistence.save(&[note("First", "")]).unwrap();
+        persistence.save(&[note("Second", "Other")]).unwrap();
+
+        persistence.compact().unwrap();
+        assert!(persistence.get_events().unwrap().is_empty());
+
+        persistence
+            .save(&[note("Second", "Other"), note("Third", "")])
+            .unwrap();
+        let loaded = EventLogPersistence::new(&path).load().unwrap();
+        assert_eq!(loaded, vec![note("Second", "Other"), note("Third", "")]);
+        assert_eq!(persistence.get_events().unwrap()[0].0, 4);
+
+        clean_up(&path);
+    }
+
+    #[test]
+    fn test_should_list_and_restore_revisions() {
+        let path = log_path("restore");
+        let persistence = EventLogPersistence::new(&path);
+        persistence.save(&[note("First", "")]).unwrap();
+        persistence
+            .save(&[note("First", ""), note("Second", "")])
+            .unwrap();
+        persistence.save(&[note("Second", "")]).unwrap();
+
+        let history = persistence.get_history().unwrap();
+        let messages: Vec<&str> = history.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Delete note \"First\"",
+                "Create note \"Second\"",
+                "Create note \"First\""
+            ]
+        );
+        assert_eq!(history[2].id, "1");
+
+        assert_eq!(persistence.restore("1").unwrap(), vec![note("First", "")]);
+        let loaded = EventLogPersistence::new(&path).load().unwrap();
+        assert_eq!(loaded, vec![note("First", "")]);
+        assert_eq!(persistence.get_events().unwrap().len(), 4);
+        assert!(persistence.restore("9").is_err());
+
+        persistence.compact().unwrap();
+        assert!(persistence.restore("1").is_err());
+
+        clean_up(&path);
+    }
+
+    #[test]
+    fn test_should_ignore_torn_last_line() {
+        let path = log_path("torn");
+        let persistence = EventLogPersistence::new(&path);
+        persistence.save(&[note("First", "")]).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"sequence\":2,\"eve").unwrap();
+
+        let persistence = EventLogPersistence::new(&path);
+        let loaded = persistence.load().unwrap();
+        assert_eq!(loaded, vec![note("First", "")]);
+
+        persistence
+            .save(&[note("First", ""), note("Second", "")])
+            .unwrap();
+        let loaded = EventLogPersistence::new(&path).load().unwrap();
+        assert_eq!(loaded, vec![note("First", ""), note("Second", "")]);
+
+        clean_up(&path);
+    }
+
+    #[test]
+    fn test_should_reject_corrupt_event_before_last_line() {
+        let path = log_path("corrupt");
+        fs::write(&path, "not json\n{}\n").unwrap();
+
+        let result = EventLogPersistence::new(&path).load();
+        assert_eq!(
+            result,
+            Err("Invalid event on line 1: expected ident at line 1 column 2".to_string())
+        );
+
+        clean_up(&path);
+    }
+}
@@ -1,40 +1,28 @@
 use super::persistence_trait::PersistenceTrait;
 use crate::note::Note;
 
-use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
 
 pub struct FilePersistence {
     file_path: String,
 }
 
 impl FilePersistence {
-    #[allow(dead_code)]
     pub fn new(file_path: String) -> Self {
         FilePersistence { file_path }
     }
 
+    // Writes the notes as a JSON array with one note per line
     fn convert_note_to_string(notes: &[Note]) -> String {
-        let mut result = String::new();
-        for note in notes {
-            result.push_str(&note.get_as_json());
-            result.push_str(",\n");
-        }
-        if !result.is_empty() {
-            result.pop(); // Remove the last comma
-            result.pop(); // Remove the last newline
-        }
+        let lines: Vec<String> = notes
+            .iter()
+            .map(|note| note.to_json_value().to_string())
+            .collect();
+        let mut result = lines.join(",\n");
         result.push('\n');
         result
     }
-
-    fn convert_string_to_note(json_string: &str) -> Note {
-        let json_value: serde_json::Value = serde_json::from_str(json_string).unwrap();
-        let title = json_value["title"].as_str().unwrap().to_string();
-        let content = json_value["content"].as_str().unwrap().to_string();
-        let date_time = json_value["date_time"].as_str().unwrap().to_string();
-        Note::new_with_date_time(title, content, date_time).unwrap()
-    }
 }
 
 impl PersistenceTrait for FilePersistence {
@@ -54,32 +42,25 @@ impl PersistenceTrait for FilePersistence {
     }
 
     fn load(&self) -> Result<Vec<Note>, String> {
-        let file = match File::open(&self.file_path) {
-            Ok(file) => file,
+        let content = match fs::read_to_string(&self.file_path) {
+            Ok(content) => content,
             Err(_) => {
                 // If the file doesn't exist or any error occurs, return an empty vector
                 return Ok(Vec::new());
             }
         };
-
-        let reader = BufReader::new(file);
-
-        let mut notes = Vec::new();
-
-        for line in reader.lines() {
-            let mut line = line.map_err(|e| e.to_string())?;
-            if !line.trim().is_empty() {
-                if line.starts_with('[') || line.starts_with(']') {
-                    continue; // Skip the opening and closing brackets
-                }
-                if line.ends_with(',') {
-                    line = line.trim_end_matches(',').to_string();
-                }
-                let note = FilePersistence::convert_string_to_note(&line);
-                notes.push(note);
-            }
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
         }
-        Ok(notes)
+
+        let json_value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid notes file {}: {}", self.file_path, e))?;
+        json_value
+            .as_array()
+            .ok_or(format!("Invalid notes file {}: not a list", self.file_path))?
+            .iter()
+            .map(Note::from_json_value)
+            .collect()
     }
 }
 
@@ -90,6 +71,61 @@ use std::fs::remove_file;
 mod test {
     use super::*;
 
+    #[test]
+    fn test_file_persistence_keeps_states_and_tags() {
+        let file_path = "test_notes_states.json".to_string();
+        let persistence = FilePersistence { file_path };
+        let mut archived = Note::new("Archived".to_string(), "Content".to_string()).unwrap();
+        archived.set_archived(true);
+        let mut pinned = Note::new("Pinned".to_string(), "Content".to_string()).unwrap();
+        pinned.set_pinned(true);
+        pinned.add_tag("work").unwrap();
+
+        persistence.save(&[archived, pinned]).unwrap();
+        let loaded_notes = persistence.load().unwrap();
+
+        assert!(loaded_notes[0].get_tags().is_empty());
+        assert_eq!(loaded_notes[1].get_tags(), ["work"]);
+        assert!(loaded_notes[0].is_archived());
+        assert!(!loaded_notes[0].is_pinned());
+        assert!(!loaded_notes[1].is_archived());
+        assert!(loaded_notes[1].is_pinned());
+
+        remove_file("test_notes_states.json").unwrap(); // Clean up the test file
+    }
+
+    #[test]
+    fn test_file_persistence_keeps_quotes_and_newlines() {
+        let file_path = "test_notes_escaped.json".to_string();
+        let persistence = FilePersistence { file_path };
+        let note = Note::new(
+            "Say \"hi\"".to_string(),
+            "Line 1\nLine \\ \"2\"".to_string(),
+        )
+        .unwrap();
+
+        persistence.save(std::slice::from_ref(&note)).unwrap();
+        let loaded_notes = persistence.load().unwrap();
+
+        assert_eq!(loaded_notes, vec![note]);
+        std::fs::write("test_notes_escaped.json", "[\n{\"title\":\n").unwrap();
+        assert!(persistence.load().is_err());
+        remove_file("test_notes_escaped.json").unwrap();
+    }
+
+    #[test]
+    fn test_should_load_notes_without_states() {
+        let json_value = serde_json::from_str(
+            r#"{"title":"Title" ,"content":"Content", "date_time":"2025-01-01 10:00"}"#,
+        )
+        .unwrap();
+        let note = Note::from_json_value(&json_value).unwrap();
+
+        assert!(!note.is_archived());
+        assert!(!note.is_pinned());
+        assert!(note.get_tags().is_empty());
+    }
+
     #[test]
     fn test_file_persistence() {
         let file_path = "test_notes.json".to_string();
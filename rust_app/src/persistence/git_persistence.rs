@@ -0,0 +1,367 @@
+use super::persistence_trait::{PersistenceTrait, Revision};
+use super::vault_persistence::VaultPersistence;
+use crate::note::Note;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const REMOTE_NAME: &str = "origin";
+
+/// Stores notes like `VaultPersistence` in the working tree of a git
+/// repository and commits every save, so the history of the notes can be
+/// listed and restored, and synced through an ordinary git remote.
+pub struct GitPersistence {
+    repository: PathBuf,
+    vault: VaultPersistence,
+    has_remote: bool,
+}
+
+impl GitPersistence {
+    pub fn new(repository: &str) -> Result<Self, String> {
+        let vault = VaultPersistence::new(repository)?;
+        let repository = PathBuf::from(repository);
+
+        if !repository.join(".git").exists() {
+            Self::run_git(&repository, &["init", "--quiet"])?;
+        }
+        // Commits need an identity, which may not be configured on this machine
+        if Self::run_git(&repository, &["config", "user.name"]).is_err() {
+            Self::run_git(&repository, &["config", "user.name", "Note Taker"])?;
+            Self::run_git(
+                &repository,
+                &["config", "user.email", "note_taker@localhost"],
+            )?;
+        }
+        let has_remote = Self::run_git(&repository, &["remote", "get-url", REMOTE_NAME]).is_ok();
+
+        Ok(GitPersistence {
+            repository,
+            vault,
+            has_remote,
+        })
+    }
+
+    /// Syncs with a remote repository: it is pulled from on load and pushed to on save.
+    pub fn with_remote(mut self, url: &str) -> Result<Self, String> {
+        if self.has_remote {
+            self.git(&["remote", "set-url", REMOTE_NAME, url])?;
+        } else {
+            self.git(&["remote", "add", REMOTE_NAME, url])?;
+        }
+        self.has_remote = true;
+        Ok(self)
+    }
+
+    fn run_git(repository: &Path, args: &[&str]) -> Result<String, String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repository)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, String> {
+        Self::run_git(&self.repository, args)
+    }
+
+    fn has_commits(&self) -> bool {
+        self.git(&["rev-parse", "--verify", "--quiet", "HEAD"])
+            .is_ok()
+    }
+
+    fn branch(&self) -> Result<String, String> {
+        Ok(self
+            .git(&["symbolic-ref", "--short", "HEAD"])?
+            .trim()
+            .to_string())
+    }
+
+    fn remote_has_branch(&self, branch: &str) -> Result<bool, String> {
+        Ok(!self
+            .git(&["ls-remote", "--heads", REMOTE_NAME, branch])?
+            .trim()
+            .is_empty())
+    }
+
+    fn title_of(path: &str) -> String {
+        Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string())
+    }
+
+    fn is_archived_path(path: &str) -> bool {
+        path.starts_with("archive/")
+    }
+
+    /// Describes the staged changes, e.g. `Add note "Groceries"` for a single
+    /// change or a summary with one line per note for several.
+    fn describe_changes(name_status: &str) -> String {
+        let mut changes = Vec::new();
+        let (mut added, mut updated, mut deleted) = (0, 0, 0);
+        for line in name_status.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let change = match fields.as_slice() {
+                ["A", path] => {
+                    added += 1;
+                    format!("Add note \"{}\"", Self::title_of(path))
+                }
+                ["D", path] => {
+                    deleted += 1;
+                    format!("Delete note \"{}\"", Self::title_of(path))
+                }
+                [status, from, to] if status.starts_with('R') => {
+                    updated += 1;
+                    let (from_title, to_title) = (Self::title_of(from), Self::title_of(to));
+                    if from_title != to_title {
+                        format!("Rename note \"{}\" to \"{}\"", from_title, to_title)
+                    } else if Self::is_archived_path(to) {
+                        format!("Archive note \"{}\"", to_title)
+                    } else {
+                        format!("Unarchive note \"{}\"", to_title)
+                    }
+                }
+                [_, path, ..] => {
+                    updated += 1;
+                    format!("Update note \"{}\"", Self::title_of(path))
+                }
+                _ => continue,
+            };
+            changes.push(change);
+        }
+
+        if changes.len() == 1 {
+            return changes.remove(0);
+        }
+        let mut summary = Vec::new();
+        for (count, action) in [(added, "added"), (updated, "updated"), (deleted, "deleted")] {
+            if count > 0 {
+                summary.push(format!("{} {}", count, action));
+            }
+        }
+        format!(
+            "Update notes: {}\n\n{}",
+            summary.join(", "),
+            changes.join("\n")
+        )
+    }
+
+    /// Commits everything in the working tree, returning `false` if nothing changed.
+    fn commit(&self, message: Option<&str>) -> Result<bool, String> {
+        self.git(&["add", "--all"])?;
+        let name_status = self.git(&["diff", "--cached", "--name-status", "-M"])?;
+        if name_status.trim().is_empty() {
+            return Ok(false);
+        }
+        let message = match message {
+            Some(message) => message.to_string(),
+            None => Self::describe_changes(&name_status),
+        };
+        self.git(&["commit", "--quiet", "-m", &message])?;
+        if self.has_remote {
+            self.git(&["push", "--quiet", REMOTE_NAME, &self.branch()?])?;
+        }
+        Ok(true)
+    }
+}
+
+impl PersistenceTrait for GitPersistence {
+    fn save(&self, notes: &[Note]) -> Result<(), String> {
+        self.vault.save(notes)?;
+        self.commit(None)?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<Note>, String> {
+        if self.has_remote {
+            let branch = self.branch()?;
+            if self.remote_has_branch(&branch)? {
+                if self.has_commits() {
+                    self.git(&["pull", "--quiet", "--ff-only", REMOTE_NAME, &branch])?;
+                } else {
+                    self.git(&["fetch", "--quiet", REMOTE_NAME, &branch])?;
+                    self.git(&["reset", "--quiet", "--hard", "FETCH_HEAD"])?;
+                }
+            }
+        }
+        self.vault.load()
+    }
+
+    /// Lists the commits of the notes repository, most recent first.
+    fn get_history(&self) -> Result<Vec<Revision>, String> {
+        if !self.has_commits() {
+            return Ok(Vec::new());
+        }
+        let log = self.git(&[
+            "log",
+            "--format=%h%x09%ad%x09%s",
+            "--date=format:%Y-%m-%d %H:%M",
+        ])?;
+        Ok(log
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                Some(Revision {
+                    id: fields.next()?.to_string(),
+                    date_time: fields.next()?.to_string(),
+                    message: fields.next()?.to_string(),
+                })
+            })
+            .collect())
+    }
+
+    /// Brings the notes back to how they were at a past revision, recording
+    /// that as a new commit, and returns them.
+    fn restore(&self, revision: &str) -> Result<Vec<Note>, String> {
+        let commit = format!("{}^{{commit}}", revision);
+        self.git(&["rev-parse", "--verify", "--quiet", &commit])
+            .map_err(|_| format!("Unknown revision: {}", revision))?;
+        self.git(&["read-tree", "-u", "--reset", revision])?;
+        self.commit(Some(&format!("Restore notes from {}", revision)))?;
+        self.vault.load()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("note_taker_git_{}", name));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    // Versions of a note share its UID, as they do when it is edited
+    fn note(title: &str, content: &str) -> Note {
+        let mut note = Note::new_with_date_time(
+            title.to_string(),
+            content.to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap();
+        note.set_uid(&note.get_id());
+        note
+    }
+
+    fn messages(persistence: &GitPersistence) -> Vec<String> {
+        persistence
+            .get_history()
+            .unwrap()
+            .into_iter()
+            .map(|revision| revision.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_save_should_commit_described_changes() {
+        let directory = temp_directory("commits");
+        let persistence = GitPersistence::new(directory.to_str().unwrap()).unwrap();
+        let mut archived = note("Second", "");
+        archived.set_archived(true);
+
+        persistence.save(&[note("First", "")]).unwrap();
+        persistence.save(&[note("First", "")]).unwrap();
+        persistence
+            .save(&[note("First", "Edited"), note("Second", "")])
+            .unwrap();
+        persistence
+            .save(&[note("First", "Edited"), archived])
+            .unwrap();
+        persistence.save(&[]).unwrap();
+
+        assert_eq!(
+            messages(&persistence),
+            vec![
+                "Update notes: 2 deleted",
+                "Archive note \"Second\"",
+                "Update notes: 1 added, 1 updated",
+                "Add note \"First\"",
+            ]
+        );
+        assert!(persistence.load().unwrap().is_empty());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_describe_changes() {
+        assert_eq!(
+            GitPersistence::describe_changes("R090\tOld.md\tNew.md\n"),
+            "Rename note \"Old\" to \"New\""
+        );
+        assert_eq!(
+            GitPersistence::describe_changes("R100\tarchive/Note.md\tNote.md\n"),
+            "Unarchive note \"Note\""
+        );
+        assert_eq!(
+            GitPersistence::describe_changes("A\tNew.md\nD\tOld.md\n"),
+            "Update notes: 1 added, 1 deleted\n\nAdd note \"New\"\nDelete note \"Old\""
+        );
+    }
+
+    #[test]
+    fn test_should_restore_past_state() {
+        let directory = temp_directory("restore");
+        let persistence = GitPersistence::new(directory.to_str().unwrap()).unwrap();
+        persistence.save(&[note("First", "Original")]).unwrap();
+        let revision = persistence.get_history().unwrap()[0].id.clone();
+        persistence
+            .save(&[note("First", "Edited"), note("Second", "")])
+            .unwrap();
+
+        let restored = persistence.restore(&revision).unwrap();
+
+        assert_eq!(restored, vec![note("First", "Original")]);
+        assert_eq!(persistence.load().unwrap(), restored);
+        assert_eq!(
+            messages(&persistence)[0],
+            format!("Restore notes from {}", revision)
+        );
+        assert!(persistence.restore("not-a-revision").is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_sync_through_bare_remote() {
+        let remote = temp_directory("remote.git");
+        GitPersistence::run_git(
+            &std::env::temp_dir(),
+            &["init", "--quiet", "--bare", remote.to_str().unwrap()],
+        )
+        .unwrap();
+        let first_directory = temp_directory("first_clone");
+        let second_directory = temp_directory("second_clone");
+
+        let first = GitPersistence::new(first_directory.to_str().unwrap())
+            .unwrap()
+            .with_remote(remote.to_str().unwrap())
+            .unwrap();
+        first.load().unwrap();
+        first.save(&[note("Shared", "From first")]).unwrap();
+
+        let second = GitPersistence::new(second_directory.to_str().unwrap())
+            .unwrap()
+            .with_remote(remote.to_str().unwrap())
+            .unwrap();
+        let loaded = second.load().unwrap();
+        assert_eq!(loaded, vec![note("Shared", "From first")]);
+
+        second.save(&[note("Shared", "From second")]).unwrap();
+        assert_eq!(first.load().unwrap(), vec![note("Shared", "From second")]);
+
+        for directory in [remote, first_directory, second_directory] {
+            std::fs::remove_dir_all(directory).unwrap();
+        }
+    }
+}
@@ -1,4 +1,8 @@
+pub mod encrypted_persistence;
+pub mod event_log_persistence;
 pub mod file_persistence;
+pub mod git_persistence;
 pub mod persistence_trait;
 pub mod postgresql_persistence;
 pub mod rusqlite_persistence;
+pub mod vault_persistence;
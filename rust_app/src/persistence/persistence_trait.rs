@@ -1,7 +1,38 @@
 use crate::note::Note;
 
-#[allow(dead_code)]
+/// A past state of the notes, as kept by backends with a history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revision {
+    pub id: String,
+    pub date_time: String,
+    pub message: String,
+}
+
 pub trait PersistenceTrait {
     fn save(&self, notes: &[Note]) -> Result<(), String>;
     fn load(&self) -> Result<Vec<Note>, String>;
+
+    /// Lists the past states of the notes, most recent first.
+    fn get_history(&self) -> Result<Vec<Revision>, String> {
+        Err("This storage backend keeps no history".to_string())
+    }
+
+    /// Brings the notes back to a revision from `get_history`, keeping the
+    /// current state in the history, and returns them.
+    fn restore(&self, _revision: &str) -> Result<Vec<Note>, String> {
+        Err("This storage backend keeps no history".to_string())
+    }
+
+    /// Drops what the backend keeps beyond the current notes, if anything.
+    fn compact(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Saves notes through a backend opened apart from the session that changed
+/// them, such as one opened by a signal handler. It is loaded first, so it
+/// knows the files and notes it already stores and only writes the changes.
+pub fn load_and_save(persistence: &dyn PersistenceTrait, notes: &[Note]) -> Result<(), String> {
+    persistence.load()?;
+    persistence.save(notes)
 }
@@ -1,5 +1,6 @@
 use super::persistence_trait::PersistenceTrait;
 use crate::note::Note;
+use crate::reminders::Reminder;
 
 use dotenv::dotenv;
 use std::env;
@@ -20,6 +21,12 @@ impl PostgresqlPersistence {
         Ok(PostgresqlPersistence { connection_string })
     }
 
+    pub fn with_connection_string(connection_string: &str) -> Self {
+        PostgresqlPersistence {
+            connection_string: connection_string.to_string(),
+        }
+    }
+
     async fn connect(&self) -> Result<Client, Error> {
         let (client, connection) = tokio_postgres::connect(&self.connection_string, NoTls).await?;
         tokio::spawn(async move {
@@ -30,32 +37,79 @@ impl PostgresqlPersistence {
         Ok(client)
     }
 
+    // Databases created before notes could be archived, pinned, tagged or have
+    // reminders and UIDs lack these columns
+    async fn add_missing_columns(client: &Client) -> Result<(), Error> {
+        client
+            .batch_execute(
+                "ALTER TABLE notes ADD COLUMN IF NOT EXISTS archived BOOLEAN NOT NULL DEFAULT FALSE;
+                 ALTER TABLE notes ADD COLUMN IF NOT EXISTS pinned BOOLEAN NOT NULL DEFAULT FALSE;
+                 ALTER TABLE notes ADD COLUMN IF NOT EXISTS tags TEXT NOT NULL DEFAULT '';
+                 ALTER TABLE notes ADD COLUMN IF NOT EXISTS reminder TEXT NOT NULL DEFAULT '';
+                 ALTER TABLE notes ADD COLUMN IF NOT EXISTS uid TEXT NOT NULL DEFAULT '';",
+            )
+            .await
+    }
+
     async fn save_notes(&self, notes: &[Note]) -> Result<(), Error> {
-        let client = self.connect().await?;
+        let mut client = self.connect().await?;
+        Self::add_missing_columns(&client).await?;
+
+        // The table mirrors the saved notes, so states and deletions are kept
+        let transaction = client.transaction().await?;
+        transaction.execute("DELETE FROM notes", &[]).await?;
         for note in notes {
-            client
+            transaction
                 .execute(
-                    "INSERT INTO notes (title, content, date_time) VALUES ($1, $2, $3)",
+                    "INSERT INTO notes (title, content, date_time, archived, pinned, tags, reminder, uid) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
                     &[
                         &note.get_title(),
                         &note.get_content(),
                         &note.get_date_time(),
+                        &note.is_archived(),
+                        &note.is_pinned(),
+                        &note.get_tags().join(","),
+                        &note
+                            .get_reminder()
+                            .map(|reminder| reminder.to_string())
+                            .unwrap_or_default(),
+                        &note.get_uid(),
                     ],
                 )
                 .await?;
         }
-        Ok(())
+        transaction.commit().await
     }
 
-    async fn load_notes(&self) -> Result<Vec<Note>, Error> {
-        let client = self.connect().await?;
+    async fn load_notes(&self) -> Result<Vec<Note>, String> {
+        let client = self.connect().await.map_err(|e| e.to_string())?;
+        Self::add_missing_columns(&client)
+            .await
+            .map_err(|e| e.to_string())?;
         let rows = client
-            .query("SELECT title, content, date_time FROM notes", &[])
-            .await?;
+            .query(
+                "SELECT title, content, date_time, archived, pinned, tags, reminder, uid FROM notes",
+                &[],
+            )
+            .await
+            .map_err(|e| e.to_string())?;
 
+        // Rows that do not make a valid note are reported, not loaded
         let mut notes = Vec::new();
         for row in rows {
-            let note = Note::new_with_date_time(row.get(0), row.get(1), row.get(2)).unwrap();
+            let mut note = Note::new_with_date_time(row.get(0), row.get(1), row.get(2))?;
+            note.set_archived(row.get(3));
+            note.set_pinned(row.get(4));
+            let tags: String = row.get(5);
+            for tag in tags.split(',').filter(|tag| !tag.is_empty()) {
+                note.add_tag(tag)?;
+            }
+            let reminder: String = row.get(6);
+            if !reminder.is_empty() {
+                note.set_reminder(Some(Reminder::parse(&reminder)?));
+            }
+            let uid: String = row.get(7);
+            note.set_uid(&uid);
             notes.push(note);
         }
         Ok(notes)
@@ -72,8 +126,6 @@ impl PersistenceTrait for PostgresqlPersistence {
 
     fn load(&self) -> Result<Vec<Note>, String> {
         let runtime = Runtime::new().unwrap();
-        runtime
-            .block_on(self.load_notes())
-            .map_err(|e| e.to_string())
+        runtime.block_on(self.load_notes())
     }
 }
@@ -1,12 +1,12 @@
 use super::persistence_trait::PersistenceTrait;
 use crate::note::Note;
+use crate::reminders::Reminder;
 use rusqlite::{Connection, Result, params};
 
 pub struct RusqlitePersistence {
     connection: Connection,
 }
 
-#[allow(dead_code)]
 impl RusqlitePersistence {
     pub fn new(db_name: &str) -> Result<Self> {
         let connection = Connection::open(db_name)?;
@@ -16,46 +16,113 @@ impl RusqlitePersistence {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 title TEXT NOT NULL,
                 content TEXT NOT NULL,
-                date_time TEXT NOT NULL
+                date_time TEXT NOT NULL,
+                archived INTEGER NOT NULL DEFAULT 0,
+                pinned INTEGER NOT NULL DEFAULT 0,
+                tags TEXT NOT NULL DEFAULT '',
+                reminder TEXT NOT NULL DEFAULT '',
+                uid TEXT NOT NULL DEFAULT ''
             )",
             params![],
         )?;
 
+        // Databases created before notes could be archived, pinned, tagged or
+        // have reminders and UIDs lack these columns
+        for (column, definition) in [
+            ("archived", "INTEGER NOT NULL DEFAULT 0"),
+            ("pinned", "INTEGER NOT NULL DEFAULT 0"),
+            ("tags", "TEXT NOT NULL DEFAULT ''"),
+            ("reminder", "TEXT NOT NULL DEFAULT ''"),
+            ("uid", "TEXT NOT NULL DEFAULT ''"),
+        ] {
+            if !Self::has_column(&connection, column)? {
+                connection.execute(
+                    &format!("ALTER TABLE notes ADD COLUMN {column} {definition}"),
+                    params![],
+                )?;
+            }
+        }
+
         Ok(RusqlitePersistence { connection })
     }
+
+    fn has_column(connection: &Connection, column: &str) -> Result<bool> {
+        let mut stmt = connection.prepare("SELECT name FROM pragma_table_info('notes')")?;
+        let mut names = stmt.query_map(params![], |row| row.get::<_, String>(0))?;
+        names.try_fold(false, |found, name| Ok(found || name? == column))
+    }
 }
 
 impl PersistenceTrait for RusqlitePersistence {
     fn save(&self, notes: &[Note]) -> Result<(), String> {
-        let mut stmt = self
+        // The table mirrors the saved notes, so states and deletions are kept.
+        // It all happens in one transaction, so a failed save leaves the
+        // notes as they were
+        let transaction = self
             .connection
-            .prepare("INSERT INTO notes (title, content, date_time) VALUES (?,?,?)")
-            .unwrap();
-        for note in notes {
-            stmt.execute(params![
-                note.get_title(),
-                note.get_content(),
-                note.get_date_time()
-            ])
-            .unwrap();
+            .unchecked_transaction()
+            .map_err(|e| e.to_string())?;
+        transaction
+            .execute("DELETE FROM notes", params![])
+            .map_err(|e| e.to_string())?;
+        {
+            let mut stmt = transaction
+                .prepare(
+                    "INSERT INTO notes (title, content, date_time, archived, pinned, tags, reminder, uid) VALUES (?,?,?,?,?,?,?,?)",
+                )
+                .map_err(|e| e.to_string())?;
+            for note in notes {
+                stmt.execute(params![
+                    note.get_title(),
+                    note.get_content(),
+                    note.get_date_time(),
+                    note.is_archived(),
+                    note.is_pinned(),
+                    note.get_tags().join(","),
+                    note.get_reminder()
+                        .map(|reminder| reminder.to_string())
+                        .unwrap_or_default(),
+                    note.get_uid()
+                ])
+                .map_err(|e| e.to_string())?;
+            }
         }
-        Ok(())
+        transaction.commit().map_err(|e| e.to_string())
     }
 
     fn load(&self) -> Result<Vec<Note>, String> {
         let mut stmt = self
             .connection
-            .prepare("SELECT title, content, date_time FROM notes")
-            .unwrap();
+            .prepare(
+                "SELECT title, content, date_time, archived, pinned, tags, reminder, uid FROM notes",
+            )
+            .map_err(|e| e.to_string())?;
+        // Rows that do not make a valid note are reported, not loaded
         let note_iter = stmt
             .query_map(params![], |row| {
-                Ok(Note::new_with_date_time(row.get(0)?, row.get(1)?, row.get(2)?).unwrap())
+                let note = Note::new_with_date_time(row.get(0)?, row.get(1)?, row.get(2)?);
+                let tags: String = row.get(5)?;
+                let reminder: String = row.get(6)?;
+                let uid: String = row.get(7)?;
+                let (archived, pinned) = (row.get(3)?, row.get(4)?);
+                Ok(note.and_then(|mut note| {
+                    note.set_archived(archived);
+                    note.set_pinned(pinned);
+                    for tag in tags.split(',').filter(|tag| !tag.is_empty()) {
+                        note.add_tag(tag)?;
+                    }
+                    if !reminder.is_empty() {
+                        note.set_reminder(Some(Reminder::parse(&reminder)?));
+                    }
+                    note.set_uid(&uid);
+                    Ok(note)
+                }))
             })
-            .unwrap();
+            .map_err(|e| e.to_string())?;
 
         let mut notes = Vec::new();
         for note in note_iter {
-            notes.push(note.unwrap());
+            notes.push(note.map_err(|e| e.to_string())??);
         }
         Ok(notes)
     }
@@ -87,4 +154,80 @@ mod tests {
         persistence.connection.close().unwrap();
         std::fs::remove_file("test.db").unwrap();
     }
+
+    #[test]
+    fn test_rusqlite_persistence_keeps_states_and_deletions() {
+        let persistence = RusqlitePersistence::new("test_states.db").unwrap();
+        let mut note1 =
+            Note::new("Test Title 1".to_string(), "Test Content 1".to_string()).unwrap();
+        let note2 = Note::new("Test Title 2".to_string(), "Test Content 2".to_string()).unwrap();
+        note1.set_archived(true);
+        note1.set_pinned(true);
+        note1.add_tag("work").unwrap();
+        note1.add_tag("urgent").unwrap();
+        note1.set_reminder(Some(Reminder::parse("2025-03-04 09:00 weekly").unwrap()));
+
+        persistence.save(&[note1.clone(), note2.clone()]).unwrap();
+        persistence.save(&[note1.clone()]).unwrap();
+        let loaded_notes = persistence.load().unwrap();
+
+        assert_eq!(loaded_notes.len(), 1);
+        assert_eq!(loaded_notes[0].get_tags(), ["urgent", "work"]);
+        assert!(loaded_notes[0].is_archived());
+        assert!(loaded_notes[0].is_pinned());
+        assert_eq!(loaded_notes[0].get_reminder(), note1.get_reminder());
+        assert_eq!(loaded_notes[0].get_uid(), note1.get_uid());
+        assert_eq!(
+            persistence.load().unwrap()[0].get_reminder(),
+            note1.get_reminder()
+        );
+
+        persistence.connection.close().unwrap();
+        std::fs::remove_file("test_states.db").unwrap();
+    }
+
+    #[test]
+    fn test_failed_save_should_keep_stored_notes() {
+        let persistence = RusqlitePersistence::new("test_failed_save.db").unwrap();
+        let note1 = Note::new("Test Title 1".to_string(), "Test Content 1".to_string()).unwrap();
+        let rejected = Note::new("Rejected".to_string(), "Test Content".to_string()).unwrap();
+        persistence.save(std::slice::from_ref(&note1)).unwrap();
+        persistence
+            .connection
+            .execute(
+                "CREATE TRIGGER reject BEFORE INSERT ON notes WHEN NEW.title = 'Rejected'
+                 BEGIN SELECT RAISE(ABORT, 'rejected'); END",
+                params![],
+            )
+            .unwrap();
+
+        assert!(persistence.save(&[rejected]).is_err());
+        assert_eq!(persistence.load().unwrap(), vec![note1]);
+
+        persistence.connection.close().unwrap();
+        std::fs::remove_file("test_failed_save.db").unwrap();
+    }
+
+    #[test]
+    fn test_load_should_report_invalid_rows() {
+        let persistence = RusqlitePersistence::new("test_invalid_rows.db").unwrap();
+        for (tags, reminder) in [("work", "tomorrow"), ("two words", "")] {
+            persistence
+                .connection
+                .execute("DELETE FROM notes", params![])
+                .unwrap();
+            persistence
+                .connection
+                .execute(
+                    "INSERT INTO notes (title, content, date_time, tags, reminder) VALUES ('Title', '', '2025-03-04 09:00', ?, ?)",
+                    params![tags, reminder],
+                )
+                .unwrap();
+
+            assert!(persistence.load().is_err());
+        }
+
+        persistence.connection.close().unwrap();
+        std::fs::remove_file("test_invalid_rows.db").unwrap();
+    }
 }
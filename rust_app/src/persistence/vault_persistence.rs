@@ -0,0 +1,474 @@
+use super::persistence_trait::PersistenceTrait;
+use crate::front_matter;
+use crate::note::Note;
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+const ARCHIVE_DIRECTORY: &str = "archive";
+const MAX_FILE_NAME_LENGTH: usize = 100;
+// File names are limited to 255 bytes on most file systems, which leaves room
+// for a ` (n).md` suffix and a `_` prefix
+const MAX_FILE_NAME_BYTES: usize = 200;
+
+/// Stores each note as a Markdown file with YAML front matter in a directory,
+/// archived notes in an `archive` subdirectory, so notes can be edited in any
+/// text editor and versioned with git.
+///
+/// The backend remembers the content of every file it last read or wrote, so
+/// changes made by other programs between loads are never overwritten: a file
+/// edited externally is left alone, and a note changed here in the meantime
+/// is written next to it as a separate file. Files are only deleted when they
+/// still hold what this backend last saw in them.
+pub struct VaultPersistence {
+    directory: PathBuf,
+    known_files: RefCell<HashMap<PathBuf, KnownFile>>,
+}
+
+// What a file held when last read or written, and how its note is rendered
+#[derive(Clone, Copy)]
+struct KnownFile {
+    content_hash: u64,
+    note_hash: u64,
+}
+
+impl VaultPersistence {
+    pub fn new(directory: &str) -> Result<Self, String> {
+        let directory = PathBuf::from(directory);
+        fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+        Ok(VaultPersistence {
+            directory,
+            known_files: RefCell::new(HashMap::new()),
+        })
+    }
+
+    fn hash(content: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn read_hash(path: &Path) -> Option<u64> {
+        fs::read_to_string(path)
+            .ok()
+            .map(|content| Self::hash(&content))
+    }
+
+    /// Turns a title into a file name stem that is valid on common file systems.
+    pub fn file_stem(title: &str) -> String {
+        let stem: String = title
+            .chars()
+            .map(|c| {
+                if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                    '-'
+                } else {
+                    c
+                }
+            })
+            .take(MAX_FILE_NAME_LENGTH)
+            .scan(0, |bytes, c| {
+                *bytes += c.len_utf8();
+                (*bytes <= MAX_FILE_NAME_BYTES).then_some(c)
+            })
+            .collect();
+        let stem = stem.trim().trim_matches('.').trim().to_string();
+
+        if stem.is_empty() {
+            return "untitled".to_string();
+        }
+        // Device names cannot be used as file names on Windows
+        let device = stem.split('.').next().unwrap_or("").to_uppercase();
+        if ["CON", "PRN", "AUX", "NUL"].contains(&device.as_str())
+            || ((device.starts_with("COM") || device.starts_with("LPT"))
+                && device.len() == 4
+                && device.ends_with(|c: char| c.is_ascii_digit()))
+        {
+            return format!("_{}", stem);
+        }
+        stem
+    }
+
+    /// Collects the Markdown files under a directory, skipping hidden entries
+    /// such as `.git` or the `.obsidian` settings folder.
+    pub fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+        for entry in fs::read_dir(directory).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                Self::collect_files(&path, files)?;
+            } else if path.extension().is_some_and(|extension| extension == "md") {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn modified_date_time(path: &Path) -> NaiveDateTime {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map(|modified| DateTime::<Local>::from(modified).naive_local())
+            .unwrap_or_else(|_| Local::now().naive_local())
+    }
+
+    /// Picks a path for a note written by this save, avoiding the paths other
+    /// notes took and files this backend does not own.
+    fn choose_path(
+        &self,
+        note: &Note,
+        taken: &HashSet<PathBuf>,
+        known_files: &HashMap<PathBuf, KnownFile>,
+    ) -> PathBuf {
+        let directory = if note.is_archived() {
+            self.directory.join(ARCHIVE_DIRECTORY)
+        } else {
+            self.directory.clone()
+        };
+        let stem = Self::file_stem(note.get_title());
+
+        (1..)
+            .map(|n| match n {
+                1 => directory.join(format!("{}.md", stem)),
+                n => directory.join(format!("{} ({}).md", stem, n)),
+            })
+            .find(|path| {
+                if taken.contains(path) {
+                    return false;
+                }
+                match Self::read_hash(path) {
+                    None => !path.exists(),
+                    // Only overwrite files unchanged since this backend saw them
+                    Some(hash) => known_files
+                        .get(path)
+                        .is_some_and(|known| known.content_hash == hash),
+                }
+            })
+            .unwrap()
+    }
+}
+
+impl PersistenceTrait for VaultPersistence {
+    fn save(&self, notes: &[Note]) -> Result<(), String> {
+        let mut known_files = self.known_files.borrow_mut();
+        let paths_by_note: HashMap<u64, PathBuf> = known_files
+            .iter()
+            .map(|(path, known)| (known.note_hash, path.clone()))
+            .collect();
+        let mut taken = HashSet::new();
+        let mut saved_files = HashMap::new();
+
+        // Notes unchanged since they were read or written keep their file, and
+        // if that file was edited or removed externally the external version wins
+        let mut changed_notes = Vec::new();
+        for note in notes {
+            let document = front_matter::note_to_document(note);
+            let note_hash = Self::hash(&document);
+            match paths_by_note.get(&note_hash) {
+                Some(path) if !taken.contains(path) => {
+                    taken.insert(path.clone());
+                    saved_files.insert(path.clone(), known_files[path]);
+                }
+                _ => changed_notes.push((note, document, note_hash)),
+            }
+        }
+
+        for (note, document, note_hash) in changed_notes {
+            let path = self.choose_path(note, &taken, &known_files);
+            if Self::read_hash(&path) != Some(note_hash) {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::write(&path, &document).map_err(|e| e.to_string())?;
+            }
+            taken.insert(path.clone());
+            saved_files.insert(
+                path,
+                KnownFile {
+                    content_hash: note_hash,
+                    note_hash,
+                },
+            );
+        }
+
+        for (path, known) in known_files.iter() {
+            if !taken.contains(path) && Self::read_hash(path) == Some(known.content_hash) {
+                fs::remove_file(path).map_err(|e| e.to_string())?;
+            }
+        }
+
+        *known_files = saved_files;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<Note>, String> {
+        let mut files = Vec::new();
+        Self::collect_files(&self.directory, &mut files)?;
+        files.sort();
+
+        let mut known_files = self.known_files.borrow_mut();
+        known_files.clear();
+        let mut notes = Vec::new();
+        for path in files {
+            let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            let fallback_title = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("untitled");
+            let note = front_matter::note_from_document(
+                &content,
+                fallback_title,
+                Self::modified_date_time(&path),
+            )
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+            known_files.insert(
+                path,
+                KnownFile {
+                    content_hash: Self::hash(&content),
+                    note_hash: Self::hash(&front_matter::note_to_document(&note)),
+                },
+            );
+            notes.push(note);
+        }
+        Ok(notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::persistence_trait::load_and_save;
+
+    fn setup_vault(name: &str) -> (VaultPersistence, PathBuf) {
+        let directory = std::env::temp_dir().join(format!("note_taker_vault_{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        let persistence = VaultPersistence::new(directory.to_str().unwrap()).unwrap();
+        (persistence, directory)
+    }
+
+    fn note(title: &str, content: &str) -> Note {
+        Note::new_with_date_time(
+            title.to_string(),
+            content.to_string(),
+            "2025-01-01 10:00".to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_vault_persistence() {
+        let (persistence, directory) = setup_vault("round_trip");
+        let mut archived = note("Archived", "Old");
+        archived.set_archived(true);
+        let notes = vec![note("First", "# Heading\n\nBody"), archived];
+
+        persistence.save(&notes).unwrap();
+        let loaded_notes = persistence.load().unwrap();
+
+        assert!(directory.join("First.md").exists());
+        assert!(directory.join("archive").join("Archived.md").exists());
+        assert_eq!(loaded_notes.len(), 2);
+        assert!(loaded_notes.contains(&notes[0]));
+        assert!(loaded_notes.iter().any(|n| n.is_archived()));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_make_file_names_safe() {
+        assert_eq!(VaultPersistence::file_stem("a/b\\c: d?"), "a-b-c- d-");
+        assert_eq!(VaultPersistence::file_stem(" ..hidden "), "hidden");
+        assert_eq!(VaultPersistence::file_stem("..."), "untitled");
+        assert_eq!(VaultPersistence::file_stem("con"), "_con");
+        assert_eq!(VaultPersistence::file_stem("COM1.txt"), "_COM1.txt");
+        assert_eq!(VaultPersistence::file_stem(&"x".repeat(200)).len(), 100);
+        assert_eq!(VaultPersistence::file_stem(&"笔记".repeat(50)).len(), 198);
+    }
+
+    #[test]
+    fn test_should_save_long_multibyte_titles() {
+        let (persistence, directory) = setup_vault("long_titles");
+        let title = "长".repeat(100);
+
+        persistence
+            .save(&[note(&title, "One"), note(&title, "Two")])
+            .unwrap();
+
+        assert_eq!(persistence.load().unwrap().len(), 2);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_handle_file_name_collisions() {
+        let (persistence, directory) = setup_vault("collisions");
+        let notes = vec![
+            note("Same", "One"),
+            note("Same", "Two"),
+            note("Same?", "Three"),
+        ];
+
+        persistence.save(&notes).unwrap();
+
+        assert!(directory.join("Same.md").exists());
+        assert!(directory.join("Same (2).md").exists());
+        assert!(directory.join("Same-.md").exists());
+        assert_eq!(persistence.load().unwrap().len(), 3);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_delete_files_of_deleted_and_renamed_notes() {
+        let (persistence, directory) = setup_vault("deletions");
+        persistence
+            .save(&[note("Keep", ""), note("Delete", "")])
+            .unwrap();
+
+        persistence.save(&[note("Keep renamed", "")]).unwrap();
+
+        assert!(!directory.join("Keep.md").exists());
+        assert!(!directory.join("Delete.md").exists());
+        assert!(directory.join("Keep renamed.md").exists());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_load_files_added_externally() {
+        let (persistence, directory) = setup_vault("external_add");
+        fs::create_dir_all(directory.join("nested")).unwrap();
+        fs::write(
+            directory.join("nested").join("From editor.md"),
+            "Plain text",
+        )
+        .unwrap();
+        fs::write(directory.join("ignored.txt"), "Not a note").unwrap();
+
+        let loaded_notes = persistence.load().unwrap();
+
+        assert_eq!(loaded_notes.len(), 1);
+        assert_eq!(loaded_notes[0].get_title(), "From editor");
+        assert_eq!(loaded_notes[0].get_content(), "Plain text");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_should_not_rewrite_unchanged_files_from_other_tools() {
+        let (persistence, directory) = setup_vault("external_format");
+        let content = "---\ntitle:   Spaced\n---\nBody\n";
+        fs::write(directory.join("Spaced.md"), content).unwrap();
+
+        let notes = persistence.load().unwrap();
+        persistence.save(&notes).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(directory.join("Spaced.md")).unwrap(),
+            content
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_save_should_not_touch_files_added_after_load() {
+        let (persistence, directory) = setup_vault("external_after_load");
+        persistence.load().unwrap();
+        fs::write(directory.join("Note.md"), "Written by another program").unwrap();
+
+        persistence.save(&[note("Note", "Written here")]).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(directory.join("Note.md")).unwrap(),
+            "Written by another program"
+        );
+        assert!(directory.join("Note (2).md").exists());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_external_edits_should_win_over_unchanged_notes() {
+        let (persistence, directory) = setup_vault("external_edit");
+        let notes = vec![note("Note", "Original")];
+        persistence.save(&notes).unwrap();
+
+        let edited = fs::read_to_string(directory.join("Note.md"))
+            .unwrap()
+            .replace("Original", "Edited externally");
+        fs::write(directory.join("Note.md"), &edited).unwrap();
+        persistence.save(&notes).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(directory.join("Note.md")).unwrap(),
+            edited
+        );
+        assert!(!directory.join("Note (2).md").exists());
+        assert_eq!(
+            persistence.load().unwrap()[0].get_content(),
+            "Edited externally"
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_conflicting_edits_should_keep_both_versions() {
+        let (persistence, directory) = setup_vault("conflict");
+        persistence.save(&[note("Note", "Original")]).unwrap();
+
+        let edited = fs::read_to_string(directory.join("Note.md"))
+            .unwrap()
+            .replace("Original", "Edited externally");
+        fs::write(directory.join("Note.md"), &edited).unwrap();
+        persistence.save(&[note("Note", "Edited here")]).unwrap();
+
+        let mut contents: Vec<String> = persistence
+            .load()
+            .unwrap()
+            .iter()
+            .map(|n| n.get_content().to_string())
+            .collect();
+        contents.sort();
+        assert_eq!(contents, vec!["Edited externally", "Edited here"]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_saving_through_another_backend_should_not_duplicate_files() {
+        let (persistence, directory) = setup_vault("other_backend");
+        persistence
+            .save(&[note("First", "One"), note("Second", "Two")])
+            .unwrap();
+
+        // As the signal handler does, with the session's notes since then
+        let other = VaultPersistence::new(directory.to_str().unwrap()).unwrap();
+        load_and_save(&other, &[note("First", "Edited"), note("Third", "Three")]).unwrap();
+
+        let mut files: Vec<String> = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["First.md", "Third.md"]);
+        assert_eq!(
+            VaultPersistence::new(directory.to_str().unwrap())
+                .unwrap()
+                .load()
+                .unwrap(),
+            vec![note("First", "Edited"), note("Third", "Three")]
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
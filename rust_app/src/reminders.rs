@@ -0,0 +1,766 @@
+use crate::note::Note;
+use crate::note_taker::NoteTaker;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// A cron-like schedule of the form `minute hour day-of-month month
+/// day-of-week`, where each field is `*`, a number, a range such as `1-5`,
+/// a list of those, or any of them with a step such as `*/15`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    expression: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+// Returns which of the values from 0 to `max` a field allows
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, String> {
+    let invalid = || format!("Invalid schedule field: {}", field);
+    let number = |text: &str| {
+        text.parse::<u32>()
+            .ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(invalid)
+    };
+    let mut allowed = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|&step| step > 0)
+                    .ok_or_else(invalid)?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (number(start)?, number(end)?),
+            // A single value with a step runs to the end of the field
+            None if step > 1 => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "Schedule needs minute, hour, day, month and weekday: {}",
+                expression
+            ));
+        };
+        let mut weekday_values = parse_field(weekdays, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if weekday_values.pop() == Some(true) {
+            weekday_values[0] = true;
+        }
+        Ok(Schedule {
+            expression: fields.join(" "),
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekday_values,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+
+    // As in cron, a day matches either field when both are restricted
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days[date.day() as usize];
+        let weekday = self.weekdays[date.weekday().num_days_from_sunday() as usize];
+        self.months[date.month() as usize]
+            && match (self.any_day, self.any_weekday) {
+                (true, true) => true,
+                (true, false) => weekday,
+                (false, true) => day,
+                (false, false) => day || weekday,
+            }
+    }
+
+    /// Returns the first scheduled minute after a date time, if there is one
+    /// within the next years.
+    pub fn next_after(&self, date_time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = date_time.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        // Four years and a day cover every day a schedule can be limited to
+        start
+            .date()
+            .iter_days()
+            .take(4 * 366)
+            .filter(|&date| self.matches_day(date))
+            .find_map(|date| {
+                (0..24)
+                    .filter(|&hour| self.hours[hour as usize])
+                    .flat_map(|hour| {
+                        (0..60)
+                            .filter(|&minute| self.minutes[minute as usize])
+                            .filter_map(move |minute| date.and_hms_opt(hour, minute, 0))
+                    })
+                    .find(|&candidate| candidate >= start)
+            })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repeat {
+    Never,
+    Daily,
+    Weekly,
+    Cron(Schedule),
+}
+
+impl Repeat {
+    /// Reads `never` (or nothing), `daily`, `weekly` or `cron` followed by a
+    /// schedule.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        match text.to_lowercase().as_str() {
+            "" | "never" => Ok(Repeat::Never),
+            "daily" => Ok(Repeat::Daily),
+            "weekly" => Ok(Repeat::Weekly),
+            _ => match text.split_once(char::is_whitespace) {
+                Some((word, schedule)) if word.eq_ignore_ascii_case("cron") => {
+                    Ok(Repeat::Cron(Schedule::parse(schedule)?))
+                }
+                _ => Err(format!("Invalid repeat: {}", text)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Repeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Repeat::Never => write!(f, "never"),
+            Repeat::Daily => write!(f, "daily"),
+            Repeat::Weekly => write!(f, "weekly"),
+            Repeat::Cron(schedule) => write!(f, "cron {}", schedule),
+        }
+    }
+}
+
+/// When a note is due, how it repeats and until when it is snoozed. It is
+/// written as `YYYY-MM-DD HH:MM`, followed by the repeat unless it is
+/// `never`, and by `snoozed YYYY-MM-DD HH:MM` while snoozed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reminder {
+    at: NaiveDateTime,
+    repeat: Repeat,
+    snoozed_until: Option<NaiveDateTime>,
+}
+
+fn parse_date_time(text: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(text.trim(), DATE_TIME_FORMAT)
+        .map_err(|_| format!("Invalid reminder time: {}", text.trim()))
+}
+
+impl Reminder {
+    /// Creates a reminder at a time, moved to the first scheduled time from
+    /// then on when it repeats on a schedule.
+    pub fn new(at: NaiveDateTime, repeat: Repeat) -> Result<Self, String> {
+        let at = match &repeat {
+            Repeat::Cron(schedule) => schedule
+                .next_after(at - Duration::minutes(1))
+                .ok_or(format!("Schedule never runs: {}", schedule))?,
+            _ => at,
+        };
+        Ok(Reminder {
+            at,
+            repeat,
+            snoozed_until: None,
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (text, snoozed_until) = match text.split_once(" snoozed ") {
+            Some((text, until)) => (text, Some(parse_date_time(until)?)),
+            None => (text, None),
+        };
+        let text = text.trim();
+        // The time is written as YYYY-MM-DD HH:MM
+        let split = text.len().min(16);
+        if !text.is_char_boundary(split) {
+            return Err(format!("Invalid reminder: {}", text));
+        }
+        let (at, repeat) = text.split_at(split);
+        Ok(Reminder {
+            at: parse_date_time(at)?,
+            repeat: Repeat::parse(repeat)?,
+            snoozed_until,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn get_at(&self) -> NaiveDateTime {
+        self.at
+    }
+
+    #[allow(dead_code)]
+    pub fn get_repeat(&self) -> &Repeat {
+        &self.repeat
+    }
+
+    /// Returns when the reminder fires next, which is later than its time
+    /// while it is snoozed.
+    pub fn get_due(&self) -> NaiveDateTime {
+        self.snoozed_until.unwrap_or(self.at)
+    }
+
+    pub fn is_due(&self, now: NaiveDateTime) -> bool {
+        self.get_due() <= now
+    }
+
+    pub fn snooze(&mut self, until: NaiveDateTime) {
+        self.snoozed_until = Some(until);
+    }
+
+    /// Returns the reminder as it is after firing: `None` if it does not
+    /// repeat, else at its first time after `now`. Missed times are skipped.
+    pub fn next(&self, now: NaiveDateTime) -> Option<Reminder> {
+        let period = match &self.repeat {
+            Repeat::Never => return None,
+            Repeat::Daily => Duration::days(1),
+            Repeat::Weekly => Duration::weeks(1),
+            Repeat::Cron(schedule) => {
+                return Some(Reminder {
+                    at: schedule.next_after(now.max(self.at))?,
+                    repeat: self.repeat.clone(),
+                    snoozed_until: None,
+                });
+            }
+        };
+        let mut at = self.at;
+        if at <= now {
+            let periods = (now - at).num_minutes() / period.num_minutes() + 1;
+            at += period * periods as i32;
+        }
+        Some(Reminder {
+            at,
+            repeat: self.repeat.clone(),
+            snoozed_until: None,
+        })
+    }
+}
+
+impl fmt::Display for Reminder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.at.format(DATE_TIME_FORMAT))?;
+        if self.repeat != Repeat::Never {
+            write!(f, " {}", self.repeat)?;
+        }
+        if let Some(until) = self.snoozed_until {
+            write!(f, " snoozed {}", until.format(DATE_TIME_FORMAT))?;
+        }
+        Ok(())
+    }
+}
+
+/// How a due reminder is announced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notifier {
+    /// A shell command, run with the note in `NOTE_TITLE`, `NOTE_CONTENT`,
+    /// `NOTE_ID` and `NOTE_DUE`.
+    Command(String),
+    /// A file a line is appended to for each reminder.
+    Log(PathBuf),
+}
+
+impl Notifier {
+    /// Runs `NOTES_REMINDER_COMMAND` when it is set, else appends to
+    /// `NOTES_REMINDER_LOG` (`~/.note_taker_reminders.log` by default).
+    pub fn from_env() -> Self {
+        if let Ok(command) = std::env::var("NOTES_REMINDER_COMMAND")
+            && !command.trim().is_empty()
+        {
+            return Notifier::Command(command);
+        }
+        let path = std::env::var_os("NOTES_REMINDER_LOG")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME")
+                    .map(|home| PathBuf::from(home).join(".note_taker_reminders.log"))
+            })
+            .unwrap_or_else(|| PathBuf::from("note_taker_reminders.log"));
+        Notifier::Log(path)
+    }
+
+    pub fn notify(&self, note: &Note, due: NaiveDateTime) -> Result<(), String> {
+        let due = due.format(DATE_TIME_FORMAT).to_string();
+        match self {
+            Notifier::Command(command) => {
+                // The content of a locked note stays secret
+                let content = if note.is_locked() {
+                    ""
+                } else {
+                    note.get_content()
+                };
+                let status = std::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .env("NOTE_TITLE", note.get_title())
+                    .env("NOTE_CONTENT", content)
+                    .env("NOTE_ID", note.get_id())
+                    .env("NOTE_DUE", &due)
+                    .status()
+                    .map_err(|e| format!("Failed to run {}: {}", command, e))?;
+                if !status.success() {
+                    return Err(format!("Reminder command failed with {}", status));
+                }
+                Ok(())
+            }
+            Notifier::Log(path) => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| e.to_string())?;
+                writeln!(file, "{} {}", due, note.get_title()).map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// Remembers the reminders already announced, by note and due time, so a
+/// reminder brought back by a session saving an older copy of the notes is
+/// not announced again. Entries are kept for a week after they were due.
+pub struct FiredLog {
+    path: PathBuf,
+    entries: HashSet<String>,
+}
+
+impl FiredLog {
+    /// Uses `NOTES_REMINDER_FIRED`, else `~/.note_taker_reminders_fired`.
+    pub fn from_env(now: NaiveDateTime) -> Result<Self, String> {
+        let path = std::env::var_os("NOTES_REMINDER_FIRED")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME")
+                    .map(|home| PathBuf::from(home).join(".note_taker_reminders_fired"))
+            })
+            .unwrap_or_else(|| PathBuf::from("note_taker_reminders_fired"));
+        FiredLog::open(path, now)
+    }
+
+    /// Reads the log at `path`, dropping the entries due over a week before `now`.
+    pub fn open(path: PathBuf, now: NaiveDateTime) -> Result<Self, String> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.to_string()),
+        };
+        let oldest = now - Duration::weeks(1);
+        let entries: HashSet<String> = text
+            .lines()
+            .filter(|line| {
+                line.split_once(' ')
+                    .and_then(|(_, due)| parse_date_time(due).ok())
+                    .is_some_and(|due| due >= oldest)
+            })
+            .map(|line| line.to_string())
+            .collect();
+        if entries.len() != text.lines().count() {
+            let mut kept: Vec<&String> = entries.iter().collect();
+            kept.sort();
+            let kept: String = kept.iter().map(|line| format!("{}\n", line)).collect();
+            fs::write(&path, kept).map_err(|e| e.to_string())?;
+        }
+        Ok(FiredLog { path, entries })
+    }
+
+    fn entry(note: &Note, due: NaiveDateTime) -> String {
+        format!("{} {}", note.get_id(), due.format(DATE_TIME_FORMAT))
+    }
+
+    pub fn contains(&self, note: &Note, due: NaiveDateTime) -> bool {
+        self.entries.contains(&FiredLog::entry(note, due))
+    }
+
+    pub fn record(&mut self, note: &Note, due: NaiveDateTime) -> Result<(), String> {
+        let entry = FiredLog::entry(note, due);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{}", entry)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| e.to_string())?;
+        self.entries.insert(entry);
+        Ok(())
+    }
+}
+
+/// Announces the due reminders of the notes that are not archived, unless
+/// `fired` shows they already were, then moves repeating ones to their next
+/// time and clears the others. A reminder that fails to be announced stays
+/// due, so it is tried again; the first failure is returned once the other
+/// reminders are done. Returns the notes whose reminders were announced.
+pub fn fire_due(
+    note_taker: &mut NoteTaker,
+    notifier: &Notifier,
+    fired: &mut FiredLog,
+    now: NaiveDateTime,
+) -> Result<Vec<Note>, String> {
+    let due: Vec<Note> = note_taker
+        .get_due_reminders(now)
+        .iter()
+        .map(|&index| note_taker.get_note(index))
+        .collect();
+    let mut announced = Vec::new();
+    let mut error = None;
+    for note in due {
+        let reminder = note.get_reminder().unwrap();
+        if !fired.contains(&note, reminder.get_due()) {
+            let result = notifier
+                .notify(&note, reminder.get_due())
+                .and_then(|()| fired.record(&note, reminder.get_due()));
+            if let Err(e) = result {
+                error.get_or_insert(format!("{}: {}", note.get_title(), e));
+                continue;
+            }
+            announced.push(note.clone());
+        }
+        let index = note_taker.index_of(&note).ok_or("Note not found")?;
+        note_taker.set_reminder(index, reminder.next(now))?;
+    }
+    match error {
+        Some(e) => Err(e),
+        None => Ok(announced),
+    }
+}
+
+/// Copies the reminders `changed` has changed since the notes were `loaded`
+/// into `latest`, the notes as stored now. Notes edited, deleted or given
+/// another reminder since they were loaded are left as they are.
+pub fn merge_reminders(
+    loaded: &[Note],
+    changed: &NoteTaker,
+    latest: &mut NoteTaker,
+) -> Result<(), String> {
+    for note in loaded {
+        let Some(index) = changed.index_of(note) else {
+            continue;
+        };
+        let reminder = changed.get_note(index).get_reminder().cloned();
+        if reminder.as_ref() == note.get_reminder() {
+            continue;
+        }
+        if let Some(index) = latest.index_of(note)
+            && latest.get_note(index).get_reminder() == note.get_reminder()
+        {
+            latest.set_reminder(index, reminder)?;
+        }
+    }
+    Ok(())
+}
+
+// Fires the due reminders of the notes as `open` loads them, then saves the
+// reminder changes alone into the notes as stored by then, so edits other
+// sessions saved meanwhile are kept
+fn check(
+    open: &impl Fn() -> Result<NoteTaker, String>,
+    notifier: &Notifier,
+    fired: &mut FiredLog,
+    now: NaiveDateTime,
+) -> Result<Vec<Note>, String> {
+    let mut note_taker = open()?;
+    let loaded = note_taker.get_all_notes();
+    let result = fire_due(&mut note_taker, notifier, fired, now);
+    if note_taker.has_unsaved_changes() {
+        let mut latest = open()?;
+        merge_reminders(&loaded, &note_taker, &mut latest)?;
+        if latest.has_unsaved_changes() {
+            latest.save()?;
+        }
+    }
+    result
+}
+
+/// Checks for due reminders at the start of every minute, loading the notes
+/// with `open` each time so changes saved by other sessions are seen. Only
+/// the changed reminders are saved, into the notes as stored right before.
+/// A session that loaded the notes earlier still saves its own copy of the
+/// reminders, which `fired` keeps from being announced twice. Only returns
+/// with `once`, after the first check.
+pub fn run_daemon(
+    open: impl Fn() -> Result<NoteTaker, String>,
+    notifier: &Notifier,
+    fired: &mut FiredLog,
+    once: bool,
+) -> Result<(), String> {
+    loop {
+        let now = chrono::Local::now().naive_local();
+        match check(&open, notifier, fired, now) {
+            Ok(announced) => {
+                for note in announced {
+                    println!(
+                        "{} Reminded {}",
+                        now.format(DATE_TIME_FORMAT),
+                        note.get_title()
+                    );
+                }
+            }
+            Err(e) if once => return Err(e),
+            Err(e) => eprintln!("{}", e),
+        }
+        if once {
+            return Ok(());
+        }
+        let now = chrono::Local::now().naive_local();
+        std::thread::sleep(std::time::Duration::from_secs(60 - now.second() as u64));
+    }
+}
+
+#[cfg(test)]
+use crate::persistence::file_persistence::FilePersistence;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date_time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, DATE_TIME_FORMAT).unwrap()
+    }
+
+    fn setup_note_taker() -> NoteTaker {
+        let persistence = Box::new(FilePersistence::new("test_notes.json".to_string()));
+        NoteTaker::new(persistence)
+    }
+
+    fn reminder_of(note_taker: &NoteTaker, title: &str) -> Option<String> {
+        let index = note_taker.get_by_title(title)[0];
+        note_taker
+            .get_note(index)
+            .get_reminder()
+            .map(|reminder| reminder.to_string())
+    }
+
+    fn temp_fired_log(name: &str, now: &str) -> FiredLog {
+        let path = std::env::temp_dir().join(format!("note_taker_reminders_fired_{}", name));
+        let _ = fs::remove_file(&path);
+        FiredLog::open(path, date_time(now)).unwrap()
+    }
+
+    #[test]
+    fn test_should_parse_and_write_reminders() {
+        for text in [
+            "2025-03-04 09:00",
+            "2025-03-04 09:00 daily",
+            "2025-03-04 09:00 weekly snoozed 2025-03-04 09:10",
+            "2025-03-04 09:00 cron */15 9-17 * * 1-5",
+        ] {
+            assert_eq!(Reminder::parse(text).unwrap().to_string(), text);
+        }
+        assert_eq!(
+            Reminder::parse("2025-03-04 09:00 never")
+                .unwrap()
+                .to_string(),
+            "2025-03-04 09:00"
+        );
+        assert!(Reminder::parse("2025-03-04").is_err());
+        assert!(Reminder::parse("2025-03-04 09:00 hourly").is_err());
+        assert!(Reminder::parse("2025-03-04 09:00 cron 60 * * * *").is_err());
+        assert!(Reminder::parse("2025-03-04 09:00 cron * * *").is_err());
+    }
+
+    #[test]
+    fn test_should_find_next_scheduled_time() {
+        let weekdays = Schedule::parse("30 9 * * 1-5").unwrap();
+        // 2025-03-07 is a Friday
+        assert_eq!(
+            weekdays.next_after(date_time("2025-03-07 09:30")),
+            Some(date_time("2025-03-10 09:30"))
+        );
+        let either = Schedule::parse("0 0 13 * 5").unwrap();
+        assert_eq!(
+            either.next_after(date_time("2025-03-04 12:00")),
+            Some(date_time("2025-03-07 00:00"))
+        );
+        let leap_day = Schedule::parse("0 12 29 2 *").unwrap();
+        assert_eq!(
+            leap_day.next_after(date_time("2025-03-04 12:00")),
+            Some(date_time("2028-02-29 12:00"))
+        );
+        assert_eq!(
+            Schedule::parse("0 0 31 2 *")
+                .unwrap()
+                .next_after(date_time("2025-03-04 12:00")),
+            None
+        );
+        assert_eq!(
+            Reminder::new(date_time("2025-03-04 09:00"), Repeat::Cron(weekdays))
+                .unwrap()
+                .get_at(),
+            date_time("2025-03-04 09:30")
+        );
+    }
+
+    #[test]
+    fn test_should_repeat_and_snooze() {
+        let mut daily = Reminder::new(date_time("2025-03-04 09:00"), Repeat::Daily).unwrap();
+        daily.snooze(date_time("2025-03-04 09:10"));
+
+        assert!(!daily.is_due(date_time("2025-03-04 09:05")));
+        assert!(daily.is_due(date_time("2025-03-04 09:10")));
+        // Missed days are skipped, and the snooze does not shift the time
+        assert_eq!(
+            daily
+                .next(date_time("2025-03-06 10:00"))
+                .unwrap()
+                .to_string(),
+            "2025-03-07 09:00 daily"
+        );
+        let weekly = Reminder::new(date_time("2025-03-04 09:00"), Repeat::Weekly).unwrap();
+        assert_eq!(
+            weekly
+                .next(date_time("2025-03-04 09:00"))
+                .unwrap()
+                .get_due(),
+            date_time("2025-03-11 09:00")
+        );
+        let once = Reminder::new(date_time("2025-03-04 09:00"), Repeat::Never).unwrap();
+        assert_eq!(once.next(date_time("2025-03-04 09:00")), None);
+    }
+
+    #[test]
+    fn test_should_fire_due_reminders() {
+        let path = std::env::temp_dir().join("note_taker_reminders_fire.log");
+        let _ = std::fs::remove_file(&path);
+        let mut note_taker = setup_note_taker();
+        for (title, reminder) in [
+            ("Standup", "2025-03-04 09:00 daily"),
+            ("Dentist", "2025-03-04 08:00"),
+            ("Later", "2025-03-05 08:00"),
+        ] {
+            let mut note = Note::new(title.to_string(), String::new()).unwrap();
+            note.set_reminder(Some(Reminder::parse(reminder).unwrap()));
+            note_taker.add_note(note);
+        }
+
+        let mut fired_log = temp_fired_log("fire", "2025-03-04 09:00");
+        let fired = fire_due(
+            &mut note_taker,
+            &Notifier::Log(path.clone()),
+            &mut fired_log,
+            date_time("2025-03-04 09:00"),
+        )
+        .unwrap();
+
+        let mut titles: Vec<&str> = fired.iter().map(|note| note.get_title()).collect();
+        titles.sort();
+        assert_eq!(titles, vec!["Dentist", "Standup"]);
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert!(log.contains("2025-03-04 09:00 Standup\n"));
+        assert!(log.contains("2025-03-04 08:00 Dentist\n"));
+        assert_eq!(
+            reminder_of(&note_taker, "Standup"),
+            Some("2025-03-05 09:00 daily".to_string())
+        );
+        assert_eq!(reminder_of(&note_taker, "Dentist"), None);
+        assert_eq!(
+            reminder_of(&note_taker, "Later"),
+            Some("2025-03-05 08:00".to_string())
+        );
+
+        let failing = Notifier::Command("exit 1".to_string());
+        assert!(
+            fire_due(
+                &mut note_taker,
+                &failing,
+                &mut fired_log,
+                date_time("2025-03-05 08:00")
+            )
+            .is_err()
+        );
+        assert_eq!(
+            reminder_of(&note_taker, "Later"),
+            Some("2025-03-05 08:00".to_string())
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_should_keep_interleaved_saves() {
+        let db = std::env::temp_dir().join("note_taker_reminders_interleaved.json");
+        let db = db.to_str().unwrap().to_string();
+        let log = std::env::temp_dir().join("note_taker_reminders_interleaved.log");
+        let _ = fs::remove_file(&db);
+        let _ = fs::remove_file(&log);
+        let open = || {
+            let mut note_taker = NoteTaker::new(Box::new(FilePersistence::new(db.clone())));
+            note_taker.load().map(|()| note_taker)
+        };
+        let mut note = Note::new("Dentist".to_string(), String::new()).unwrap();
+        note.set_reminder(Some(Reminder::parse("2025-03-04 09:00").unwrap()));
+        let mut note_taker = open().unwrap();
+        note_taker.add_note(note);
+        note_taker.save().unwrap();
+        let stale = open().unwrap();
+
+        // Another session saves a new note while the daemon is firing
+        let opened = std::cell::Cell::new(0);
+        let open_during_edit = || {
+            opened.set(opened.get() + 1);
+            if opened.get() == 2 {
+                let mut other = open()?;
+                other.add_note(Note::new("Groceries".to_string(), String::new())?);
+                other.save()?;
+            }
+            open()
+        };
+        let notifier = Notifier::Log(log.clone());
+        let mut fired = temp_fired_log("interleaved", "2025-03-04 09:00");
+        let announced = check(
+            &open_during_edit,
+            &notifier,
+            &mut fired,
+            date_time("2025-03-04 09:00"),
+        )
+        .unwrap();
+
+        assert_eq!(announced.len(), 1);
+        let note_taker = open().unwrap();
+        assert_eq!(note_taker.get_by_title("Groceries").len(), 1);
+        assert_eq!(reminder_of(&note_taker, "Dentist"), None);
+
+        // A session that loaded the notes before saves the reminder back
+        stale.save().unwrap();
+        let announced = check(&open, &notifier, &mut fired, date_time("2025-03-04 09:01")).unwrap();
+
+        assert!(announced.is_empty());
+        assert_eq!(fs::read_to_string(&log).unwrap().lines().count(), 1);
+        assert_eq!(reminder_of(&open().unwrap(), "Dentist"), None);
+        fs::remove_file(db).unwrap();
+        fs::remove_file(log).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Config, Context, Editor, Helper};
+
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+/// The menu commands, numbered from 1 in this order.
+pub const COMMANDS: [(&str, &str); 27] = [
+    ("add", "Add Note"),
+    ("view", "View Notes"),
+    ("delete", "Delete Note"),
+    ("edit", "Edit Note"),
+    ("tag", "Tag/Untag Note"),
+    ("move", "Move Note"),
+    ("pin", "Pin/Unpin Note"),
+    ("archive", "Archive/Unarchive Note"),
+    ("archived", "View Archived Notes"),
+    ("undo", "Undo"),
+    ("redo", "Redo"),
+    ("links", "Show Links"),
+    ("broken", "Show Broken Links"),
+    ("graph", "Export Graph"),
+    ("report", "Show Graph Report"),
+    ("path", "Find Path Between Notes"),
+    ("lock", "Lock/Unlock Note"),
+    ("import", "Import Notes"),
+    ("export", "Export Notes"),
+    ("backup", "Backup Notes"),
+    ("restore", "Restore Backup"),
+    ("journal", "Daily Journal"),
+    ("templates", "Templates"),
+    ("tasks", "Open Tasks"),
+    ("remind", "Reminders"),
+    ("history", "Storage History"),
+    ("exit", "Exit"),
+];
+
+/// The number of the exit command, always the last one.
+pub const EXIT: u32 = COMMANDS.len() as u32;
+
+pub fn print_menu() {
+    for (i, (name, label)) in COMMANDS.iter().enumerate() {
+        println!("{}. {} ({})", i + 1, label, name);
+    }
+}
+
+/// Reads a menu choice given by number or by command name, returning its number.
+pub fn parse_command(input: &str) -> Option<u32> {
+    let input = input.trim().to_lowercase();
+    if let Ok(number) = input.parse::<u32>() {
+        return (1..=COMMANDS.len() as u32)
+            .contains(&number)
+            .then_some(number);
+    }
+    let input = if input == "quit" { "exit" } else { &input };
+    COMMANDS
+        .iter()
+        .position(|(name, _)| *name == input)
+        .map(|i| i as u32 + 1)
+}
+
+/// What a command prompt read.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Choice(u32),
+    Help,
+    Invalid(String),
+    /// Ctrl-C, which only clears the line.
+    Interrupted,
+    /// Ctrl-D, or the end of piped input.
+    Exit,
+}
+
+#[derive(Default)]
+struct NoteHelper {
+    candidates: Vec<String>,
+}
+
+impl NoteHelper {
+    // Completes the whole line, as note titles may hold spaces
+    fn complete_line(&self, line: &str) -> Vec<String> {
+        let line = line.to_lowercase();
+        self.candidates
+            .iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&line))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Completer for NoteHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let pairs = self
+            .complete_line(&line[..pos])
+            .into_iter()
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+        Ok((0, pairs))
+    }
+}
+
+impl Hinter for NoteHelper {
+    type Hint = String;
+}
+
+impl Highlighter for NoteHelper {}
+
+impl Validator for NoteHelper {}
+
+impl Helper for NoteHelper {}
+
+/// Reads commands and answers with line editing, tab completion and a
+/// history kept across sessions.
+pub struct Repl {
+    editor: Editor<NoteHelper, DefaultHistory>,
+    history_path: Option<PathBuf>,
+}
+
+impl Repl {
+    /// Opens a prompt whose history lives in `NOTES_HISTORY`, else
+    /// `~/.note_taker_history`.
+    pub fn new() -> Result<Self, String> {
+        let history_path = std::env::var_os("NOTES_HISTORY")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".note_taker_history"))
+            });
+        let config = Config::builder()
+            .max_history_size(1000)
+            .and_then(|builder| builder.history_ignore_dups(true))
+            .map_err(|e| e.to_string())?
+            .auto_add_history(false)
+            .build();
+        let mut editor = Editor::with_config(config).map_err(|e| e.to_string())?;
+        editor.set_helper(Some(NoteHelper::default()));
+        if let Some(path) = &history_path {
+            // There is no history yet on a first run
+            let _ = editor.load_history(path);
+        }
+        Ok(Repl {
+            editor,
+            history_path,
+        })
+    }
+
+    fn readline(&mut self, prompt: &str, candidates: Vec<String>) -> Result<String, ReadlineError> {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.candidates = candidates;
+        }
+        self.editor.readline(prompt)
+    }
+
+    fn remember(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        let _ = self.editor.add_history_entry(line);
+        if let Some(path) = &self.history_path {
+            let _ = self.editor.save_history(path);
+        }
+    }
+
+    /// Reads a menu choice, completing command names.
+    pub fn read_command(&mut self) -> Command {
+        let names = COMMANDS.iter().map(|(name, _)| name.to_string()).collect();
+        match self.readline("> ", names) {
+            Ok(line) => {
+                self.remember(&line);
+                match line.trim() {
+                    "" => Command::Invalid(line),
+                    "help" | "?" => Command::Help,
+                    input => parse_command(input).map_or(Command::Invalid(line), Command::Choice),
+                }
+            }
+            Err(ReadlineError::Interrupted) => Command::Interrupted,
+            Err(ReadlineError::Eof) => Command::Exit,
+            Err(e) => {
+                println!("{}", e);
+                Command::Exit
+            }
+        }
+    }
+
+    /// Reads an answer, or `None` when the user cancels with Ctrl-C or Ctrl-D.
+    pub fn read(&mut self, prompt: &str) -> Option<String> {
+        self.read_completing(prompt, Vec::new())
+    }
+
+    /// Reads an answer, completing it from `candidates` such as note titles.
+    pub fn read_completing(&mut self, prompt: &str, candidates: Vec<String>) -> Option<String> {
+        let line = self.readline(&format!("{} ", prompt), candidates).ok()?;
+        self.remember(&line);
+        Some(line)
+    }
+
+    /// Reads an answer that is kept out of the history, such as a password.
+    pub fn read_secret(&mut self, prompt: &str) -> Option<String> {
+        read_secret(prompt)
+    }
+}
+
+/// Reads a secret such as a password without echoing it, or `None` when the
+/// user cancels with Ctrl-C, Ctrl-D or Escape. Input that is not a terminal
+/// is read as a plain line.
+pub fn read_secret(prompt: &str) -> Option<String> {
+    print!("{} ", prompt);
+    std::io::stdout().flush().ok()?;
+    if !std::io::stdin().is_terminal() {
+        let mut line = String::new();
+        let read = std::io::stdin().lock().read_line(&mut line).ok()?;
+        return (read > 0).then(|| line.trim_end_matches(['\n', '\r']).to_string());
+    }
+
+    terminal::enable_raw_mode().ok()?;
+    let mut secret = String::new();
+    let result = loop {
+        let Ok(Event::Key(key)) = event::read() else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => break Some(secret),
+            KeyCode::Esc => break None,
+            KeyCode::Char('c' | 'd') if control => break None,
+            KeyCode::Backspace => {
+                secret.pop();
+            }
+            KeyCode::Char(c) if !control => secret.push(c),
+            _ => {}
+        }
+    };
+    let _ = terminal::disable_raw_mode();
+    println!();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_parse_commands_by_number_or_name() {
+        assert_eq!(parse_command("1"), Some(1));
+        assert_eq!(parse_command(" Edit "), Some(4));
+        assert_eq!(parse_command("quit"), Some(EXIT));
+        assert_eq!(parse_command("0"), None);
+        assert_eq!(parse_command("28"), None);
+        assert_eq!(parse_command("-1"), None);
+        assert_eq!(parse_command("abc"), None);
+    }
+
+    #[test]
+    fn test_should_complete_whole_line() {
+        let helper = NoteHelper {
+            candidates: vec![
+                "Meeting notes".to_string(),
+                "meeting agenda".to_string(),
+                "Groceries".to_string(),
+            ],
+        };
+
+        assert_eq!(
+            helper.complete_line("meet"),
+            vec!["Meeting notes", "meeting agenda"]
+        );
+        assert_eq!(helper.complete_line("Meeting n"), vec!["Meeting notes"]);
+        assert!(helper.complete_line("x").is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+/// A checklist item such as `- [ ] Call Bob` or `- [x] Book room`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    /// The 0-based line of the content the task is on.
+    pub line: usize,
+    pub text: String,
+    pub done: bool,
+}
+
+// Returns the offset of the box of a task line and whether it is checked
+fn find_box(line: &str) -> Option<(usize, bool)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let bullet = rest.strip_prefix(['-', '*', '+'])?;
+    if !bullet.starts_with(' ') {
+        return None;
+    }
+    let offset = indent + 1 + (bullet.len() - bullet.trim_start().len());
+    let done = match line[offset..].get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    // The box must be followed by the text, or end the line
+    match line[offset + 3..].chars().next() {
+        None | Some(' ' | '\t' | '\r') => Some((offset, done)),
+        _ => None,
+    }
+}
+
+/// Returns the checklist items in some content, skipping fenced code blocks.
+pub fn parse_tasks(content: &str) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut in_code = false;
+    for (i, line) in content.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        if let Some((offset, done)) = find_box(line) {
+            tasks.push(Task {
+                line: i,
+                text: line[offset + 3..].trim().to_string(),
+                done,
+            });
+        }
+    }
+    tasks
+}
+
+/// Checks the task on a line of some content if it is open, or opens it if
+/// it is checked, leaving the rest of the content as it is.
+pub fn toggle_task(content: &str, line: usize) -> Result<String, String> {
+    let task = parse_tasks(content)
+        .into_iter()
+        .find(|task| task.line == line)
+        .ok_or(format!("No task on line {}", line + 1))?;
+    let lines: Vec<String> = content
+        .split('\n')
+        .enumerate()
+        .map(|(i, text)| {
+            if i != task.line {
+                return text.to_string();
+            }
+            let (offset, _) = find_box(text).unwrap();
+            let mark = if task.done { "[ ]" } else { "[x]" };
+            format!("{}{}{}", &text[..offset], mark, &text[offset + 3..])
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(line: usize, text: &str, done: bool) -> Task {
+        Task {
+            line,
+            text: text.to_string(),
+            done,
+        }
+    }
+
+    #[test]
+    fn test_should_parse_tasks() {
+        let content = "# Actions\n- [ ] Call Bob\n- [x] Book room\n  * [X] Nested\n- [] Not a task\n-[ ] Not either\n- [ ]\n+ [ ]tight\n```\n- [ ] In code\n```\n1. [ ] Numbered";
+
+        assert_eq!(
+            parse_tasks(content),
+            vec![
+                task(1, "Call Bob", false),
+                task(2, "Book room", true),
+                task(3, "Nested", true),
+                task(6, "", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_should_toggle_task() {
+        let content = "Intro\n  - [ ] Call Bob [ ]\n- [X] Done";
+
+        assert_eq!(
+            toggle_task(content, 1),
+            Ok("Intro\n  - [x] Call Bob [ ]\n- [X] Done".to_string())
+        );
+        assert_eq!(
+            toggle_task(content, 2),
+            Ok("Intro\n  - [ ] Call Bob [ ]\n- [ ] Done".to_string())
+        );
+        assert_eq!(
+            toggle_task(content, 0),
+            Err("No task on line 1".to_string())
+        );
+        assert_eq!(
+            toggle_task(content, 9),
+            Err("No task on line 10".to_string())
+        );
+    }
+}
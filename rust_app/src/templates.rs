@@ -0,0 +1,260 @@
+use crate::note::Note;
+use crate::note_taker::NoteTaker;
+
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+
+/// Templates are notes with this tag, so they are stored by every backend.
+pub const TEMPLATE_TAG: &str = "template";
+
+const BUILT_IN_VARIABLES: [&str; 3] = ["date", "time", "title"];
+
+/// Returns the indexes of the templates, ordered by name.
+pub fn get_templates(note_taker: &NoteTaker) -> Vec<usize> {
+    let mut templates: Vec<Note> = note_taker
+        .get_all_notes()
+        .into_iter()
+        .filter(|note| note.has_tag(TEMPLATE_TAG))
+        .collect();
+    templates.sort_by_key(|note| note.get_title().to_lowercase());
+    templates
+        .iter()
+        .filter_map(|note| note_taker.index_of(note))
+        .collect()
+}
+
+/// Returns the index of the template with a name, regardless of case.
+pub fn find_template(note_taker: &NoteTaker, name: &str) -> Option<usize> {
+    get_templates(note_taker).into_iter().find(|&index| {
+        note_taker
+            .get_note(index)
+            .get_title()
+            .eq_ignore_ascii_case(name.trim())
+    })
+}
+
+pub fn create_template(
+    note_taker: &mut NoteTaker,
+    name: &str,
+    body: &str,
+) -> Result<usize, String> {
+    if find_template(note_taker, name).is_some() {
+        return Err(format!("Template {} already exists", name.trim()));
+    }
+    let mut template = Note::new(name.to_string(), body.to_string())?;
+    template.add_tag(TEMPLATE_TAG)?;
+    note_taker.add_note(template.clone());
+    note_taker
+        .index_of(&template)
+        .ok_or("Note not found".to_string())
+}
+
+pub fn edit_template(note_taker: &mut NoteTaker, index: usize, body: &str) -> Result<(), String> {
+    let template = note_taker.get_note(index);
+    note_taker.edit_note(index, template.get_title().to_string(), body.to_string())
+}
+
+// Calls `replace` with the trimmed name of each `{{name}}` placeholder, and
+// keeps the placeholder as written when it returns `None`
+fn replace_variables(body: &str, mut replace: impl FnMut(&str) -> Option<String>) -> String {
+    let mut output = String::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + length];
+        output.push_str(&rest[..start]);
+        match replace(name.trim()) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&rest[start..start + length + 4]),
+        }
+        rest = &rest[start + length + 4..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Returns the variables of a template the user is asked for, in the order
+/// they first appear.
+pub fn get_prompts(body: &str) -> Vec<String> {
+    let mut prompts: Vec<String> = Vec::new();
+    replace_variables(body, |name| {
+        if !name.is_empty()
+            && !BUILT_IN_VARIABLES.contains(&name)
+            && !prompts.iter().any(|prompt| prompt == name)
+        {
+            prompts.push(name.to_string());
+        }
+        None
+    });
+    prompts
+}
+
+/// Replaces `{{date}}`, `{{time}}`, `{{title}}` and the variables in
+/// `values`, leaving other placeholders as they are.
+pub fn expand(
+    body: &str,
+    title: &str,
+    now: NaiveDateTime,
+    values: &HashMap<String, String>,
+) -> String {
+    replace_variables(body, |name| match name {
+        "date" => Some(now.format("%Y-%m-%d").to_string()),
+        "time" => Some(now.format("%H:%M").to_string()),
+        "title" => Some(title.to_string()),
+        _ => values.get(name).cloned(),
+    })
+}
+
+/// Creates a note from a template, asking `ask` for the value of each of its
+/// prompts. The note gets the template's tags, but for the template tag.
+/// Returns `None` if `ask` does.
+pub fn note_from_template(
+    template: &Note,
+    title: &str,
+    now: NaiveDateTime,
+    mut ask: impl FnMut(&str) -> Option<String>,
+) -> Result<Option<Note>, String> {
+    let mut values = HashMap::new();
+    for prompt in get_prompts(template.get_content()) {
+        let Some(value) = ask(&prompt) else {
+            return Ok(None);
+        };
+        values.insert(prompt, value.trim().to_string());
+    }
+    let title = expand(title, title, now, &values);
+    let content = expand(template.get_content(), &title, now, &values);
+    let mut note =
+        Note::new_with_date_time(title, content, now.format("%Y-%m-%d %H:%M").to_string())?;
+    for tag in template.get_tags() {
+        if tag != TEMPLATE_TAG {
+            note.add_tag(tag)?;
+        }
+    }
+    Ok(Some(note))
+}
+
+#[cfg(test)]
+use crate::persistence::file_persistence::FilePersistence;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2025-03-04 09:15", "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn setup_note_taker() -> NoteTaker {
+        let persistence = Box::new(FilePersistence::new("test_notes.json".to_string()));
+        NoteTaker::new(persistence)
+    }
+
+    #[test]
+    fn test_should_expand_variables() {
+        let values = HashMap::from([("who".to_string(), "Ana".to_string())]);
+
+        assert_eq!(
+            expand(
+                "# {{title}}\n{{ date }} {{time}} with {{who}}, {{unknown}} {{",
+                "Sync",
+                now(),
+                &values
+            ),
+            "# Sync\n2025-03-04 09:15 with Ana, {{unknown}} {{"
+        );
+        assert_eq!(
+            get_prompts("{{title}} {{Attendees}} {{date}} {{ Actions }} {{Attendees}} {{}}"),
+            vec!["Attendees", "Actions"]
+        );
+    }
+
+    #[test]
+    fn test_should_create_note_from_template() {
+        let mut note_taker = setup_note_taker();
+        let index = create_template(
+            &mut note_taker,
+            "Meeting",
+            "# {{title}} on {{date}}\nAttendees: {{attendees}}",
+        )
+        .unwrap();
+        note_taker.tag_note(index, "work").unwrap();
+        let template = note_taker.get_note(find_template(&note_taker, "meeting").unwrap());
+        let mut asked = Vec::new();
+
+        let note = note_from_template(&template, "Sync {{date}}", now(), |prompt| {
+            asked.push(prompt.to_string());
+            Some("Ana, Bo".to_string())
+        })
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(asked, vec!["attendees"]);
+        assert_eq!(note.get_title(), "Sync 2025-03-04");
+        assert_eq!(
+            note.get_content(),
+            "# Sync 2025-03-04 on 2025-03-04\nAttendees: Ana, Bo"
+        );
+        assert_eq!(note.get_date_time(), "2025-03-04 09:15");
+        assert_eq!(note.get_tags(), ["work"]);
+        assert_eq!(
+            note_from_template(&template, "Cancelled", now(), |_| None),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_should_list_create_and_edit_templates() {
+        let mut note_taker = setup_note_taker();
+        note_taker.add_note(Note::new("Plain".to_string(), String::new()).unwrap());
+        create_template(&mut note_taker, "Incident", "Impact:").unwrap();
+        create_template(&mut note_taker, "bug", "Steps:").unwrap();
+
+        assert!(create_template(&mut note_taker, "INCIDENT", "").is_err());
+        let names: Vec<String> = get_templates(&note_taker)
+            .iter()
+            .map(|&index| note_taker.get_note(index).get_title().to_string())
+            .collect();
+        assert_eq!(names, vec!["bug", "Incident"]);
+
+        let index = find_template(&note_taker, "incident").unwrap();
+        edit_template(&mut note_taker, index, "Impact: {{impact}}").unwrap();
+        let index = find_template(&note_taker, "incident").unwrap();
+        assert_eq!(
+            note_taker.get_note(index).get_content(),
+            "Impact: {{impact}}"
+        );
+        assert!(note_taker.get_note(index).has_tag(TEMPLATE_TAG));
+    }
+
+    #[test]
+    fn test_templates_should_not_be_listed_or_exported() {
+        let mut note_taker = setup_note_taker();
+        note_taker.add_note(Note::new("Plain".to_string(), String::new()).unwrap());
+        let index = create_template(&mut note_taker, "Deadline", "due:2025-03-07 17:00").unwrap();
+        let at = chrono::NaiveDate::from_ymd_opt(2025, 3, 4)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let reminder = crate::reminders::Reminder::new(at, crate::reminders::Repeat::Never);
+        note_taker
+            .set_reminder(index, Some(reminder.unwrap()))
+            .unwrap();
+
+        let titles: Vec<String> = note_taker
+            .get_notes()
+            .iter()
+            .map(|note| note.get_title().to_string())
+            .collect();
+        assert_eq!(titles, vec!["Plain"]);
+        let csv = crate::export::to_csv(&note_taker.get_notes());
+        assert!(!csv.contains("Deadline"));
+        let ics = crate::calendar::to_ics(&note_taker.get_all_notes(), chrono::Utc::now());
+        assert!(!ics.contains("Deadline"));
+        assert!(note_taker.get_reminders().is_empty());
+        assert!(note_taker.get_due_reminders(at).is_empty());
+        let plain = note_taker.get_by_title("Plain")[0];
+        assert!(note_taker.tag_note(plain, TEMPLATE_TAG).is_err());
+    }
+}
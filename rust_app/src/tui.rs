@@ -0,0 +1,544 @@
+use crate::note::Note;
+use crate::note_taker::NoteTaker;
+
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use std::time::Duration;
+
+const HELP: &str = "/ filter  a add  e edit  d delete  u undo  r redo  s save  q quit";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    AddTitle,
+    AddContent { title: String },
+    EditTitle { index: usize },
+    EditContent { index: usize, title: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Browse,
+    Filter,
+    Prompt { step: Step, input: String },
+    ConfirmDelete { index: usize },
+}
+
+/// The state of the terminal UI: the notes matching the filter, the selected
+/// one, and what the keys currently do.
+pub struct App<'a> {
+    note_taker: &'a mut NoteTaker,
+    filter: String,
+    selected: usize,
+    mode: Mode,
+    message: String,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    pub fn new(note_taker: &'a mut NoteTaker) -> Self {
+        App {
+            note_taker,
+            filter: String::new(),
+            selected: 0,
+            mode: Mode::Browse,
+            message: String::new(),
+            quit: false,
+        }
+    }
+
+    // Locked notes only match by title and tags, as their content is encrypted
+    fn matches(note: &Note, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        note.get_title().to_lowercase().contains(&filter)
+            || note.get_tags().iter().any(|tag| tag.contains(&filter))
+            || (!note.is_locked() && note.get_content().to_lowercase().contains(&filter))
+    }
+
+    /// Returns the notes listed, as in `NoteTaker::get_notes`, that match the filter.
+    fn visible(&self) -> Vec<Note> {
+        self.note_taker
+            .get_notes()
+            .into_iter()
+            .filter(|note| Self::matches(note, &self.filter))
+            .collect()
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let visible = self.visible();
+        visible
+            .get(self.selected)
+            .and_then(|note| self.note_taker.index_of(note))
+    }
+
+    fn clamp_selection(&mut self) {
+        let len = self.visible().len();
+        self.selected = self.selected.min(len.saturating_sub(1));
+    }
+
+    // Keeps a note selected after it moved in the list, e.g. when edited
+    fn select(&mut self, note: &Note) {
+        if let Some(position) = self.visible().iter().position(|n| n == note) {
+            self.selected = position;
+        }
+        self.clamp_selection();
+    }
+
+    fn changed(&mut self, message: &str) {
+        self.message = match self.note_taker.take_autosave_error() {
+            Some(e) => e,
+            None => message.to_string(),
+        };
+    }
+
+    fn save(&mut self) -> bool {
+        match self.note_taker.save() {
+            Ok(()) => {
+                self.message = "Saved.".to_string();
+                true
+            }
+            Err(e) => {
+                self.message = format!("Save failed: {}", e);
+                false
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        match self.mode.clone() {
+            Mode::Browse => self.handle_browse_key(key),
+            Mode::Filter => self.handle_filter_key(key),
+            Mode::Prompt { step, input } => self.handle_prompt_key(key, step, input),
+            Mode::ConfirmDelete { index } => {
+                if key.code == KeyCode::Char('y') {
+                    self.note_taker.delete_note(index);
+                    self.clamp_selection();
+                    self.changed("Note deleted.");
+                } else {
+                    self.message = "Delete cancelled.".to_string();
+                }
+                self.mode = Mode::Browse;
+            }
+        }
+    }
+
+    fn handle_browse_key(&mut self, key: KeyEvent) {
+        self.message.clear();
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected += 1;
+                self.clamp_selection();
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::PageDown => {
+                self.selected += 10;
+                self.clamp_selection();
+            }
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(10),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => {
+                self.selected = usize::MAX;
+                self.clamp_selection();
+            }
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.clamp_selection();
+            }
+            KeyCode::Char('a') => {
+                self.mode = Mode::Prompt {
+                    step: Step::AddTitle,
+                    input: String::new(),
+                }
+            }
+            KeyCode::Char('e') => match self.selected_index() {
+                Some(index) if self.note_taker.get_note(index).is_locked() => {
+                    self.message = "Note is locked".to_string();
+                }
+                Some(index) => {
+                    self.mode = Mode::Prompt {
+                        step: Step::EditTitle { index },
+                        input: self.note_taker.get_note(index).get_title().to_string(),
+                    }
+                }
+                None => {}
+            },
+            KeyCode::Char('d') => {
+                if let Some(index) = self.selected_index() {
+                    self.mode = Mode::ConfirmDelete { index };
+                }
+            }
+            KeyCode::Char('u') => match self.note_taker.undo() {
+                Ok(true) => {
+                    self.clamp_selection();
+                    self.changed("Undone.");
+                }
+                Ok(false) => self.message = "Nothing to undo.".to_string(),
+                Err(e) => self.message = e,
+            },
+            KeyCode::Char('r') => match self.note_taker.redo() {
+                Ok(true) => {
+                    self.clamp_selection();
+                    self.changed("Redone.");
+                }
+                Ok(false) => self.message = "Nothing to redo.".to_string(),
+                Err(e) => self.message = e,
+            },
+            KeyCode::Char('s') => {
+                self.save();
+            }
+            KeyCode::Char('q') if !self.note_taker.has_unsaved_changes() || self.save() => {
+                self.quit = true
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => self.mode = Mode::Browse,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.mode = Mode::Browse;
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) => self.filter.push(c),
+            _ => {}
+        }
+        self.selected = 0;
+    }
+
+    fn handle_prompt_key(&mut self, key: KeyEvent, step: Step, mut input: String) {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Browse;
+                self.message = "Cancelled.".to_string();
+                return;
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Enter => {
+                self.submit(step, input);
+                return;
+            }
+            _ => {}
+        }
+        self.mode = Mode::Prompt { step, input };
+    }
+
+    fn submit(&mut self, step: Step, input: String) {
+        self.mode = Mode::Browse;
+        match step {
+            Step::AddTitle => {
+                self.mode = Mode::Prompt {
+                    step: Step::AddContent { title: input },
+                    input: String::new(),
+                }
+            }
+            Step::AddContent { title } => match Note::new(title, input) {
+                Ok(note) => {
+                    self.note_taker.add_note(note.clone());
+                    self.select(&note);
+                    self.changed("Note added.");
+                }
+                Err(e) => self.message = e,
+            },
+            Step::EditTitle { index } => {
+                let content = self.note_taker.get_note(index).get_content().to_string();
+                self.mode = Mode::Prompt {
+                    step: Step::EditContent {
+                        index,
+                        title: input,
+                    },
+                    input: content,
+                }
+            }
+            Step::EditContent { index, title } => {
+                match self
+                    .note_taker
+                    .edit_note(index, title.clone(), input.clone())
+                {
+                    Ok(()) => {
+                        if let Some(&index) = self.note_taker.get_by_title(&title).first() {
+                            let note = self.note_taker.get_note(index);
+                            self.select(&note);
+                        }
+                        self.changed("Note edited.");
+                    }
+                    Err(e) => self.message = e,
+                }
+            }
+        }
+    }
+
+    fn preview(note: &Note) -> Text<'static> {
+        let mut lines = vec![
+            Line::from(note.get_title().to_string().bold()),
+            Line::from(note.get_date_time().dim()),
+        ];
+        if !note.get_tags().is_empty() {
+            lines.push(Line::from(
+                format!("Tags: {}", note.get_tags().join(", ")).dim(),
+            ));
+        }
+        let states: Vec<&str> = [
+            (note.is_pinned(), "Pinned"),
+            (note.is_archived(), "Archived"),
+            (note.is_locked(), "Locked"),
+        ]
+        .iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, state)| *state)
+        .collect();
+        if !states.is_empty() {
+            lines.push(Line::from(states.join(", ").italic()));
+        }
+        lines.push(Line::default());
+        if note.is_locked() {
+            lines.push(Line::from("(locked)".dim()));
+        } else {
+            lines.extend(
+                note.get_content()
+                    .lines()
+                    .map(|line| Line::from(line.to_string())),
+            );
+        }
+        Text::from(lines)
+    }
+
+    fn prompt_label(step: &Step) -> &'static str {
+        match step {
+            Step::AddTitle => "New title",
+            Step::AddContent { .. } => "Content",
+            Step::EditTitle { .. } => "Title",
+            Step::EditContent { .. } => "Content",
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+
+        let visible = self.visible();
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|note| {
+                let mut title = note.get_title().to_string();
+                if note.is_pinned() {
+                    title.insert_str(0, "* ");
+                }
+                if note.is_locked() {
+                    title.push_str(" (locked)");
+                }
+                ListItem::new(title)
+            })
+            .collect();
+        let list_title = if self.filter.is_empty() {
+            format!("Notes ({})", visible.len())
+        } else {
+            format!("Notes ({}) /{}", visible.len(), self.filter)
+        };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(list_title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        let preview = match visible.get(self.selected) {
+            Some(note) => Self::preview(note),
+            None => Text::from("No notes".dim()),
+        };
+        frame.render_widget(
+            Paragraph::new(preview)
+                .wrap(Wrap { trim: false })
+                .block(Block::default().borders(Borders::ALL).title("Preview")),
+            preview_area,
+        );
+
+        let save_state = if !self.note_taker.has_unsaved_changes() {
+            Span::from(" Saved ").green()
+        } else {
+            Span::from(" Unsaved ").yellow()
+        };
+        let line = match &self.mode {
+            Mode::Browse if self.message.is_empty() => {
+                Line::from(vec![save_state, Span::from(HELP).dim()])
+            }
+            Mode::Browse => Line::from(vec![save_state, Span::from(self.message.clone())]),
+            Mode::Filter => Line::from(vec![
+                save_state,
+                Span::from(format!("Filter: {}_", self.filter)),
+            ]),
+            Mode::Prompt { step, input } => Line::from(vec![
+                save_state,
+                Span::from(format!("{}: {}_", Self::prompt_label(step), input)),
+            ]),
+            Mode::ConfirmDelete { .. } => {
+                Line::from(vec![save_state, Span::from("Delete this note? (y/n)")])
+            }
+        };
+        frame.render_widget(Paragraph::new(line), status);
+    }
+}
+
+/// Runs the terminal UI until the user quits, saving on the way out.
+pub fn run(note_taker: &mut NoteTaker) -> Result<(), String> {
+    let mut terminal = ratatui::init();
+    let mut app = App::new(note_taker);
+    let result = (|| {
+        while !app.quit {
+            terminal
+                .draw(|frame| app.render(frame))
+                .map_err(|e| e.to_string())?;
+            // Waking up every second lets periodic autosave run while idle
+            if event::poll(Duration::from_secs(1)).map_err(|e| e.to_string())? {
+                if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+                    app.handle_key(key);
+                }
+            } else if let Err(e) = app.note_taker.autosave_if_due() {
+                app.message = format!("Autosave failed: {}", e);
+            }
+        }
+        Ok(())
+    })();
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+use crate::persistence::file_persistence::FilePersistence;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn setup_note_taker() -> NoteTaker {
+        let persistence = Box::new(FilePersistence::new("test_notes.json".to_string()));
+        let mut note_taker = NoteTaker::new(persistence);
+        for (title, content) in [
+            ("Groceries", "Milk and eggs"),
+            ("Rust", "Ownership"),
+            ("Secret", "Hidden milk"),
+        ] {
+            note_taker.add_note(
+                Note::new_with_date_time(
+                    title.to_string(),
+                    content.to_string(),
+                    "2025-01-01 10:00".to_string(),
+                )
+                .unwrap(),
+            );
+        }
+        let secret = note_taker.get_by_title("Secret")[0];
+        note_taker.lock_note(secret, "password").unwrap();
+        note_taker
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\x1b' => KeyCode::Esc,
+                '\x08' => KeyCode::Backspace,
+                c => KeyCode::Char(c),
+            };
+            app.handle_key(KeyEvent::from(code));
+        }
+    }
+
+    fn titles(app: &App) -> Vec<String> {
+        app.visible()
+            .iter()
+            .map(|note| note.get_title().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_should_filter_as_you_type() {
+        let mut note_taker = setup_note_taker();
+        let mut app = App::new(&mut note_taker);
+
+        press(&mut app, "/mil");
+        assert_eq!(titles(&app), vec!["Groceries"]);
+        press(&mut app, "\x08\x08\x08ru");
+        assert_eq!(titles(&app), vec!["Rust"]);
+        press(&mut app, "\x1b");
+        assert_eq!(titles(&app).len(), 3);
+    }
+
+    #[test]
+    fn test_should_add_edit_and_delete_notes() {
+        let mut note_taker = setup_note_taker();
+        let mut app = App::new(&mut note_taker);
+
+        press(&mut app, "aTodo\nCall Bob\n");
+        assert!(app.note_taker.has_unsaved_changes());
+        assert_eq!(app.visible()[app.selected].get_title(), "Todo");
+
+        press(&mut app, "e\x08\x08\x08\x08Done\n\n");
+        assert_eq!(app.visible()[app.selected].get_title(), "Done");
+        assert_eq!(app.visible()[app.selected].get_content(), "Call Bob");
+
+        press(&mut app, "dn");
+        assert_eq!(titles(&app).len(), 4);
+        press(&mut app, "dy");
+        assert_eq!(titles(&app), vec!["Groceries", "Rust", "Secret"]);
+
+        press(&mut app, "u");
+        assert_eq!(titles(&app).len(), 4);
+    }
+
+    #[test]
+    fn test_should_not_edit_locked_notes() {
+        let mut note_taker = setup_note_taker();
+        let mut app = App::new(&mut note_taker);
+
+        press(&mut app, "jje");
+        assert_eq!(app.mode, Mode::Browse);
+        assert_eq!(app.message, "Note is locked");
+    }
+
+    #[test]
+    fn test_should_render_list_preview_and_status() {
+        let mut note_taker = setup_note_taker();
+        let mut app = App::new(&mut note_taker);
+        press(&mut app, "j");
+        let mut terminal = Terminal::new(TestBackend::new(80, 10)).unwrap();
+
+        terminal.draw(|frame| app.render(frame)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let screen: String = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol().to_string())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(screen.contains("Notes (3)"));
+        assert!(screen.contains("Secret (locked)"));
+        assert!(screen.contains("Ownership"));
+        // The notes of the test were added but never saved
+        assert!(screen.contains("Unsaved"));
+    }
+}